@@ -0,0 +1,104 @@
+//! Resolves the RPC endpoint and signing keypairs from the Solana CLI config
+//! file, with command-line overrides taking precedence.
+
+use anyhow::{ anyhow, Context, Result };
+use solana_cli_config::ConfigInput;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{ read_keypair_file, Keypair },
+    signer::Signer,
+};
+
+pub struct Config {
+    pub rpc_url: String,
+    pub commitment: CommitmentConfig,
+    pub payer: Keypair,
+    pub admin: Keypair,
+    pub treasury: Option<Treasury>,
+    pub dry_run: bool,
+}
+
+/// The platform treasury, given either as a keypair file (required where the
+/// treasury must co-sign) or as a bare address.
+pub enum Treasury {
+    Signer(Keypair),
+    Address(Pubkey),
+}
+
+impl Treasury {
+    pub fn parse(value: &str) -> Result<Self> {
+        if let Ok(address) = value.parse::<Pubkey>() {
+            return Ok(Treasury::Address(address));
+        }
+        read_keypair(value).map(Treasury::Signer)
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        match self {
+            Treasury::Signer(keypair) => keypair.pubkey(),
+            Treasury::Address(address) => *address,
+        }
+    }
+}
+
+impl Config {
+    pub fn load(
+        config_file: Option<&str>,
+        url: Option<&str>,
+        keypair: Option<&str>,
+        admin: Option<&str>,
+        treasury: Option<&str>,
+        dry_run: bool
+    ) -> Result<Self> {
+        let cli_config = match config_file.or(solana_cli_config::CONFIG_FILE.as_deref()) {
+            Some(path) if std::path::Path::new(path).exists() => {
+                solana_cli_config::Config::load(path).map_err(|err|
+                    anyhow!("failed to read config {}: {}", path, err)
+                )?
+            }
+            Some(path) if config_file.is_some() => {
+                return Err(anyhow!("config file {} does not exist", path));
+            }
+            _ => solana_cli_config::Config::default(),
+        };
+
+        let (_, rpc_url) = ConfigInput::compute_json_rpc_url_setting(
+            url.unwrap_or_default(),
+            &cli_config.json_rpc_url
+        );
+        let (_, commitment) = ConfigInput::compute_commitment_config("", &cli_config.commitment);
+        let (_, keypair_path) = ConfigInput::compute_keypair_path_setting(
+            keypair.unwrap_or_default(),
+            &cli_config.keypair_path
+        );
+        let payer = read_keypair(&keypair_path)?;
+        let admin = match admin {
+            Some(path) => read_keypair(path)?,
+            None => payer.insecure_clone(),
+        };
+        let treasury = treasury.map(Treasury::parse).transpose()?;
+
+        Ok(Config { rpc_url, commitment, payer, admin, treasury, dry_run })
+    }
+
+    pub fn treasury(&self) -> Result<Pubkey> {
+        self.treasury
+            .as_ref()
+            .map(Treasury::pubkey)
+            .ok_or_else(|| anyhow!("this command needs --treasury"))
+    }
+
+    pub fn treasury_signer(&self) -> Result<&Keypair> {
+        match &self.treasury {
+            Some(Treasury::Signer(keypair)) => Ok(keypair),
+            _ => Err(anyhow!("this command needs --treasury as a keypair file")),
+        }
+    }
+}
+
+fn read_keypair(path: &str) -> Result<Keypair> {
+    read_keypair_file(path).map_err(|err| anyhow!("{}", err)).with_context(||
+        format!("failed to read keypair {}", path)
+    )
+}
@@ -0,0 +1,108 @@
+//! Human-readable dumps of decoded program accounts.
+
+use solana_sdk::pubkey::Pubkey;
+use staking_on_solana::state::{ PlatformInfo, PoolConfig, PoolState, UserInfo };
+use staking_on_solana::utils::{ PoolInfo, UserPosition };
+
+pub fn print_platform(address: &Pubkey, platform: &PlatformInfo) {
+    println!("Platform {}", address);
+    println!("  treasury:         {}", platform.treasury);
+    println!("  deploy fee:       {} lamports", platform.deploy_fee);
+    println!("  performance fee:  {} lamports", platform.performance_fee);
+    println!("  treasury share:   {} bps of pool fees", platform.treasury_fee_share);
+    println!("  reward fee:       {} bps of rewards", platform.reward_performance_fee);
+}
+
+pub fn print_pool(address: &Pubkey, config: &PoolConfig, state: &PoolState) {
+    println!("PoolConfig {}", address);
+    println!("  owner:                {}", config.owner);
+    println!("  pool id:              {}", config.pool_id);
+    println!("  duration:             {} days", config.duration);
+    println!("  start slot:           {}", config.start_slot);
+    println!("  end slot:             {}", config.end_slot);
+    println!("  reward per slot:      {}", config.reward_per_slot);
+    println!("  stake fee:            {} bps", config.stake_fee);
+    println!("  unstake fee:          {} bps", config.unstake_fee);
+    println!("  creator fees:         {}", config.creator_fees);
+    println!("  treasury fees:        {}", config.treasury_fees);
+    println!("  performance fee:      {:?}", config.performance_fee_model);
+    println!("  min stake:            {}", config.min_stake);
+    println!("  max per wallet:       {}", config.max_stake_per_wallet);
+    println!("  max total staked:     {}", config.max_total_staked);
+    match config.allowlist_root {
+        Some(root) => println!("  allowlist root:       {}", Pubkey::from(root)),
+        None => println!("  allowlist root:       none"),
+    }
+    match config.boost_collection {
+        Some(collection) => println!("  boost:                {} bps for {}", config.boost_multiplier, collection),
+        None => println!("  boost:                none"),
+    }
+    println!("  lock multiplier:      {} bps", config.lock_multiplier);
+    println!("  unbonding slots:      {}", config.unbonding_slots);
+    let forfeit = if config.forfeit_unvested { ", forfeited on unstake" } else { "" };
+    println!("  vesting slots:        {}{}", config.vesting_slots, forfeit);
+    println!("  referral share:       {} bps of {:?}", config.referral_share, config.referral_source);
+    println!("  stake mint:           {} ({} decimals)", config.stake_mint, config.stake_mint_decimals);
+    println!("  reward mint:          {} ({} decimals)", config.reward_mint, config.reward_mint_decimals);
+    println!("  stake vault:          {}", config.pool_stake_token_vault);
+    println!("  reward vault:         {}", config.pool_reward_token_vault);
+    println!("PoolState {}", config.state_addr);
+    println!("  total staked:         {}", state.total_staked);
+    println!("  total boosted:        {}", state.total_boosted);
+    println!("  total unbonding:      {}", state.total_unbonding);
+    println!("  last reward slot:     {}", state.last_reward_slot);
+    println!("  acc token per share:  {}", state.acc_token_per_share);
+    println!("  reward amount:        {}", state.reward_amount);
+    println!("  should total paid:    {}", state.should_total_paid);
+    println!("  paid rewards:         {}", state.paid_rewards);
+    println!("  total earned:         {}", state.total_earned);
+}
+
+pub fn print_pool_info(info: &PoolInfo) {
+    println!("As of slot {}", info.slot);
+    println!("  TVL:                  {}", info.tvl);
+    println!("  reward rate:          {} per slot", info.reward_rate);
+    println!("  remaining slots:      {}", info.remaining_slots);
+    println!("  funded slots:         {}", info.runway_slots);
+    println!("  reward shortfall:     {}", info.insufficient_rewards);
+}
+
+pub fn print_user(address: &Pubkey, user: &UserInfo, pending: Option<u64>) {
+    println!("UserInfo {}", address);
+    println!("  staked amount:  {}", user.staked_amount);
+    println!("  reward debt:    {}", user.reward_debt);
+    println!("  deposit slot:   {}", user.deposit_slot);
+    println!("  boosted amount: {}", user.boosted_amount);
+    if let Some(mint) = user.boost_mint {
+        println!("  boost NFT:      {} ({} bps)", mint, user.boost_multiplier);
+    }
+    if user.lock_multiplier > 0 {
+        println!("  locked until:   slot {} ({} bps)", user.lock_end_slot, user.lock_multiplier);
+    }
+    if user.vesting_amount > 0 || user.vested_amount > 0 {
+        println!("  vesting reward: {} until slot {}", user.vesting_amount, user.vesting_end_slot);
+        println!("  vested reward:  {} as of slot {}", user.vested_amount, user.vesting_start_slot);
+    }
+    if let Some(recipient) = user.reward_recipient {
+        println!("  rewards to:     {}", recipient);
+    }
+    if let Some(referrer) = user.referrer {
+        println!("  referrer:       {}", referrer);
+    }
+    if user.ticket_count > 0 {
+        println!("  tickets opened: {}", user.ticket_count);
+        println!("  tickets open:   {}", user.open_tickets);
+    }
+    if let Some(pending) = pending {
+        println!("  pending reward: {}", pending);
+    }
+}
+
+pub fn print_user_position(position: &UserPosition) {
+    println!("As of slot {}", position.slot);
+    println!("  locked:         {}", if position.locked { "yes" } else { "no" });
+    println!("  unstake fee:    {}", position.unstake_fee);
+    println!("  performance:    {} lamports", position.performance_fee);
+    println!("  reward fee:     {}", position.reward_fee);
+    println!("  referral cut:   {}", position.referral_fee);
+}
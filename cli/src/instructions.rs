@@ -0,0 +1,1231 @@
+//! Instruction builders for every `staking_on_solana` entrypoint the CLI drives.
+//!
+//! Builders are pure: they only derive addresses and encode account metas and
+//! instruction data, so they can be exercised without an RPC connection.
+
+use std::collections::BTreeMap;
+
+use anchor_lang::{ InstructionData, ToAccountMetas };
+use serde::Deserialize;
+use solana_sdk::{
+    instruction::{ AccountMeta, Instruction },
+    pubkey::{ ParsePubkeyError, Pubkey },
+    system_program,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use staking_on_solana::state::{
+    position_seed,
+    AllowlistProof,
+    PerformanceFeeModel,
+    PoolConfig,
+    ReferralSource,
+    BOOST_ESCROW_SEED,
+    FEE_EXEMPTION_SEED,
+    HOOK_MINT_ALLOWANCE_SEED,
+    REFERRER_SEED,
+    WITHDRAWAL_TICKET_SEED,
+};
+use staking_on_solana::utils::{ merkle, metadata::metadata_address };
+use staking_on_solana::{ accounts, instruction, ID as PROGRAM_ID };
+
+pub fn platform_address(treasury: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[treasury.as_ref()], &PROGRAM_ID).0
+}
+
+pub fn pool_config_address(pool_id: &str, creator: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[pool_id.as_bytes(), creator.as_ref()], &PROGRAM_ID).0
+}
+
+pub fn user_info_address(pool_config: &Pubkey, staker: &Pubkey, position: u32) -> Pubkey {
+    let seeds: &[&[u8]] = &[pool_config.as_ref(), staker.as_ref(), &position_seed(position)];
+    Pubkey::find_program_address(seeds, &PROGRAM_ID).0
+}
+
+/// The `index`th withdrawal ticket opened from a stake position.
+pub fn ticket_address(user_info: &Pubkey, index: u64) -> Pubkey {
+    let seeds: &[&[u8]] = &[WITHDRAWAL_TICKET_SEED, user_info.as_ref(), &index.to_le_bytes()];
+    Pubkey::find_program_address(seeds, &PROGRAM_ID).0
+}
+
+pub fn referrer_address(pool: &Pubkey, referrer: &Pubkey) -> Pubkey {
+    let seeds: &[&[u8]] = &[REFERRER_SEED, pool.as_ref(), referrer.as_ref()];
+    Pubkey::find_program_address(seeds, &PROGRAM_ID).0
+}
+
+pub fn hook_allowance_address(treasury: &Pubkey, mint: &Pubkey) -> Pubkey {
+    let platform = platform_address(treasury);
+    let seeds: &[&[u8]] = &[HOOK_MINT_ALLOWANCE_SEED, platform.as_ref(), mint.as_ref()];
+    Pubkey::find_program_address(seeds, &PROGRAM_ID).0
+}
+
+/// The PDA the program signs its `emit_cpi!` event instructions with.
+pub fn event_authority_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"__event_authority"], &PROGRAM_ID).0
+}
+
+/// `owner`'s associated token account for a `mint` of `token_program`.
+pub fn ata(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(owner, mint, token_program)
+}
+
+/// Creates `owner`'s associated token account for `mint` unless it already exists.
+/// The treasury's reward token account, which only reward-share pools take.
+pub fn treasury_reward_account(treasury: &Pubkey, config: &PoolConfig) -> Option<Pubkey> {
+    let address = ata(treasury, &config.reward_mint, &config.token_program);
+    (config.performance_fee_model == PerformanceFeeModel::RewardShare).then_some(address)
+}
+
+pub fn fee_exemption_address(scope: &Pubkey, wallet: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[FEE_EXEMPTION_SEED, scope.as_ref(), wallet.as_ref()], &PROGRAM_ID).0
+}
+
+/// A user's fee exemption accounts, passed only when they exist.
+#[derive(Default)]
+pub struct FeeExemptions {
+    pub pool: Option<Pubkey>,
+    pub platform: Option<Pubkey>,
+}
+
+/// Wallets a stake position points to, as recorded in its `UserInfo`.
+#[derive(Default, Clone, Copy)]
+pub struct PositionLinks {
+    /// For `stake`, the referrer to record if the position has none yet
+    pub referrer: Option<Pubkey>,
+    pub reward_recipient: Option<Pubkey>,
+}
+
+impl PositionLinks {
+    fn referrer_info(&self, pool: &Pubkey) -> Option<Pubkey> {
+        self.referrer.map(|referrer| referrer_address(pool, &referrer))
+    }
+
+    /// The wallet rewards of `user`'s position are paid to
+    pub fn reward_owner(&self, user: &Pubkey) -> Pubkey {
+        self.reward_recipient.unwrap_or(*user)
+    }
+
+    fn reward_account(&self, user: &Pubkey, config: &PoolConfig) -> Pubkey {
+        ata(&self.reward_owner(user), &config.reward_mint, &config.token_program)
+    }
+}
+
+pub fn create_ata(payer: &Pubkey, owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Instruction {
+    create_associated_token_account_idempotent(payer, owner, mint, token_program)
+}
+
+pub fn allow_hook_mint(treasury: &Pubkey, mint: &Pubkey, allowed: bool) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::AllowHookMint {
+            allowance: hook_allowance_address(treasury, mint),
+            platform: platform_address(treasury),
+            treasury: *treasury,
+            mint: *mint,
+            system_program: system_program::ID,
+        }).to_account_metas(None),
+        data: (instruction::AllowHookMint { allowed }).data(),
+    }
+}
+
+pub fn set_pool_fee_exemption(owner: &Pubkey, pool: &Pubkey, wallet: &Pubkey, discount: u16) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::SetPoolFeeExemption {
+            fee_exemption: fee_exemption_address(pool, wallet),
+            pool_config_account: *pool,
+            owner: *owner,
+            system_program: system_program::ID,
+        }).to_account_metas(None),
+        data: (instruction::SetPoolFeeExemption { wallet: *wallet, discount }).data(),
+    }
+}
+
+pub fn set_platform_fee_exemption(treasury: &Pubkey, wallet: &Pubkey, discount: u16) -> Instruction {
+    let platform = platform_address(treasury);
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::SetPlatformFeeExemption {
+            fee_exemption: fee_exemption_address(&platform, wallet),
+            platform,
+            treasury: *treasury,
+            system_program: system_program::ID,
+        }).to_account_metas(None),
+        data: (instruction::SetPlatformFeeExemption { wallet: *wallet, discount }).data(),
+    }
+}
+
+pub fn initialize(
+    admin: &Pubkey,
+    treasury: &Pubkey,
+    deploy_fee: u64,
+    performance_fee: u64,
+    treasury_fee_share: u16,
+    reward_performance_fee: u16
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::Initialize {
+            platform: platform_address(treasury),
+            admin: *admin,
+            treasury: *treasury,
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+        }).to_account_metas(None),
+        data: (instruction::Initialize {
+            deploy_fee,
+            performance_fee,
+            treasury_fee_share,
+            reward_performance_fee,
+        }).data(),
+    }
+}
+
+pub struct CreatePoolArgs {
+    pub pool_id: String,
+    pub stake_fee: u16,
+    pub unstake_fee: u16,
+    pub initial_funding: u64,
+    pub reward_per_slot: u64,
+    pub duration: u16,
+    /// Token program owning both mints
+    pub token_program: Pubkey,
+    /// Allowances of mints with a transfer hook
+    pub stake_hook_allowance: Option<Pubkey>,
+    pub reward_hook_allowance: Option<Pubkey>,
+    pub performance_fee_model: PerformanceFeeModel,
+}
+
+/// Pool vaults are the vault authority's associated token accounts, matching
+/// the layout used by the TypeScript test-suite.
+pub fn create_pool(
+    creator: &Pubkey,
+    admin: &Pubkey,
+    treasury: &Pubkey,
+    pool_state: &Pubkey,
+    stake_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    args: CreatePoolArgs
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::CreatePool {
+            pool_config_account: pool_config_address(&args.pool_id, creator),
+            pool_state_account: *pool_state,
+            platform: platform_address(treasury),
+            creator: *creator,
+            treasury: *treasury,
+            stake_mint: *stake_mint,
+            reward_mint: *reward_mint,
+            pool_stake_token_vault: ata(admin, stake_mint, &args.token_program),
+            pool_reward_token_vault: ata(admin, reward_mint, &args.token_program),
+            creator_reward_token_vault: ata(creator, reward_mint, &args.token_program),
+            stake_hook_allowance: args.stake_hook_allowance,
+            reward_hook_allowance: args.reward_hook_allowance,
+            system_program: system_program::ID,
+            token_program: args.token_program,
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::CreatePool {
+            pool_id: args.pool_id,
+            stake_fee: args.stake_fee,
+            unstake_fee: args.unstake_fee,
+            initial_funding: args.initial_funding,
+            reward_per_slot: args.reward_per_slot,
+            duration: args.duration,
+            performance_fee_model: args.performance_fee_model,
+        }).data(),
+    }
+}
+
+pub fn start_reward(deployer: &Pubkey, pool: &Pubkey, config: &PoolConfig) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::StartReward {
+            deployer: *deployer,
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+            token_program: config.token_program,
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::StartReward {}).data(),
+    }
+}
+
+pub fn set_stake_limits(
+    owner: &Pubkey,
+    pool: &Pubkey,
+    min_stake: u64,
+    max_stake_per_wallet: u64,
+    max_total_staked: u64
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::SetStakeLimits {
+            pool_config_account: *pool,
+            owner: *owner,
+        }).to_account_metas(None),
+        data: (instruction::SetStakeLimits { min_stake, max_stake_per_wallet, max_total_staked }).data(),
+    }
+}
+
+pub fn set_allowlist(owner: &Pubkey, pool: &Pubkey, allowlist_root: Option<[u8; 32]>) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::SetAllowlist {
+            pool_config_account: *pool,
+            owner: *owner,
+        }).to_account_metas(None),
+        data: (instruction::SetAllowlist { allowlist_root }).data(),
+    }
+}
+
+pub fn set_boost_collection(
+    owner: &Pubkey,
+    pool: &Pubkey,
+    boost_collection: Option<Pubkey>,
+    boost_multiplier: u16
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::SetBoostCollection {
+            pool_config_account: *pool,
+            owner: *owner,
+        }).to_account_metas(None),
+        data: (instruction::SetBoostCollection { boost_collection, boost_multiplier }).data(),
+    }
+}
+
+pub fn boost_escrow_address(pool_config: &Pubkey, user: &Pubkey, position: u32) -> Pubkey {
+    let user_info = user_info_address(pool_config, user, position);
+    Pubkey::find_program_address(&[BOOST_ESCROW_SEED, user_info.as_ref()], &PROGRAM_ID).0
+}
+
+pub fn lock_boost_nft(
+    user: &Pubkey,
+    pool: &Pubkey,
+    config: &PoolConfig,
+    nft_mint: &Pubkey,
+    nft_token_program: &Pubkey,
+    position: u32
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::LockBoostNft {
+            user: *user,
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+            user_info: user_info_address(pool, user, position),
+            nft_mint: *nft_mint,
+            nft_metadata: metadata_address(nft_mint),
+            user_nft_account: ata(user, nft_mint, nft_token_program),
+            boost_escrow: boost_escrow_address(pool, user, position),
+            nft_token_program: *nft_token_program,
+            system_program: system_program::ID,
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::LockBoostNft { position }).data(),
+    }
+}
+
+pub fn unlock_boost_nft(
+    user: &Pubkey,
+    pool: &Pubkey,
+    config: &PoolConfig,
+    nft_mint: &Pubkey,
+    nft_token_program: &Pubkey,
+    position: u32
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::UnlockBoostNft {
+            user: *user,
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+            user_info: user_info_address(pool, user, position),
+            nft_mint: *nft_mint,
+            user_nft_account: ata(user, nft_mint, nft_token_program),
+            boost_escrow: boost_escrow_address(pool, user, position),
+            nft_token_program: *nft_token_program,
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::UnlockBoostNft { position }).data(),
+    }
+}
+
+pub fn set_lock_multiplier(owner: &Pubkey, pool: &Pubkey, lock_multiplier: u16) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::SetLockMultiplier {
+            pool_config_account: *pool,
+            owner: *owner,
+        }).to_account_metas(None),
+        data: (instruction::SetLockMultiplier { lock_multiplier }).data(),
+    }
+}
+
+pub fn extend_lock(
+    user: &Pubkey,
+    pool: &Pubkey,
+    config: &PoolConfig,
+    lock_weeks: u16,
+    position: u32
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::ExtendLock {
+            user: *user,
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+            user_info: user_info_address(pool, user, position),
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::ExtendLock { lock_weeks, position }).data(),
+    }
+}
+
+pub fn expire_lock(user: &Pubkey, pool: &Pubkey, config: &PoolConfig, position: u32) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::ExpireLock {
+            user: *user,
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+            user_info: user_info_address(pool, user, position),
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::ExpireLock { position }).data(),
+    }
+}
+
+pub fn close_user_info(user: &Pubkey, pool: &Pubkey, config: &PoolConfig, position: u32) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::CloseUserInfo {
+            user: *user,
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+            user_info: user_info_address(pool, user, position),
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::CloseUserInfo { position }).data(),
+    }
+}
+
+pub fn update_pool(pool: &Pubkey, config: &PoolConfig) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::UpdatePool {
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::UpdatePool {}).data(),
+    }
+}
+
+pub fn update_pools(pools: &[(Pubkey, PoolConfig)]) -> Instruction {
+    let mut accounts = (accounts::UpdatePools {
+        event_authority: event_authority_address(),
+        program: PROGRAM_ID,
+    }).to_account_metas(None);
+    // Each pool's config, then its state account
+    for (pool, config) in pools {
+        accounts.push(AccountMeta::new_readonly(*pool, false));
+        accounts.push(AccountMeta::new(config.state_addr, false));
+    }
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts,
+        data: (instruction::UpdatePools {}).data(),
+    }
+}
+
+pub fn set_vesting(owner: &Pubkey, pool: &Pubkey, vesting_slots: u64, forfeit_unvested: bool) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::SetVesting {
+            pool_config_account: *pool,
+            owner: *owner,
+        }).to_account_metas(None),
+        data: (instruction::SetVesting { vesting_slots, forfeit_unvested }).data(),
+    }
+}
+
+pub fn set_referral(
+    owner: &Pubkey,
+    pool: &Pubkey,
+    referral_share: u16,
+    referral_source: ReferralSource
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::SetReferral {
+            pool_config_account: *pool,
+            owner: *owner,
+        }).to_account_metas(None),
+        data: (instruction::SetReferral { referral_share, referral_source }).data(),
+    }
+}
+
+pub fn set_reward_recipient(
+    user: &Pubkey,
+    pool: &Pubkey,
+    position: u32,
+    recipient: Option<Pubkey>
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::SetRewardRecipient {
+            user: *user,
+            pool_config_account: *pool,
+            user_info: user_info_address(pool, user, position),
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::SetRewardRecipient { position, recipient }).data(),
+    }
+}
+
+pub fn claim_referral(referrer: &Pubkey, admin: &Pubkey, pool: &Pubkey, config: &PoolConfig) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::ClaimReferral {
+            referrer: *referrer,
+            admin: *admin,
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+            referrer_info: referrer_address(pool, referrer),
+            referrer_stake_token_vault: ata(referrer, &config.stake_mint, &config.token_program),
+            referrer_reward_token_vault: ata(referrer, &config.reward_mint, &config.token_program),
+            pool_stake_token_vault: config.pool_stake_token_vault,
+            pool_reward_token_vault: config.pool_reward_token_vault,
+            stake_mint: config.stake_mint,
+            reward_mint: config.reward_mint,
+            token_program: config.token_program,
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::ClaimReferral {}).data(),
+    }
+}
+
+pub fn set_unbonding_period(owner: &Pubkey, pool: &Pubkey, unbonding_slots: u64) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::SetUnbondingPeriod {
+            pool_config_account: *pool,
+            owner: *owner,
+        }).to_account_metas(None),
+        data: (instruction::SetUnbondingPeriod { unbonding_slots }).data(),
+    }
+}
+
+pub fn withdraw_ticket(
+    user: &Pubkey,
+    admin: &Pubkey,
+    pool: &Pubkey,
+    config: &PoolConfig,
+    withdrawal_ticket: &Pubkey,
+    position: u32
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::WithdrawTicket {
+            user: *user,
+            admin: *admin,
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+            withdrawal_ticket: *withdrawal_ticket,
+            user_info: user_info_address(pool, user, position),
+            user_stake_token_vault: Some(ata(user, &config.stake_mint, &config.token_program)),
+            pool_stake_token_vault: config.pool_stake_token_vault,
+            stake_mint: config.stake_mint,
+            wsol_account: None,
+            token_program: config.token_program,
+            system_program: system_program::ID,
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::WithdrawTicket {}).data(),
+    }
+}
+
+pub fn cancel_ticket(
+    user: &Pubkey,
+    pool: &Pubkey,
+    config: &PoolConfig,
+    withdrawal_ticket: &Pubkey,
+    position: u32
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::CancelTicket {
+            user: *user,
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+            withdrawal_ticket: *withdrawal_ticket,
+            user_info: user_info_address(pool, user, position),
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::CancelTicket {}).data(),
+    }
+}
+
+pub fn stop_reward(deployer: &Pubkey, pool: &Pubkey, config: &PoolConfig) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::StopReward {
+            deployer: *deployer,
+            pool_config_account: *pool,
+            pool_reward_token_vault: config.pool_reward_token_vault,
+            deployer_reward_token_vault: ata(deployer, &config.reward_mint, &config.token_program),
+            pool_state_account: config.state_addr,
+            reward_mint: config.reward_mint,
+            token_program: config.token_program,
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::StopReward {}).data(),
+    }
+}
+
+pub fn close_pool(owner: &Pubkey, admin: &Pubkey, pool: &Pubkey, config: &PoolConfig) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::ClosePool {
+            owner: *owner,
+            admin: *admin,
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+            pool_reward_token_vault: config.pool_reward_token_vault,
+            owner_reward_token_vault: ata(owner, &config.reward_mint, &config.token_program),
+            reward_mint: config.reward_mint,
+            token_program: config.token_program,
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::ClosePool {}).data(),
+    }
+}
+
+pub fn fund_pool(funder: &Pubkey, pool: &Pubkey, config: &PoolConfig, amount: u64) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::FundPool {
+            funder: *funder,
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+            funder_reward_token_vault: ata(funder, &config.reward_mint, &config.token_program),
+            pool_reward_token_vault: config.pool_reward_token_vault,
+            reward_mint: config.reward_mint,
+            token_program: config.token_program,
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::FundPool { amount }).data(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn stake(
+    staker: &Pubkey,
+    admin: &Pubkey,
+    treasury: &Pubkey,
+    pool: &Pubkey,
+    config: &PoolConfig,
+    exemptions: &FeeExemptions,
+    stake_amount: u64,
+    min_received: u64,
+    allowlist_proof: Option<AllowlistProof>,
+    lock_weeks: u16,
+    position: u32,
+    links: PositionLinks
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::Stake {
+            user_info: user_info_address(pool, staker, position),
+            referrer_info: links.referrer_info(pool),
+            staker: *staker,
+            admin: *admin,
+            treasury: *treasury,
+            platform: platform_address(treasury),
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+            user_stake_token_vault: Some(ata(staker, &config.stake_mint, &config.token_program)),
+            user_reward_token_vault: Some(links.reward_account(staker, config)),
+            pool_stake_token_vault: config.pool_stake_token_vault,
+            pool_reward_token_vault: config.pool_reward_token_vault,
+            creator_stake_token_vault: ata(&config.owner, &config.stake_mint, &config.token_program),
+            treasury_stake_token_vault: ata(treasury, &config.stake_mint, &config.token_program),
+            treasury_reward_token_vault: treasury_reward_account(treasury, config),
+            stake_mint: config.stake_mint,
+            reward_mint: config.reward_mint,
+            wsol_account: None,
+            pool_fee_exemption: exemptions.pool,
+            platform_fee_exemption: exemptions.platform,
+            system_program: system_program::ID,
+            token_program: config.token_program,
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::Stake {
+            stake_amount,
+            min_received,
+            allowlist_proof,
+            lock_weeks,
+            position,
+            referrer: links.referrer,
+        }).data(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn unstake(
+    user: &Pubkey,
+    admin: &Pubkey,
+    treasury: &Pubkey,
+    pool: &Pubkey,
+    config: &PoolConfig,
+    exemptions: &FeeExemptions,
+    unstake_amount: u64,
+    position: u32,
+    withdrawal_ticket: Option<Pubkey>,
+    links: PositionLinks
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: unstake_accounts(user, admin, treasury, pool, config, exemptions, position, withdrawal_ticket, links),
+        data: (instruction::Unstake { unstake_amount, position }).data(),
+    }
+}
+
+/// Unstakes a whole position and pays its reward, then closes it when `close`
+/// is set; takes the accounts of `unstake`.
+#[allow(clippy::too_many_arguments)]
+pub fn exit(
+    user: &Pubkey,
+    admin: &Pubkey,
+    treasury: &Pubkey,
+    pool: &Pubkey,
+    config: &PoolConfig,
+    exemptions: &FeeExemptions,
+    min_withdrawn: u64,
+    position: u32,
+    close: bool,
+    withdrawal_ticket: Option<Pubkey>,
+    links: PositionLinks
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: unstake_accounts(user, admin, treasury, pool, config, exemptions, position, withdrawal_ticket, links),
+        data: (instruction::Exit { min_withdrawn, position, close }).data(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn unstake_accounts(
+    user: &Pubkey,
+    admin: &Pubkey,
+    treasury: &Pubkey,
+    pool: &Pubkey,
+    config: &PoolConfig,
+    exemptions: &FeeExemptions,
+    position: u32,
+    withdrawal_ticket: Option<Pubkey>,
+    links: PositionLinks
+) -> Vec<AccountMeta> {
+    (accounts::Unstake {
+        user: *user,
+        admin: *admin,
+        treasury: *treasury,
+        pool_config_account: *pool,
+        pool_state_account: config.state_addr,
+        platform: platform_address(treasury),
+        user_info: user_info_address(pool, user, position),
+        withdrawal_ticket,
+        referrer_info: links.referrer_info(pool),
+        user_stake_token_vault: Some(ata(user, &config.stake_mint, &config.token_program)),
+        user_reward_token_vault: Some(links.reward_account(user, config)),
+        pool_stake_token_vault: config.pool_stake_token_vault,
+        pool_reward_token_vault: config.pool_reward_token_vault,
+        creator_stake_token_vault: ata(&config.owner, &config.stake_mint, &config.token_program),
+        treasury_stake_token_vault: ata(treasury, &config.stake_mint, &config.token_program),
+        treasury_reward_token_vault: treasury_reward_account(treasury, config),
+        stake_mint: config.stake_mint,
+        reward_mint: config.reward_mint,
+        wsol_account: None,
+        token_program: config.token_program,
+        pool_fee_exemption: exemptions.pool,
+        platform_fee_exemption: exemptions.platform,
+        system_program: system_program::ID,
+        event_authority: event_authority_address(),
+        program: PROGRAM_ID,
+    }).to_account_metas(None)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn claim_reward(
+    claimer: &Pubkey,
+    admin: &Pubkey,
+    treasury: &Pubkey,
+    pool: &Pubkey,
+    config: &PoolConfig,
+    exemptions: &FeeExemptions,
+    position: u32,
+    links: PositionLinks
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: claim_reward_accounts(claimer, admin, treasury, pool, config, exemptions, position, links),
+        data: (instruction::ClaimReward { position }).data(),
+    }
+}
+
+/// Pays out a position's vested reward; takes the accounts of `claim_reward`.
+#[allow(clippy::too_many_arguments)]
+pub fn withdraw_vested(
+    claimer: &Pubkey,
+    admin: &Pubkey,
+    treasury: &Pubkey,
+    pool: &Pubkey,
+    config: &PoolConfig,
+    exemptions: &FeeExemptions,
+    position: u32,
+    links: PositionLinks
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: claim_reward_accounts(claimer, admin, treasury, pool, config, exemptions, position, links),
+        data: (instruction::WithdrawVested { position }).data(),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn claim_reward_accounts(
+    claimer: &Pubkey,
+    admin: &Pubkey,
+    treasury: &Pubkey,
+    pool: &Pubkey,
+    config: &PoolConfig,
+    exemptions: &FeeExemptions,
+    position: u32,
+    links: PositionLinks
+) -> Vec<AccountMeta> {
+    (accounts::ClaimReward {
+        claimer: *claimer,
+        admin: *admin,
+        treasury: *treasury,
+        user_info: user_info_address(pool, claimer, position),
+        referrer_info: links.referrer_info(pool),
+        pool_config_account: *pool,
+        pool_state_account: config.state_addr,
+        platform: platform_address(treasury),
+        user_reward_token_vault: Some(links.reward_account(claimer, config)),
+        pool_reward_token_vault: config.pool_reward_token_vault,
+        treasury_reward_token_vault: treasury_reward_account(treasury, config),
+        reward_mint: config.reward_mint,
+        wsol_account: None,
+        token_program: config.token_program,
+        pool_fee_exemption: exemptions.pool,
+        platform_fee_exemption: exemptions.platform,
+        system_program: system_program::ID,
+        event_authority: event_authority_address(),
+        program: PROGRAM_ID,
+    }).to_account_metas(None)
+}
+
+/// The positions must share `links`.
+#[allow(clippy::too_many_arguments)]
+pub fn claim_all(
+    claimer: &Pubkey,
+    admin: &Pubkey,
+    treasury: &Pubkey,
+    pool: &Pubkey,
+    config: &PoolConfig,
+    exemptions: &FeeExemptions,
+    positions: &[u32],
+    links: PositionLinks
+) -> Instruction {
+    let mut accounts = (accounts::ClaimAll {
+        claimer: *claimer,
+        admin: *admin,
+        treasury: *treasury,
+        pool_config_account: *pool,
+        pool_state_account: config.state_addr,
+        platform: platform_address(treasury),
+        referrer_info: links.referrer_info(pool),
+        user_reward_token_vault: Some(links.reward_account(claimer, config)),
+        pool_reward_token_vault: config.pool_reward_token_vault,
+        treasury_reward_token_vault: treasury_reward_account(treasury, config),
+        reward_mint: config.reward_mint,
+        wsol_account: None,
+        token_program: config.token_program,
+        pool_fee_exemption: exemptions.pool,
+        platform_fee_exemption: exemptions.platform,
+        system_program: system_program::ID,
+        event_authority: event_authority_address(),
+        program: PROGRAM_ID,
+    }).to_account_metas(None);
+    // The positions' stake accounts, in order
+    for &position in positions {
+        accounts.push(AccountMeta::new(user_info_address(pool, claimer, position), false));
+    }
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts,
+        data: (instruction::ClaimAll { positions: positions.to_vec() }).data(),
+    }
+}
+
+/// Raydium pool keys, in the JSON layout produced by the Raydium SDK.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmmKeys {
+    #[serde(deserialize_with = "de_pubkey")]
+    pub amm_program: Pubkey,
+    #[serde(deserialize_with = "de_pubkey")]
+    pub id: Pubkey,
+    #[serde(deserialize_with = "de_pubkey")]
+    pub authority: Pubkey,
+    #[serde(deserialize_with = "de_pubkey")]
+    pub open_orders: Pubkey,
+    #[serde(deserialize_with = "de_pubkey")]
+    pub target_orders: Pubkey,
+    #[serde(deserialize_with = "de_pubkey")]
+    pub base_vault: Pubkey,
+    #[serde(deserialize_with = "de_pubkey")]
+    pub quote_vault: Pubkey,
+    #[serde(deserialize_with = "de_pubkey")]
+    pub market_program_id: Pubkey,
+    #[serde(deserialize_with = "de_pubkey")]
+    pub market_id: Pubkey,
+    #[serde(deserialize_with = "de_pubkey")]
+    pub market_bids: Pubkey,
+    #[serde(deserialize_with = "de_pubkey")]
+    pub market_asks: Pubkey,
+    #[serde(deserialize_with = "de_pubkey")]
+    pub market_event_queue: Pubkey,
+    #[serde(deserialize_with = "de_pubkey")]
+    pub market_base_vault: Pubkey,
+    #[serde(deserialize_with = "de_pubkey")]
+    pub market_quote_vault: Pubkey,
+    #[serde(deserialize_with = "de_pubkey")]
+    pub market_authority: Pubkey,
+}
+
+impl AmmKeys {
+    /// The swap accounts are only read when the stake and reward mints differ,
+    /// so single-token pools get fresh addresses that are safe to mark writable.
+    pub fn placeholder() -> Self {
+        Self {
+            amm_program: Pubkey::new_unique(),
+            id: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            open_orders: Pubkey::new_unique(),
+            target_orders: Pubkey::new_unique(),
+            base_vault: Pubkey::new_unique(),
+            quote_vault: Pubkey::new_unique(),
+            market_program_id: Pubkey::new_unique(),
+            market_id: Pubkey::new_unique(),
+            market_bids: Pubkey::new_unique(),
+            market_asks: Pubkey::new_unique(),
+            market_event_queue: Pubkey::new_unique(),
+            market_base_vault: Pubkey::new_unique(),
+            market_quote_vault: Pubkey::new_unique(),
+            market_authority: Pubkey::new_unique(),
+        }
+    }
+}
+
+/// A pool allowlist, as a JSON object of wallet addresses to allocations.
+/// Entries are ordered by wallet so everyone with the file derives the same root.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "BTreeMap<String, u64>")]
+pub struct Allowlist {
+    entries: Vec<(Pubkey, u64)>,
+}
+
+impl TryFrom<BTreeMap<String, u64>> for Allowlist {
+    type Error = ParsePubkeyError;
+
+    fn try_from(map: BTreeMap<String, u64>) -> Result<Self, Self::Error> {
+        let mut entries = map
+            .into_iter()
+            .map(|(wallet, allocation)| wallet.parse().map(|wallet: Pubkey| (wallet, allocation)))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|(wallet, _)| *wallet);
+        Ok(Self { entries })
+    }
+}
+
+impl Allowlist {
+    fn leaves(&self) -> Vec<[u8; 32]> {
+        self.entries
+            .iter()
+            .map(|(wallet, allocation)| merkle::allowlist_leaf(wallet, *allocation))
+            .collect()
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        merkle::root_and_proof(&self.leaves(), 0).0
+    }
+
+    /// The proof of `wallet`'s allocation, if it is on the list.
+    pub fn proof(&self, wallet: &Pubkey) -> Option<AllowlistProof> {
+        let index = self.entries.iter().position(|(entry, _)| entry == wallet)?;
+        Some(AllowlistProof {
+            allocation: self.entries[index].1,
+            proof: merkle::root_and_proof(&self.leaves(), index).1,
+        })
+    }
+}
+
+fn de_pubkey<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    value.parse().map_err(serde::de::Error::custom)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn compound_reward(
+    user: &Pubkey,
+    admin: &Pubkey,
+    treasury: &Pubkey,
+    pool: &Pubkey,
+    config: &PoolConfig,
+    exemptions: &FeeExemptions,
+    amm: &AmmKeys,
+    position: u32,
+    links: PositionLinks
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::CompoundReward {
+            user: *user,
+            admin: *admin,
+            treasury: *treasury,
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+            user_info: user_info_address(pool, user, position),
+            referrer_info: links.referrer_info(pool),
+            platform: platform_address(treasury),
+            pool_stake_token_vault: config.pool_stake_token_vault,
+            pool_reward_token_vault: config.pool_reward_token_vault,
+            treasury_reward_token_vault: treasury_reward_account(treasury, config),
+            reward_mint: config.reward_mint,
+            token_program: config.token_program,
+            amm_program: amm.amm_program,
+            amm: amm.id,
+            amm_authority: amm.authority,
+            amm_open_orders: amm.open_orders,
+            amm_target_orders: amm.target_orders,
+            pool_coin_token_account: amm.base_vault,
+            pool_pc_token_account: amm.quote_vault,
+            serum_program: amm.market_program_id,
+            serum_market: amm.market_id,
+            serum_bids: amm.market_bids,
+            serum_asks: amm.market_asks,
+            serum_event_queue: amm.market_event_queue,
+            serum_coin_vault_account: amm.market_base_vault,
+            serum_pc_vault_account: amm.market_quote_vault,
+            serum_vault_signer: amm.market_authority,
+            spl_token_program: spl_token::ID,
+            pool_fee_exemption: exemptions.pool,
+            platform_fee_exemption: exemptions.platform,
+            system_program: system_program::ID,
+            event_authority: event_authority_address(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None),
+        data: (instruction::CompoundReward { position }).data(),
+    }
+}
+
+pub fn pending_reward(user: &Pubkey, pool: &Pubkey, config: &PoolConfig, position: u32) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::PendingReward {
+            user_info: user_info_address(pool, user, position),
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+        }).to_account_metas(None),
+        data: (instruction::PendingReward {}).data(),
+    }
+}
+
+pub fn get_pool_info(pool: &Pubkey, config: &PoolConfig) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::GetPoolInfo {
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+        }).to_account_metas(None),
+        data: (instruction::GetPoolInfo {}).data(),
+    }
+}
+
+pub fn get_user_position(
+    user: &Pubkey,
+    treasury: &Pubkey,
+    pool: &Pubkey,
+    config: &PoolConfig,
+    exemptions: &FeeExemptions,
+    position: u32
+) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: (accounts::GetUserPosition {
+            user: *user,
+            pool_config_account: *pool,
+            pool_state_account: config.state_addr,
+            platform: platform_address(treasury),
+            user_info: user_info_address(pool, user, position),
+            pool_fee_exemption: exemptions.pool,
+            platform_fee_exemption: exemptions.platform,
+        }).to_account_metas(None),
+        data: (instruction::GetUserPosition { position }).data(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool_config(owner: Pubkey) -> PoolConfig {
+        PoolConfig {
+            owner,
+            pool_id: "0".to_string(),
+            duration: 30,
+            start_slot: 0,
+            end_slot: 0,
+            reward_per_slot: 1_000,
+            stake_fee: 200,
+            unstake_fee: 200,
+            stake_mint: Pubkey::new_unique(),
+            reward_mint: Pubkey::new_unique(),
+            stake_mint_decimals: 6,
+            reward_mint_decimals: 6,
+            pool_stake_token_vault: Pubkey::new_unique(),
+            pool_reward_token_vault: Pubkey::new_unique(),
+            state_addr: Pubkey::new_unique(),
+            token_program: spl_token::ID,
+            creator_fees: 0,
+            treasury_fees: 0,
+            performance_fee_model: PerformanceFeeModel::Flat,
+            min_stake: 0,
+            max_stake_per_wallet: 0,
+            max_total_staked: 0,
+            allowlist_root: None,
+            boost_collection: None,
+            boost_multiplier: 0,
+            lock_multiplier: 0,
+            unbonding_slots: 0,
+            vesting_slots: 0,
+            forfeit_unvested: false,
+            referral_share: 0,
+            referral_source: ReferralSource::StakeFee,
+        }
+    }
+
+    #[test]
+    fn stake_uses_program_derived_addresses() {
+        let staker = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let pool = Pubkey::new_unique();
+        let referrer = Pubkey::new_unique();
+        let config = pool_config(Pubkey::new_unique());
+
+        let ix = stake(
+            &staker,
+            &Pubkey::new_unique(),
+            &treasury,
+            &pool,
+            &config,
+            &FeeExemptions::default(),
+            42,
+            41,
+            None,
+            4,
+            0,
+            PositionLinks { referrer: Some(referrer), reward_recipient: None }
+        );
+
+        assert_eq!(ix.program_id, PROGRAM_ID);
+        assert_eq!(ix.accounts[0].pubkey, user_info_address(&pool, &staker, 0));
+        assert_eq!(ix.accounts[1].pubkey, referrer_address(&pool, &referrer));
+        assert!(ix.accounts[2].is_signer);
+        assert_eq!(ix.accounts[5].pubkey, platform_address(&treasury));
+        assert_eq!(
+            ix.accounts[12].pubkey,
+            ata(&config.owner, &config.stake_mint, &config.token_program)
+        );
+        assert_eq!(ix.data[8..16], 42u64.to_le_bytes());
+        assert_eq!(ix.data[16..24], 41u64.to_le_bytes());
+        assert_eq!(ix.data[24..31], [0, 4, 0, 0, 0, 0, 0]);
+        assert_eq!(ix.data[31], 1);
+        assert_eq!(ix.data[32..], referrer.to_bytes());
+    }
+
+    #[test]
+    fn allowlist_proofs_match_its_root() {
+        let wallets: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+        let json = wallets
+            .iter()
+            .enumerate()
+            .map(|(index, wallet)| format!("\"{}\": {}", wallet, index * 100))
+            .collect::<Vec<_>>()
+            .join(",");
+        let allowlist: Allowlist = serde_json::from_str(&format!("{{{}}}", json)).unwrap();
+
+        let root = allowlist.root();
+        for (index, wallet) in wallets.iter().enumerate() {
+            let proof = allowlist.proof(wallet).unwrap();
+            assert_eq!(proof.allocation, (index as u64) * 100);
+            let leaf = merkle::allowlist_leaf(wallet, proof.allocation);
+            assert!(merkle::verify_proof(&root, leaf, &proof.proof));
+        }
+        assert!(allowlist.proof(&Pubkey::new_unique()).is_none());
+    }
+
+    #[test]
+    fn amm_keys_parse_raydium_sdk_json() {
+        let key = Pubkey::new_unique().to_string();
+        let fields = [
+            "ammProgram",
+            "id",
+            "authority",
+            "openOrders",
+            "targetOrders",
+            "baseVault",
+            "quoteVault",
+            "marketProgramId",
+            "marketId",
+            "marketBids",
+            "marketAsks",
+            "marketEventQueue",
+            "marketBaseVault",
+            "marketQuoteVault",
+            "marketAuthority",
+        ];
+        let json = fields
+            .iter()
+            .map(|field| format!("\"{}\": \"{}\"", field, key))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let amm: AmmKeys = serde_json::from_str(&format!("{{{}, \"lpMint\": \"{}\"}}", json, key)).unwrap();
+
+        assert_eq!(amm.market_authority.to_string(), key);
+    }
+}
@@ -0,0 +1,942 @@
+//! `brew-staking`: operator CLI for the staking-on-solana program.
+
+mod config;
+mod display;
+mod instructions;
+
+use anchor_lang::{ AccountDeserialize, AnchorDeserialize };
+use anyhow::{ anyhow, bail, Context, Result };
+use base64::{ engine::general_purpose::STANDARD, Engine };
+use clap::{ Parser, Subcommand };
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::Instruction,
+    program::MAX_RETURN_DATA,
+    pubkey::Pubkey,
+    signature::{ Keypair, Signer },
+    transaction::Transaction,
+};
+use staking_on_solana::state::{
+    PerformanceFeeModel,
+    PlatformInfo,
+    PoolConfig,
+    PoolState,
+    ReferralSource,
+    UserInfo,
+};
+use staking_on_solana::utils::{ PoolInfo, UserPosition };
+
+use crate::config::Config;
+use crate::instructions::{ Allowlist, AmmKeys, CreatePoolArgs, FeeExemptions, PositionLinks };
+
+#[derive(Parser)]
+#[command(name = "brew-staking", version, about = "Manage staking-on-solana pools")]
+struct Cli {
+    /// Solana CLI config file (defaults to ~/.config/solana/cli/config.yml)
+    #[arg(short = 'C', long, global = true)]
+    config: Option<String>,
+
+    /// RPC URL or moniker (mainnet-beta, devnet, testnet, localhost)
+    #[arg(short = 'u', long, global = true)]
+    url: Option<String>,
+
+    /// Fee payer and default signer keypair
+    #[arg(short = 'k', long, global = true)]
+    keypair: Option<String>,
+
+    /// Keypair owning the pool token vaults (defaults to --keypair)
+    #[arg(long, global = true)]
+    admin: Option<String>,
+
+    /// Platform treasury, as an address or as a keypair file where it must sign
+    #[arg(long, global = true)]
+    treasury: Option<String>,
+
+    /// Simulate the transaction and print its logs instead of sending it
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create or update the platform fee account for --treasury
+    InitPlatform {
+        #[arg(long)]
+        deploy_fee: u64,
+        #[arg(long)]
+        performance_fee: u64,
+        /// Share of pool stake and unstake fees sent to the treasury, in basis points
+        #[arg(long, default_value_t = 0)]
+        treasury_fee_share: u16,
+        /// Cut of rewards sent to the treasury by --reward-share pools, in basis points
+        #[arg(long, default_value_t = 0)]
+        reward_performance_fee: u16,
+    },
+    /// Create a new pool owned by --keypair
+    CreatePool {
+        #[arg(long)]
+        pool_id: String,
+        #[arg(long)]
+        stake_mint: Pubkey,
+        #[arg(long)]
+        reward_mint: Pubkey,
+        /// Stake fee in basis points
+        #[arg(long, default_value_t = 0)]
+        stake_fee: u16,
+        /// Unstake fee in basis points
+        #[arg(long, default_value_t = 0)]
+        unstake_fee: u16,
+        #[arg(long)]
+        initial_funding: u64,
+        #[arg(long)]
+        reward_per_slot: u64,
+        /// Reward period in days
+        #[arg(long)]
+        duration: u16,
+        /// Pay the platform a cut of rewards instead of the flat performance fee
+        #[arg(long)]
+        reward_share: bool,
+    },
+    /// Start the reward period of a pool
+    Start {
+        pool: Pubkey,
+    },
+    /// Stop rewards and return undistributed rewards to the caller
+    Stop {
+        pool: Pubkey,
+    },
+    /// Top up a pool's reward vault from the caller's reward token account
+    Fund {
+        pool: Pubkey,
+        amount: u64,
+    },
+    /// Set a pool's minimum stake and stake caps; 0 leaves a cap off
+    Limits {
+        pool: Pubkey,
+        #[arg(long, default_value_t = 0)]
+        min_stake: u64,
+        /// Most a single wallet can have staked
+        #[arg(long, default_value_t = 0)]
+        max_per_wallet: u64,
+        /// Most the whole pool can have staked
+        #[arg(long, default_value_t = 0)]
+        max_total: u64,
+    },
+    /// Restrict staking to the wallets in a JSON file of wallet allocations
+    Allowlist {
+        pool: Pubkey,
+        /// Leave out to open the pool to everyone again
+        file: Option<String>,
+    },
+    /// Boost stakes of holders of a verified collection's NFTs
+    Boost {
+        pool: Pubkey,
+        /// Leave out to stop new boosts
+        #[arg(long)]
+        collection: Option<Pubkey>,
+        /// Stake weight with an NFT locked, in basis points (15000 = 1.5x)
+        #[arg(long, default_value_t = 10_000)]
+        multiplier: u16,
+    },
+    /// Lock an NFT of the pool's boost collection to boost your stake
+    LockNft {
+        pool: Pubkey,
+        mint: Pubkey,
+        /// Stake position, for wallets holding more than one
+        #[arg(long, default_value_t = 0)]
+        position: u32,
+    },
+    /// Take back your locked boost NFT
+    UnlockNft {
+        pool: Pubkey,
+        mint: Pubkey,
+        /// Stake position, for wallets holding more than one
+        #[arg(long, default_value_t = 0)]
+        position: u32,
+    },
+    /// Let stakers lock their stake for more reward weight
+    LockTerms {
+        pool: Pubkey,
+        /// Weight of a 52 week lock in basis points (20000 = 2x), 0 to stop new locks
+        multiplier: u16,
+    },
+    /// Relock your stake for a number of weeks from now
+    ExtendLock {
+        pool: Pubkey,
+        weeks: u16,
+        /// Stake position, for wallets holding more than one
+        #[arg(long, default_value_t = 0)]
+        position: u32,
+    },
+    /// Drop the extra weight of a stake whose lock has ended
+    ExpireLock {
+        pool: Pubkey,
+        user: Pubkey,
+        /// Stake position, for wallets holding more than one
+        #[arg(long, default_value_t = 0)]
+        position: u32,
+    },
+    /// Close an empty stake position and get its rent back
+    ClosePosition {
+        pool: Pubkey,
+        /// Stake position, for wallets holding more than one
+        #[arg(long, default_value_t = 0)]
+        position: u32,
+    },
+    /// Close a finished pool, returning its leftover reward and rent to the owner
+    ClosePool {
+        pool: Pubkey,
+    },
+    /// Bring the reward accounting of one or more pools up to the current slot
+    UpdatePool {
+        #[arg(required = true)]
+        pools: Vec<Pubkey>,
+    },
+    /// Hold unstaked tokens in withdrawal tickets for a number of slots
+    Unbonding {
+        pool: Pubkey,
+        /// Slots before a ticket can be withdrawn, 0 to pay unstakes out right away
+        slots: u64,
+    },
+    /// Vest claimed rewards linearly over a number of slots
+    Vesting {
+        pool: Pubkey,
+        /// Slots rewards vest over, 0 to pay claims out right away
+        slots: u64,
+        /// Unstaking gives up the matching share of the unvested reward to the other stakers
+        #[arg(long)]
+        forfeit: bool,
+    },
+    /// Give referrers a share of their referees' stake fees or rewards
+    Referral {
+        pool: Pubkey,
+        /// Share in basis points, up to 5000; 0 turns referrals off
+        share: u16,
+        /// Share the referees' claimed rewards instead of their stake fees
+        #[arg(long)]
+        rewards: bool,
+    },
+    /// Collect the referral earnings of --keypair
+    ClaimReferral {
+        pool: Pubkey,
+    },
+    /// Stake tokens, paying out any pending reward first
+    Stake {
+        pool: Pubkey,
+        amount: u64,
+        /// Fail if fewer tokens reach the pool, e.g. after a transfer fee
+        #[arg(long, default_value_t = 0)]
+        min_received: u64,
+        /// The pool's allowlist file, required on allowlisted pools
+        #[arg(long)]
+        allowlist: Option<String>,
+        /// Lock the whole stake for this many weeks, on pools with locks
+        #[arg(long, default_value_t = 0)]
+        lock_weeks: u16,
+        /// Stake position, for wallets holding more than one
+        #[arg(long, default_value_t = 0)]
+        position: u32,
+        /// Wallet that referred this position; only the first one is kept
+        #[arg(long)]
+        referrer: Option<Pubkey>,
+    },
+    /// Withdraw staked tokens, paying out any pending reward first
+    Unstake {
+        pool: Pubkey,
+        amount: u64,
+        /// Stake position, for wallets holding more than one
+        #[arg(long, default_value_t = 0)]
+        position: u32,
+    },
+    /// Withdraw the whole stake of a position and its pending reward
+    Exit {
+        pool: Pubkey,
+        /// Fail if less than this is withdrawn after the unstake fee
+        #[arg(long, default_value_t = 0)]
+        min_withdrawn: u64,
+        /// Close the position as well and return its rent, once nothing is left in it
+        #[arg(long)]
+        close: bool,
+        /// Stake position, for wallets holding more than one
+        #[arg(long, default_value_t = 0)]
+        position: u32,
+    },
+    /// Collect the tokens of a due withdrawal ticket
+    WithdrawTicket {
+        pool: Pubkey,
+        /// Ticket number within the position, counting from 0
+        index: u64,
+        /// Stake position, for wallets holding more than one
+        #[arg(long, default_value_t = 0)]
+        position: u32,
+    },
+    /// Put a withdrawal ticket's tokens back into the stake
+    CancelTicket {
+        pool: Pubkey,
+        /// Ticket number within the position, counting from 0
+        index: u64,
+        /// Stake position, for wallets holding more than one
+        #[arg(long, default_value_t = 0)]
+        position: u32,
+    },
+    /// Claim pending rewards
+    Claim {
+        pool: Pubkey,
+        /// Stake position, for wallets holding more than one
+        #[arg(long, default_value_t = 0)]
+        position: u32,
+    },
+    /// Pay a position's rewards to another wallet's token account
+    RewardRecipient {
+        pool: Pubkey,
+        /// Wallet to pay; leave out to pay --keypair again
+        recipient: Option<Pubkey>,
+        /// Stake position, for wallets holding more than one
+        #[arg(long, default_value_t = 0)]
+        position: u32,
+    },
+    /// Collect the reward that has vested so far
+    WithdrawVested {
+        pool: Pubkey,
+        /// Stake position, for wallets holding more than one
+        #[arg(long, default_value_t = 0)]
+        position: u32,
+    },
+    /// Claim the pending rewards of several positions in one payout
+    ClaimAll {
+        pool: Pubkey,
+        #[arg(required = true)]
+        positions: Vec<u32>,
+    },
+    /// Restake pending rewards
+    Compound {
+        pool: Pubkey,
+        /// Raydium pool keys JSON, required when stake and reward mints differ
+        #[arg(long)]
+        amm_keys: Option<String>,
+        /// Stake position, for wallets holding more than one
+        #[arg(long, default_value_t = 0)]
+        position: u32,
+    },
+    /// Print the decoded PoolConfig and PoolState
+    ShowPool {
+        pool: Pubkey,
+    },
+    /// Print a staker's UserInfo and pending reward
+    ShowUser {
+        pool: Pubkey,
+        /// Staker address (defaults to --keypair)
+        #[arg(long)]
+        user: Option<Pubkey>,
+        /// Stake position, for wallets holding more than one
+        #[arg(long, default_value_t = 0)]
+        position: u32,
+    },
+    /// Print the platform fee account for --treasury
+    ShowPlatform,
+    /// Allow pools of a transfer hook mint on the --treasury platform
+    AllowHookMint {
+        mint: Pubkey,
+        /// Withdraw an earlier allowance instead
+        #[arg(long)]
+        revoke: bool,
+    },
+    /// Discount a wallet's fees: on one pool, signed by its owner (--keypair),
+    /// or on every pool, signed by --treasury
+    ExemptFees {
+        wallet: Pubkey,
+        /// Discount in basis points; 10000 waives the fees, 0 removes the discount
+        discount: u16,
+        /// Only for this pool, on its stake and unstake fees
+        #[arg(long)]
+        pool: Option<Pubkey>,
+    },
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let config = Config::load(
+        cli.config.as_deref(),
+        cli.url.as_deref(),
+        cli.keypair.as_deref(),
+        cli.admin.as_deref(),
+        cli.treasury.as_deref(),
+        cli.dry_run
+    )?;
+    let client = RpcClient::new_with_commitment(config.rpc_url.clone(), config.commitment);
+
+    run(&client, &config, cli.command)
+}
+
+fn run(client: &RpcClient, config: &Config, command: Command) -> Result<()> {
+    let payer = config.payer.pubkey();
+    let admin = config.admin.pubkey();
+
+    match command {
+        Command::InitPlatform {
+            deploy_fee,
+            performance_fee,
+            treasury_fee_share,
+            reward_performance_fee,
+        } => {
+            let treasury = config.treasury_signer()?;
+            let ix = instructions::initialize(
+                &payer,
+                &treasury.pubkey(),
+                deploy_fee,
+                performance_fee,
+                treasury_fee_share,
+                reward_performance_fee
+            );
+            submit(client, config, vec![ix], &[treasury])
+        }
+        Command::CreatePool {
+            pool_id,
+            stake_mint,
+            reward_mint,
+            stake_fee,
+            unstake_fee,
+            initial_funding,
+            reward_per_slot,
+            duration,
+            reward_share,
+        } => {
+            let treasury = config.treasury_signer()?;
+            let pool_state = Keypair::new();
+            let pool = instructions::pool_config_address(&pool_id, &payer);
+            let token_program = client
+                .get_account(&stake_mint)
+                .with_context(|| format!("failed to fetch mint {}", stake_mint))?.owner;
+            let hook_allowance = |mint: &Pubkey| {
+                let address = instructions::hook_allowance_address(&treasury.pubkey(), mint);
+                client.get_account(&address).ok().map(|_| address)
+            };
+            let stake_hook_allowance = hook_allowance(&stake_mint);
+            let reward_hook_allowance = hook_allowance(&reward_mint);
+            let ixs = vec![
+                instructions::create_ata(&payer, &admin, &stake_mint, &token_program),
+                instructions::create_ata(&payer, &admin, &reward_mint, &token_program),
+                instructions::create_pool(
+                    &payer,
+                    &admin,
+                    &treasury.pubkey(),
+                    &pool_state.pubkey(),
+                    &stake_mint,
+                    &reward_mint,
+                    CreatePoolArgs {
+                        pool_id,
+                        stake_fee,
+                        unstake_fee,
+                        initial_funding,
+                        reward_per_slot,
+                        duration,
+                        token_program,
+                        stake_hook_allowance,
+                        reward_hook_allowance,
+                        performance_fee_model: if reward_share {
+                            PerformanceFeeModel::RewardShare
+                        } else {
+                            PerformanceFeeModel::Flat
+                        },
+                    }
+                )
+            ];
+            submit(client, config, ixs, &[treasury, &pool_state])?;
+            println!("Pool: {}", pool);
+            println!("Pool state: {}", pool_state.pubkey());
+            Ok(())
+        }
+        Command::Start { pool } => {
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            submit(client, config, vec![instructions::start_reward(&payer, &pool, &pool_config)], &[])
+        }
+        Command::Stop { pool } => {
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let ixs = vec![
+                create_pool_ata(&payer, &payer, &pool_config.reward_mint, &pool_config),
+                instructions::stop_reward(&payer, &pool, &pool_config)
+            ];
+            submit(client, config, ixs, &[])
+        }
+        Command::Fund { pool, amount } => {
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let ix = instructions::fund_pool(&payer, &pool, &pool_config, amount);
+            submit(client, config, vec![ix], &[])
+        }
+        Command::Limits { pool, min_stake, max_per_wallet, max_total } => {
+            let ix = instructions::set_stake_limits(&payer, &pool, min_stake, max_per_wallet, max_total);
+            submit(client, config, vec![ix], &[])
+        }
+        Command::Allowlist { pool, file } => {
+            let root = match file {
+                Some(path) => Some(read_json::<Allowlist>(&path)?.root()),
+                None => None,
+            };
+            submit(client, config, vec![instructions::set_allowlist(&payer, &pool, root)], &[])
+        }
+        Command::Boost { pool, collection, multiplier } => {
+            let ix = instructions::set_boost_collection(&payer, &pool, collection, multiplier);
+            submit(client, config, vec![ix], &[])
+        }
+        Command::LockNft { pool, mint, position } => {
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let nft_token_program = client.get_account(&mint)?.owner;
+            let ix = instructions::lock_boost_nft(&payer, &pool, &pool_config, &mint, &nft_token_program, position);
+            submit(client, config, vec![ix], &[])
+        }
+        Command::UnlockNft { pool, mint, position } => {
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let nft_token_program = client.get_account(&mint)?.owner;
+            let ixs = vec![
+                instructions::create_ata(&payer, &payer, &mint, &nft_token_program),
+                instructions::unlock_boost_nft(&payer, &pool, &pool_config, &mint, &nft_token_program, position)
+            ];
+            submit(client, config, ixs, &[])
+        }
+        Command::LockTerms { pool, multiplier } => {
+            submit(client, config, vec![instructions::set_lock_multiplier(&payer, &pool, multiplier)], &[])
+        }
+        Command::ExtendLock { pool, weeks, position } => {
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let ix = instructions::extend_lock(&payer, &pool, &pool_config, weeks, position);
+            submit(client, config, vec![ix], &[])
+        }
+        Command::Vesting { pool, slots, forfeit } => {
+            submit(client, config, vec![instructions::set_vesting(&payer, &pool, slots, forfeit)], &[])
+        }
+        Command::Referral { pool, share, rewards } => {
+            let source = if rewards { ReferralSource::Rewards } else { ReferralSource::StakeFee };
+            submit(client, config, vec![instructions::set_referral(&payer, &pool, share, source)], &[])
+        }
+        Command::ClaimReferral { pool } => {
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let ixs = vec![
+                create_pool_ata(&payer, &payer, &pool_config.stake_mint, &pool_config),
+                create_pool_ata(&payer, &payer, &pool_config.reward_mint, &pool_config),
+                instructions::claim_referral(&payer, &admin, &pool, &pool_config)
+            ];
+            submit(client, config, ixs, &[&config.admin])
+        }
+        Command::Unbonding { pool, slots } => {
+            submit(client, config, vec![instructions::set_unbonding_period(&payer, &pool, slots)], &[])
+        }
+        Command::ExpireLock { pool, user, position } => {
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            submit(client, config, vec![instructions::expire_lock(&user, &pool, &pool_config, position)], &[])
+        }
+        Command::ClosePosition { pool, position } => {
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let ix = instructions::close_user_info(&payer, &pool, &pool_config, position);
+            submit(client, config, vec![ix], &[])
+        }
+        Command::ClosePool { pool } => {
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let ixs = vec![
+                create_pool_ata(&payer, &payer, &pool_config.reward_mint, &pool_config),
+                instructions::close_pool(&payer, &admin, &pool, &pool_config)
+            ];
+            submit(client, config, ixs, &[&config.admin])
+        }
+        Command::UpdatePool { pools } => {
+            let ix = match pools.as_slice() {
+                [pool] => instructions::update_pool(pool, &fetch::<PoolConfig>(client, pool)?),
+                _ => {
+                    let pools = pools
+                        .iter()
+                        .map(|pool| Ok((*pool, fetch::<PoolConfig>(client, pool)?)))
+                        .collect::<Result<Vec<_>>>()?;
+                    instructions::update_pools(&pools)
+                }
+            };
+            submit(client, config, vec![ix], &[])
+        }
+        Command::Stake { pool, amount, min_received, allowlist, lock_weeks, position, referrer } => {
+            let treasury = config.treasury()?;
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let allowlist_proof = match allowlist {
+                Some(path) => {
+                    let proof = read_json::<Allowlist>(&path)?.proof(&payer);
+                    Some(proof.ok_or_else(|| anyhow!("{} is not on the allowlist in {}", payer, path))?)
+                }
+                None => None,
+            };
+            let links = position_links(client, &pool, &payer, position);
+            let mut ixs = fee_vault_atas(&payer, &treasury, &pool_config);
+            ixs.push(create_pool_ata(&payer, &links.reward_owner(&payer), &pool_config.reward_mint, &pool_config));
+            ixs.push(
+                instructions::stake(
+                    &payer,
+                    &admin,
+                    &treasury,
+                    &pool,
+                    &pool_config,
+                    &fee_exemptions(client, &treasury, &pool, &payer),
+                    amount,
+                    min_received,
+                    allowlist_proof,
+                    lock_weeks,
+                    position,
+                    PositionLinks { referrer: links.referrer.or(referrer), ..links }
+                )
+            );
+            submit(client, config, ixs, &[])
+        }
+        Command::Unstake { pool, amount, position } => {
+            let treasury = config.treasury()?;
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let links = position_links(client, &pool, &payer, position);
+            let mut ixs = fee_vault_atas(&payer, &treasury, &pool_config);
+            ixs.push(create_pool_ata(&payer, &payer, &pool_config.stake_mint, &pool_config));
+            ixs.push(create_pool_ata(&payer, &links.reward_owner(&payer), &pool_config.reward_mint, &pool_config));
+            let exemptions = fee_exemptions(client, &treasury, &pool, &payer);
+            let ticket = next_ticket(client, &pool, &pool_config, &payer, position)?;
+            ixs.push(
+                instructions::unstake(
+                    &payer,
+                    &admin,
+                    &treasury,
+                    &pool,
+                    &pool_config,
+                    &exemptions,
+                    amount,
+                    position,
+                    ticket,
+                    links
+                )
+            );
+            submit(client, config, ixs, &[&config.admin])
+        }
+        Command::Exit { pool, min_withdrawn, close, position } => {
+            let treasury = config.treasury()?;
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let links = position_links(client, &pool, &payer, position);
+            let mut ixs = fee_vault_atas(&payer, &treasury, &pool_config);
+            ixs.push(create_pool_ata(&payer, &payer, &pool_config.stake_mint, &pool_config));
+            ixs.push(create_pool_ata(&payer, &links.reward_owner(&payer), &pool_config.reward_mint, &pool_config));
+            let exemptions = fee_exemptions(client, &treasury, &pool, &payer);
+            let ticket = next_ticket(client, &pool, &pool_config, &payer, position)?;
+            ixs.push(
+                instructions::exit(
+                    &payer,
+                    &admin,
+                    &treasury,
+                    &pool,
+                    &pool_config,
+                    &exemptions,
+                    min_withdrawn,
+                    position,
+                    close,
+                    ticket,
+                    links
+                )
+            );
+            submit(client, config, ixs, &[&config.admin])
+        }
+        Command::WithdrawTicket { pool, index, position } => {
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let ticket = instructions::ticket_address(&instructions::user_info_address(&pool, &payer, position), index);
+            let ixs = vec![
+                create_pool_ata(&payer, &payer, &pool_config.stake_mint, &pool_config),
+                instructions::withdraw_ticket(&payer, &admin, &pool, &pool_config, &ticket, position)
+            ];
+            submit(client, config, ixs, &[&config.admin])
+        }
+        Command::CancelTicket { pool, index, position } => {
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let ticket = instructions::ticket_address(&instructions::user_info_address(&pool, &payer, position), index);
+            let ix = instructions::cancel_ticket(&payer, &pool, &pool_config, &ticket, position);
+            submit(client, config, vec![ix], &[])
+        }
+        Command::Claim { pool, position } => {
+            let treasury = config.treasury()?;
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let links = position_links(client, &pool, &payer, position);
+            let ixs = vec![
+                create_pool_ata(&payer, &links.reward_owner(&payer), &pool_config.reward_mint, &pool_config),
+                instructions::claim_reward(
+                    &payer,
+                    &admin,
+                    &treasury,
+                    &pool,
+                    &pool_config,
+                    &fee_exemptions(client, &treasury, &pool, &payer),
+                    position,
+                    links
+                )
+            ];
+            submit(client, config, ixs, &[&config.admin])
+        }
+        Command::RewardRecipient { pool, recipient, position } => {
+            let ix = instructions::set_reward_recipient(&payer, &pool, position, recipient);
+            submit(client, config, vec![ix], &[])
+        }
+        Command::WithdrawVested { pool, position } => {
+            let treasury = config.treasury()?;
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let links = position_links(client, &pool, &payer, position);
+            let ixs = vec![
+                create_pool_ata(&payer, &links.reward_owner(&payer), &pool_config.reward_mint, &pool_config),
+                instructions::withdraw_vested(
+                    &payer,
+                    &admin,
+                    &treasury,
+                    &pool,
+                    &pool_config,
+                    &fee_exemptions(client, &treasury, &pool, &payer),
+                    position,
+                    links
+                )
+            ];
+            submit(client, config, ixs, &[&config.admin])
+        }
+        Command::ClaimAll { pool, positions } => {
+            let treasury = config.treasury()?;
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            // The positions share a reward recipient; referred ones their referrer
+            let position_links: Vec<_> = positions
+                .iter()
+                .map(|&position| position_links(client, &pool, &payer, position))
+                .collect();
+            let links = PositionLinks {
+                referrer: position_links.iter().find_map(|links| links.referrer),
+                reward_recipient: position_links[0].reward_recipient,
+            };
+            let ixs = vec![
+                create_pool_ata(&payer, &links.reward_owner(&payer), &pool_config.reward_mint, &pool_config),
+                instructions::claim_all(
+                    &payer,
+                    &admin,
+                    &treasury,
+                    &pool,
+                    &pool_config,
+                    &fee_exemptions(client, &treasury, &pool, &payer),
+                    &positions,
+                    links
+                )
+            ];
+            submit(client, config, ixs, &[&config.admin])
+        }
+        Command::Compound { pool, amm_keys, position } => {
+            let treasury = config.treasury()?;
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let amm = match amm_keys {
+                Some(path) => read_json::<AmmKeys>(&path)?,
+                None if pool_config.stake_mint == pool_config.reward_mint => {
+                    AmmKeys::placeholder()
+                }
+                None => bail!("pool swaps rewards into the stake token; pass --amm-keys"),
+            };
+            let exemptions = fee_exemptions(client, &treasury, &pool, &payer);
+            let links = position_links(client, &pool, &payer, position);
+            let ix = instructions::compound_reward(
+                &payer,
+                &admin,
+                &treasury,
+                &pool,
+                &pool_config,
+                &exemptions,
+                &amm,
+                position,
+                links
+            );
+            submit(client, config, vec![ix], &[&config.admin])
+        }
+        Command::ShowPool { pool } => {
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let pool_state = fetch::<PoolState>(client, &pool_config.state_addr)?;
+            display::print_pool(&pool, &pool_config, &pool_state);
+            let info = simulate_view::<PoolInfo>(client, config, instructions::get_pool_info(&pool, &pool_config))?;
+            if let Some(info) = info {
+                display::print_pool_info(&info);
+            }
+            Ok(())
+        }
+        Command::ShowUser { pool, user, position } => {
+            let user = user.unwrap_or(payer);
+            let pool_config = fetch::<PoolConfig>(client, &pool)?;
+            let address = instructions::user_info_address(&pool, &user, position);
+            let user_info = fetch::<UserInfo>(client, &address)?;
+            // The fees need the platform; without it only the pending reward is shown
+            let view = match config.treasury() {
+                Ok(treasury) => {
+                    let exemptions = fee_exemptions(client, &treasury, &pool, &user);
+                    let ix = instructions::get_user_position(&user, &treasury, &pool, &pool_config, &exemptions, position);
+                    simulate_view::<UserPosition>(client, config, ix)?
+                }
+                Err(_) => None,
+            };
+            let pending = match &view {
+                Some(view) => Some(view.pending_reward),
+                None => {
+                    let ix = instructions::pending_reward(&user, &pool, &pool_config, position);
+                    simulate_view::<u64>(client, config, ix)?
+                }
+            };
+            display::print_user(&address, &user_info, pending);
+            if let Some(view) = view {
+                display::print_user_position(&view);
+            }
+            Ok(())
+        }
+        Command::ShowPlatform => {
+            let address = instructions::platform_address(&config.treasury()?);
+            let platform = fetch::<PlatformInfo>(client, &address)?;
+            display::print_platform(&address, &platform);
+            Ok(())
+        }
+        Command::AllowHookMint { mint, revoke } => {
+            let treasury = config.treasury_signer()?;
+            let ix = instructions::allow_hook_mint(&treasury.pubkey(), &mint, !revoke);
+            submit(client, config, vec![ix], &[treasury])
+        }
+        Command::ExemptFees { wallet, discount, pool } => {
+            match pool {
+                Some(pool) => {
+                    let ix = instructions::set_pool_fee_exemption(&payer, &pool, &wallet, discount);
+                    submit(client, config, vec![ix], &[])
+                }
+                None => {
+                    let treasury = config.treasury_signer()?;
+                    let ix = instructions::set_platform_fee_exemption(&treasury.pubkey(), &wallet, discount);
+                    submit(client, config, vec![ix], &[treasury])
+                }
+            }
+        }
+    }
+}
+
+/// Creates `owner`'s token account for one of the pool's mints.
+fn create_pool_ata(payer: &Pubkey, owner: &Pubkey, mint: &Pubkey, pool_config: &PoolConfig) -> Instruction {
+    instructions::create_ata(payer, owner, mint, &pool_config.token_program)
+}
+
+/// Fee-receiving stake token accounts that `stake` and `unstake` expect to exist.
+fn fee_vault_atas(payer: &Pubkey, treasury: &Pubkey, pool_config: &PoolConfig) -> Vec<Instruction> {
+    vec![
+        instructions::create_ata(payer, &pool_config.owner, &pool_config.stake_mint, &pool_config.token_program),
+        create_pool_ata(payer, treasury, &pool_config.stake_mint, pool_config)
+    ]
+}
+
+/// The fee exemptions `user` holds on `pool` and on the platform.
+fn fee_exemptions(client: &RpcClient, treasury: &Pubkey, pool: &Pubkey, user: &Pubkey) -> FeeExemptions {
+    let existing = |scope: &Pubkey| {
+        let address = instructions::fee_exemption_address(scope, user);
+        client.get_account(&address).ok().map(|_| address)
+    };
+    FeeExemptions {
+        pool: existing(pool),
+        platform: existing(&instructions::platform_address(treasury)),
+    }
+}
+
+/// The wallets a stake position points to, none for positions that don't exist yet.
+fn position_links(client: &RpcClient, pool: &Pubkey, user: &Pubkey, position: u32) -> PositionLinks {
+    let address = instructions::user_info_address(pool, user, position);
+    match fetch::<UserInfo>(client, &address) {
+        Ok(user_info) => PositionLinks {
+            referrer: user_info.referrer,
+            reward_recipient: user_info.reward_recipient,
+        },
+        Err(_) => PositionLinks::default(),
+    }
+}
+
+/// The withdrawal ticket an unstake from the position opens, on pools with an
+/// unbonding period. An exit from a position with nothing staked opens none.
+fn next_ticket(
+    client: &RpcClient,
+    pool: &Pubkey,
+    pool_config: &PoolConfig,
+    user: &Pubkey,
+    position: u32
+) -> Result<Option<Pubkey>> {
+    if pool_config.unbonding_slots == 0 {
+        return Ok(None);
+    }
+    let address = instructions::user_info_address(pool, user, position);
+    let user_info = fetch::<UserInfo>(client, &address)?;
+    if user_info.staked_amount == 0 {
+        return Ok(None);
+    }
+    Ok(Some(instructions::ticket_address(&address, user_info.ticket_count)))
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &str) -> Result<T> {
+    let file = std::fs::File::open(path).with_context(|| format!("failed to open {}", path))?;
+    serde_json::from_reader(file).with_context(|| format!("failed to parse {}", path))
+}
+
+fn fetch<T: AccountDeserialize>(client: &RpcClient, address: &Pubkey) -> Result<T> {
+    let data = client
+        .get_account_data(address)
+        .with_context(|| format!("failed to fetch account {}", address))?;
+    T::try_deserialize(&mut data.as_slice()).with_context(||
+        format!("failed to decode account {}", address)
+    )
+}
+
+fn submit(
+    client: &RpcClient,
+    config: &Config,
+    instructions: Vec<Instruction>,
+    extra_signers: &[&Keypair]
+) -> Result<()> {
+    let mut signers: Vec<&Keypair> = vec![&config.payer];
+    for signer in extra_signers {
+        if !signers.iter().any(|existing| existing.pubkey() == signer.pubkey()) {
+            signers.push(signer);
+        }
+    }
+
+    let blockhash = client.get_latest_blockhash()?;
+    let tx = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&config.payer.pubkey()),
+        &signers,
+        blockhash
+    );
+
+    if config.dry_run {
+        let result = client.simulate_transaction(&tx)?.value;
+        for line in result.logs.unwrap_or_default() {
+            println!("{}", line);
+        }
+        if let Some(units) = result.units_consumed {
+            println!("Compute units consumed: {}", units);
+        }
+        return match result.err {
+            Some(err) => Err(anyhow!("simulation failed: {}", err)),
+            None => {
+                println!("Simulation succeeded");
+                Ok(())
+            }
+        };
+    }
+
+    let signature = client.send_and_confirm_transaction_with_spinner(&tx)?;
+    println!("Signature: {}", signature);
+    Ok(())
+}
+
+/// Runs a view instruction through simulation and decodes its return data,
+/// `None` when the simulation fails. Return data comes back without its
+/// trailing zero bytes, so it's padded again first.
+fn simulate_view<T: AnchorDeserialize>(client: &RpcClient, config: &Config, ix: Instruction) -> Result<Option<T>> {
+    let blockhash = client.get_latest_blockhash()?;
+    let tx = Transaction::new_signed_with_payer(&[ix], Some(&config.payer.pubkey()), &[&config.payer], blockhash);
+    let result = client.simulate_transaction(&tx)?.value;
+    if result.err.is_some() {
+        return Ok(None);
+    }
+    let Some(return_data) = result.return_data else {
+        return Ok(None);
+    };
+    let mut bytes = STANDARD.decode(return_data.data.0)?;
+    bytes.resize(MAX_RETURN_DATA, 0);
+    let value = T::deserialize(&mut bytes.as_slice()).context("unexpected view return data")?;
+    Ok(Some(value))
+}
@@ -0,0 +1,99 @@
+//! Read-only HTTP/JSON API over the indexed data.
+//!
+//! | Route                                  | Body                              |
+//! |----------------------------------------|-----------------------------------|
+//! | `GET /pools`                           | every pool with its TVL           |
+//! | `GET /pools/{pool}`                    | one pool and its fee totals       |
+//! | `GET /pools/{pool}/fees`               | fee totals by kind and mint       |
+//! | `GET /pools/{pool}/users`              | every position in the pool        |
+//! | `GET /pools/{pool}/users/{user}`       | one position                      |
+//! | `GET /pools/{pool}/users/{user}/events`| the user's events, oldest first   |
+//! | `GET /pools/{pool}/referrers`          | referred volume of every referrer |
+//! | `GET /users/{user}`                    | the user's positions in all pools |
+//!
+//! Token amounts are decimal strings so that JavaScript clients don't lose
+//! precision on large values.
+
+use anyhow::{ anyhow, Result };
+use serde_json::{ json, Value };
+use solana_sdk::pubkey::Pubkey;
+use tiny_http::{ Header, Method, Response, Server };
+
+use crate::store::Store;
+
+/// Answers one request: the HTTP status and the JSON body.
+pub fn handle(store: &Store, method: &Method, url: &str) -> (u16, Value) {
+    if *method != Method::Get {
+        return error(405, "only GET is supported");
+    }
+    let path = url.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    match route(store, &segments) {
+        Ok(Some(body)) => (200, body),
+        Ok(None) => error(404, "not found"),
+        Err(err) => error(400, &err.to_string()),
+    }
+}
+
+fn route(store: &Store, segments: &[&str]) -> Result<Option<Value>> {
+    let body = match segments {
+        ["pools"] => json!(store.pools()?),
+        ["pools", pool] => {
+            let pool = parse_pubkey(pool)?;
+            let Some(summary) = store.pool(&pool)? else {
+                return Ok(None);
+            };
+            let mut body = json!(summary);
+            body["fees"] = json!(store.fees(&pool)?);
+            body
+        }
+        ["pools", pool, "fees"] => json!(store.fees(&parse_pubkey(pool)?)?),
+        ["pools", pool, "users"] => json!(store.positions(&parse_pubkey(pool)?)?),
+        ["pools", pool, "users", user] => {
+            match store.position(&parse_pubkey(pool)?, &parse_pubkey(user)?)? {
+                Some(position) => json!(position),
+                None => {
+                    return Ok(None);
+                }
+            }
+        }
+        ["pools", pool, "users", user, "events"] => {
+            json!(store.user_events(&parse_pubkey(pool)?, &parse_pubkey(user)?)?)
+        }
+        ["pools", pool, "referrers"] => json!(store.referrers(&parse_pubkey(pool)?)?),
+        ["users", user] => json!(store.user_positions(&parse_pubkey(user)?)?),
+        _ => {
+            return Ok(None);
+        }
+    };
+    Ok(Some(body))
+}
+
+fn parse_pubkey(value: &str) -> Result<Pubkey> {
+    value.parse().map_err(|_| anyhow!("invalid address {}", value))
+}
+
+fn error(status: u16, message: &str) -> (u16, Value) {
+    (status, json!({ "error": message }))
+}
+
+/// Serves the API on `addr` until the process is stopped.
+pub fn serve(store: &Store, addr: &str) -> Result<()> {
+    let server = Server::http(addr).map_err(|err| anyhow!("failed to bind {}: {}", addr, err))?;
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+
+    for request in server.incoming_requests() {
+        let (status, body) = handle(store, request.method(), request.url());
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(content_type.clone());
+        if let Err(err) = request.respond(response) {
+            eprintln!("failed to respond: {}", err);
+        }
+    }
+    Ok(())
+}
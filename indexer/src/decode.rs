@@ -0,0 +1,331 @@
+//! Decodes the program's `emit_cpi!` events out of confirmed transactions.
+//!
+//! `emit_cpi!` records an event as a self-invocation of the program whose
+//! data is `EVENT_IX_TAG_LE`, the event discriminator and the Borsh encoded
+//! event, so events are read from the transaction's inner instructions.
+
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::{ AnchorDeserialize, Discriminator };
+use anyhow::{ anyhow, Result };
+use serde_json::{ json, Value };
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::{
+    option_serializer::OptionSerializer,
+    EncodedConfirmedTransactionWithStatusMeta,
+    EncodedTransaction,
+    UiInstruction,
+    UiMessage,
+};
+use staking_on_solana::events::*;
+use staking_on_solana::ID as PROGRAM_ID;
+
+pub enum StakingEvent {
+    PoolCreated(PoolCreated),
+    NewStartAndEndSlots(NewStartAndEndSlots),
+    RewardsStop(RewardsStop),
+    RewardsFunded(RewardsFunded),
+    Deposit(Deposit),
+    Withdraw(Withdraw),
+    RewardClaim(RewardClaim),
+    Compound(Compound),
+    PositionChanged(PositionChanged),
+    TicketCancelled(TicketCancelled),
+    FeeCharged(FeeCharged),
+    ReferredStake(ReferredStake),
+    ReferralClaimed(ReferralClaimed),
+}
+
+/// An event together with the transaction it was emitted in.
+pub struct IndexedEvent {
+    pub signature: String,
+    /// Position of the event within its transaction
+    pub index: u32,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub event: StakingEvent,
+}
+
+fn deserialize<T: AnchorDeserialize>(data: &[u8]) -> Option<T> {
+    T::deserialize(&mut &data[..]).ok()
+}
+
+impl StakingEvent {
+    /// Decodes a discriminator-prefixed event, as found after `EVENT_IX_TAG_LE`.
+    /// Unknown discriminators and truncated events yield `None`.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None;
+        }
+        let (discriminator, data) = data.split_at(8);
+        let event = match discriminator {
+            d if d == PoolCreated::DISCRIMINATOR => Self::PoolCreated(deserialize(data)?),
+            d if d == NewStartAndEndSlots::DISCRIMINATOR => {
+                Self::NewStartAndEndSlots(deserialize(data)?)
+            }
+            d if d == RewardsStop::DISCRIMINATOR => Self::RewardsStop(deserialize(data)?),
+            d if d == RewardsFunded::DISCRIMINATOR => Self::RewardsFunded(deserialize(data)?),
+            d if d == Deposit::DISCRIMINATOR => Self::Deposit(deserialize(data)?),
+            d if d == Withdraw::DISCRIMINATOR => Self::Withdraw(deserialize(data)?),
+            d if d == RewardClaim::DISCRIMINATOR => Self::RewardClaim(deserialize(data)?),
+            d if d == Compound::DISCRIMINATOR => Self::Compound(deserialize(data)?),
+            d if d == PositionChanged::DISCRIMINATOR => Self::PositionChanged(deserialize(data)?),
+            d if d == TicketCancelled::DISCRIMINATOR => Self::TicketCancelled(deserialize(data)?),
+            d if d == FeeCharged::DISCRIMINATOR => Self::FeeCharged(deserialize(data)?),
+            d if d == ReferredStake::DISCRIMINATOR => Self::ReferredStake(deserialize(data)?),
+            d if d == ReferralClaimed::DISCRIMINATOR => Self::ReferralClaimed(deserialize(data)?),
+            _ => {
+                return None;
+            }
+        };
+        Some(event)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PoolCreated(_) => "PoolCreated",
+            Self::NewStartAndEndSlots(_) => "NewStartAndEndSlots",
+            Self::RewardsStop(_) => "RewardsStop",
+            Self::RewardsFunded(_) => "RewardsFunded",
+            Self::Deposit(_) => "Deposit",
+            Self::Withdraw(_) => "Withdraw",
+            Self::RewardClaim(_) => "RewardClaim",
+            Self::Compound(_) => "Compound",
+            Self::PositionChanged(_) => "PositionChanged",
+            Self::TicketCancelled(_) => "TicketCancelled",
+            Self::FeeCharged(_) => "FeeCharged",
+            Self::ReferredStake(_) => "ReferredStake",
+            Self::ReferralClaimed(_) => "ReferralClaimed",
+        }
+    }
+
+    pub fn pool(&self) -> Pubkey {
+        match self {
+            Self::PoolCreated(event) => event.pool_config,
+            Self::NewStartAndEndSlots(event) => event.pool_config,
+            Self::RewardsStop(event) => event.pool_config,
+            Self::RewardsFunded(event) => event.pool_config,
+            Self::Deposit(event) => event.pool_config,
+            Self::Withdraw(event) => event.pool_config,
+            Self::RewardClaim(event) => event.pool_config,
+            Self::Compound(event) => event.pool_config,
+            Self::PositionChanged(event) => event.pool_config,
+            Self::TicketCancelled(event) => event.pool_config,
+            Self::FeeCharged(event) => event.pool_config,
+            Self::ReferredStake(event) => event.pool_config,
+            Self::ReferralClaimed(event) => event.pool_config,
+        }
+    }
+
+    /// The wallet the event is about, for events that concern one.
+    pub fn user(&self) -> Option<Pubkey> {
+        match self {
+            Self::PoolCreated(event) => Some(event.creator),
+            Self::RewardsFunded(event) => Some(event.funder),
+            Self::Deposit(event) => Some(event.staker),
+            Self::Withdraw(event) => Some(event.user),
+            Self::RewardClaim(event) => Some(event.claimer),
+            Self::Compound(event) => Some(event.compounder),
+            Self::PositionChanged(event) => Some(event.user),
+            Self::TicketCancelled(event) => Some(event.user),
+            Self::FeeCharged(event) => Some(event.payer),
+            Self::ReferredStake(event) => Some(event.user),
+            Self::ReferralClaimed(event) => Some(event.referrer),
+            Self::NewStartAndEndSlots(_) | Self::RewardsStop(_) => None,
+        }
+    }
+
+    /// The event's fields as JSON. Amounts are strings, like everywhere in the API.
+    pub fn to_json(&self) -> Value {
+        match self {
+            Self::PoolCreated(event) =>
+                json!({
+                    "pool_config": event.pool_config.to_string(),
+                    "creator": event.creator.to_string(),
+                    "pool_id": event.pool_id,
+                    "stake_mint": event.stake_mint.to_string(),
+                    "reward_mint": event.reward_mint.to_string(),
+                    "stake_fee": event.stake_fee,
+                    "unstake_fee": event.unstake_fee,
+                    "reward_per_slot": event.reward_per_slot.to_string(),
+                    "duration": event.duration,
+                    "initial_funding": event.initial_funding.to_string(),
+                    "slot": event.slot,
+                }),
+            Self::NewStartAndEndSlots(event) =>
+                json!({
+                    "pool_config": event.pool_config.to_string(),
+                    "start_slot": event.start_slot,
+                    "end_slot": event.end_slot,
+                    "slot": event.slot,
+                }),
+            Self::RewardsStop(event) =>
+                json!({
+                    "pool_config": event.pool_config.to_string(),
+                    "end_slot": event.end_slot,
+                    "refund": event.refund.to_string(),
+                    "slot": event.slot,
+                }),
+            Self::RewardsFunded(event) =>
+                json!({
+                    "pool_config": event.pool_config.to_string(),
+                    "funder": event.funder.to_string(),
+                    "amount": event.amount.to_string(),
+                    "reward_amount": event.reward_amount.to_string(),
+                    "slot": event.slot,
+                }),
+            Self::Deposit(event) =>
+                json!({
+                    "pool_config": event.pool_config.to_string(),
+                    "staker": event.staker.to_string(),
+                    "amount": event.amount.to_string(),
+                    "fee": event.fee.to_string(),
+                    "staked_amount": event.staked_amount.to_string(),
+                    "total_staked": event.total_staked.to_string(),
+                    "slot": event.slot,
+                }),
+            Self::Withdraw(event) =>
+                json!({
+                    "pool_config": event.pool_config.to_string(),
+                    "user": event.user.to_string(),
+                    "amount": event.amount.to_string(),
+                    "fee": event.fee.to_string(),
+                    "staked_amount": event.staked_amount.to_string(),
+                    "total_staked": event.total_staked.to_string(),
+                    "slot": event.slot,
+                }),
+            Self::RewardClaim(event) =>
+                json!({
+                    "pool_config": event.pool_config.to_string(),
+                    "claimer": event.claimer.to_string(),
+                    "amount": event.amount.to_string(),
+                    "staked_amount": event.staked_amount.to_string(),
+                    "total_staked": event.total_staked.to_string(),
+                    "slot": event.slot,
+                }),
+            Self::Compound(event) =>
+                json!({
+                    "pool_config": event.pool_config.to_string(),
+                    "compounder": event.compounder.to_string(),
+                    "amount": event.amount.to_string(),
+                    "staked": event.staked.to_string(),
+                    "staked_amount": event.staked_amount.to_string(),
+                    "total_staked": event.total_staked.to_string(),
+                    "slot": event.slot,
+                }),
+            Self::PositionChanged(event) =>
+                json!({
+                    "pool_config": event.pool_config.to_string(),
+                    "user": event.user.to_string(),
+                    "position": event.position,
+                    "staked_amount": event.staked_amount.to_string(),
+                    "slot": event.slot,
+                }),
+            Self::TicketCancelled(event) =>
+                json!({
+                    "pool_config": event.pool_config.to_string(),
+                    "user": event.user.to_string(),
+                    "position": event.position,
+                    "ticket": event.ticket.to_string(),
+                    "amount": event.amount.to_string(),
+                    "staked_amount": event.staked_amount.to_string(),
+                    "total_staked": event.total_staked.to_string(),
+                    "slot": event.slot,
+                }),
+            Self::FeeCharged(event) =>
+                json!({
+                    "pool_config": event.pool_config.to_string(),
+                    "payer": event.payer.to_string(),
+                    "recipient": event.recipient.to_string(),
+                    "kind": fee_kind_name(event.kind),
+                    "mint": event.mint.map(|mint| mint.to_string()),
+                    "amount": event.amount.to_string(),
+                    "slot": event.slot,
+                }),
+            Self::ReferredStake(event) =>
+                json!({
+                    "pool_config": event.pool_config.to_string(),
+                    "user": event.user.to_string(),
+                    "position": event.position,
+                    "referrer": event.referrer.to_string(),
+                    "amount": event.amount.to_string(),
+                    "new_referee": event.new_referee,
+                    "slot": event.slot,
+                }),
+            Self::ReferralClaimed(event) =>
+                json!({
+                    "pool_config": event.pool_config.to_string(),
+                    "referrer": event.referrer.to_string(),
+                    "stake_amount": event.stake_amount.to_string(),
+                    "reward_amount": event.reward_amount.to_string(),
+                    "slot": event.slot,
+                }),
+        }
+    }
+}
+
+pub fn fee_kind_name(kind: FeeKind) -> &'static str {
+    match kind {
+        FeeKind::Deploy => "deploy",
+        FeeKind::Performance => "performance",
+        FeeKind::Stake => "stake",
+        FeeKind::Unstake => "unstake",
+        FeeKind::Reward => "reward",
+        FeeKind::Referral => "referral",
+    }
+}
+
+/// Every staking event emitted by a successful transaction, in execution order.
+/// Failed transactions emit nothing, and other programs' instructions are skipped.
+pub fn transaction_events(tx: &EncodedConfirmedTransactionWithStatusMeta) -> Result<Vec<IndexedEvent>> {
+    let EncodedTransaction::Json(ui_tx) = &tx.transaction.transaction else {
+        return Err(anyhow!("transaction must be fetched with json encoding"));
+    };
+    let UiMessage::Raw(message) = &ui_tx.message else {
+        return Err(anyhow!("transaction must be fetched with json encoding"));
+    };
+    let signature = ui_tx.signatures
+        .first()
+        .ok_or_else(|| anyhow!("transaction has no signature"))?
+        .clone();
+    let meta = tx.transaction.meta
+        .as_ref()
+        .ok_or_else(|| anyhow!("transaction {} has no status meta", signature))?;
+    if meta.err.is_some() {
+        return Ok(Vec::new());
+    }
+
+    // Lookup table addresses follow the static keys, writable ones first
+    let mut account_keys = message.account_keys.clone();
+    if let OptionSerializer::Some(loaded) = &meta.loaded_addresses {
+        account_keys.extend(loaded.writable.iter().cloned());
+        account_keys.extend(loaded.readonly.iter().cloned());
+    }
+    let program_id = PROGRAM_ID.to_string();
+
+    let OptionSerializer::Some(inner_instructions) = &meta.inner_instructions else {
+        return Ok(Vec::new());
+    };
+    let mut events = Vec::new();
+    for instruction in inner_instructions.iter().flat_map(|inner| &inner.instructions) {
+        let UiInstruction::Compiled(instruction) = instruction else {
+            continue;
+        };
+        if account_keys.get(instruction.program_id_index as usize) != Some(&program_id) {
+            continue;
+        }
+        let Ok(data) = bs58::decode(&instruction.data).into_vec() else {
+            continue;
+        };
+        let Some(event) = data.strip_prefix(&EVENT_IX_TAG_LE).and_then(StakingEvent::decode) else {
+            continue;
+        };
+        events.push(IndexedEvent {
+            signature: signature.clone(),
+            index: events.len() as u32,
+            slot: tx.slot,
+            block_time: tx.block_time,
+            event,
+        });
+    }
+    Ok(events)
+}
@@ -0,0 +1,10 @@
+//! Off-chain indexer for staking-on-solana events.
+//!
+//! Transactions are read from an RPC node or from a recording, their
+//! `emit_cpi!` events are decoded with the program's own `#[event]` types and
+//! materialized into SQLite, and the result is served over HTTP/JSON.
+
+pub mod api;
+pub mod decode;
+pub mod source;
+pub mod store;
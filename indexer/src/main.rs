@@ -0,0 +1,96 @@
+//! `brew-staking-indexer`: indexes staking-on-solana events and serves them.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{ Context, Result };
+use brew_staking_indexer::{ api, source, store::Store };
+use clap::{ Parser, Subcommand };
+use solana_cli_config::ConfigInput;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+#[derive(Parser)]
+#[command(name = "brew-staking-indexer", version, about = "Index staking-on-solana events")]
+struct Cli {
+    /// SQLite database to write to and serve from
+    #[arg(long, global = true, default_value = "brew-staking.db")]
+    db: PathBuf,
+
+    /// RPC URL or moniker (mainnet-beta, devnet, testnet, localhost)
+    #[arg(short = 'u', long, global = true, default_value = "localhost")]
+    url: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Index the program's transactions since the last sync
+    Sync {
+        /// Keep polling for new transactions
+        #[arg(long)]
+        follow: bool,
+        /// Seconds between polls with --follow
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+    },
+    /// Save the program's transactions to a JSON Lines recording
+    Record {
+        out: PathBuf,
+        /// Only the newest transactions
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Index a recording made by `record`
+    Replay {
+        recording: PathBuf,
+    },
+    /// Serve the indexed data over HTTP
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        bind: String,
+    },
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let (_, url) = ConfigInput::compute_json_rpc_url_setting(&cli.url, "");
+    let client = || RpcClient::new_with_commitment(url.clone(), CommitmentConfig::confirmed());
+
+    match cli.command {
+        Command::Sync { follow, interval } => {
+            let client = client();
+            let mut store = Store::open(&cli.db)?;
+            loop {
+                let applied = source::sync(&client, &mut store)?;
+                println!("Indexed {} events", applied);
+                if !follow {
+                    return Ok(());
+                }
+                thread::sleep(Duration::from_secs(interval));
+            }
+        }
+        Command::Record { out, limit } => {
+            let file = File::create(&out).with_context(|| format!("failed to create {}", out.display()))?;
+            let recorded = source::record(&client(), &mut BufWriter::new(file), limit)?;
+            println!("Recorded {} transactions", recorded);
+            Ok(())
+        }
+        Command::Replay { recording } => {
+            let mut store = Store::open(&cli.db)?;
+            let applied = source::replay(&mut store, &source::read_recording(recording)?)?;
+            println!("Indexed {} events", applied);
+            Ok(())
+        }
+        Command::Serve { bind } => {
+            let store = Store::open(&cli.db)?;
+            println!("Listening on http://{}", bind);
+            api::serve(&store, &bind)
+        }
+    }
+}
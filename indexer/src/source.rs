@@ -0,0 +1,121 @@
+//! Where transactions come from: an RPC node, or a recording of one.
+//!
+//! A recording is a JSON Lines file holding one `getTransaction` result
+//! (`json` encoding) per line, oldest first. `record` writes one from an RPC
+//! node, and it can be replayed into a database without any network access.
+
+use std::fs::File;
+use std::io::{ BufRead, BufReader, Write };
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{ Context, Result };
+use solana_client::rpc_client::{ GetConfirmedSignaturesForAddress2Config, RpcClient };
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::{ EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding };
+use staking_on_solana::ID as PROGRAM_ID;
+
+use crate::decode::transaction_events;
+use crate::store::Store;
+
+/// The most signatures `getSignaturesForAddress` returns per page
+const PAGE_SIZE: usize = 1_000;
+
+pub fn read_recording(path: impl AsRef<Path>) -> Result<Vec<EncodedConfirmedTransactionWithStatusMeta>> {
+    let path = path.as_ref();
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut transactions = Vec::new();
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let tx = serde_json
+            ::from_str(&line)
+            .with_context(|| format!("{}:{}: invalid transaction", path.display(), number + 1))?;
+        transactions.push(tx);
+    }
+    Ok(transactions)
+}
+
+/// Applies transactions in order, returning how many new events they held.
+pub fn replay(store: &mut Store, transactions: &[EncodedConfirmedTransactionWithStatusMeta]) -> Result<usize> {
+    let mut applied = 0;
+    for tx in transactions {
+        applied += store.apply(&transaction_events(tx)?)?;
+    }
+    Ok(applied)
+}
+
+/// Signatures of the program's successful transactions after `until`, oldest first.
+/// With `limit`, only the newest `limit` of them.
+fn signatures_since(
+    client: &RpcClient,
+    until: Option<Signature>,
+    limit: Option<usize>
+) -> Result<Vec<Signature>> {
+    let mut signatures = Vec::new();
+    let mut before = None;
+    loop {
+        let page = client.get_signatures_for_address_with_config(&PROGRAM_ID, GetConfirmedSignaturesForAddress2Config {
+            before,
+            until,
+            limit: Some(PAGE_SIZE),
+            commitment: Some(client.commitment()),
+        })?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(Signature::from_str(&last.signature)?);
+        let full_page = page.len() == PAGE_SIZE;
+        for status in page {
+            if status.err.is_none() {
+                signatures.push(Signature::from_str(&status.signature)?);
+            }
+        }
+        if !full_page || limit.is_some_and(|limit| signatures.len() >= limit) {
+            break;
+        }
+    }
+    if let Some(limit) = limit {
+        signatures.truncate(limit);
+    }
+    signatures.reverse();
+    Ok(signatures)
+}
+
+fn fetch_transaction(client: &RpcClient, signature: &Signature) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
+    client
+        .get_transaction_with_config(signature, RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Json),
+            commitment: Some(client.commitment()),
+            max_supported_transaction_version: Some(0),
+        })
+        .with_context(|| format!("failed to fetch transaction {}", signature))
+}
+
+/// Indexes the program's transactions since the last sync. The cursor moves
+/// after every transaction, so an interrupted sync resumes where it stopped.
+pub fn sync(client: &RpcClient, store: &mut Store) -> Result<usize> {
+    let until = store.last_signature()?.map(|signature| Signature::from_str(&signature)).transpose()?;
+    let mut applied = 0;
+    for signature in signatures_since(client, until, None)? {
+        let tx = fetch_transaction(client, &signature)?;
+        applied += store.apply(&transaction_events(&tx)?)?;
+        store.set_last_signature(&signature.to_string())?;
+    }
+    Ok(applied)
+}
+
+/// Writes the program's newest transactions to a recording, returning how
+/// many were written.
+pub fn record(client: &RpcClient, out: &mut impl Write, limit: Option<usize>) -> Result<usize> {
+    let signatures = signatures_since(client, None, limit)?;
+    for signature in &signatures {
+        let tx = fetch_transaction(client, signature)?;
+        serde_json::to_writer(&mut *out, &tx)?;
+        writeln!(out)?;
+    }
+    Ok(signatures.len())
+}
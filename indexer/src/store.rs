@@ -0,0 +1,606 @@
+//! SQLite materialization of the event stream.
+//!
+//! Every event is kept in `events`, keyed by its transaction signature and
+//! position, and folded into per-pool, per-position and per-fee totals as it
+//! is inserted. Re-applying a transaction is a no-op, so overlapping syncs and
+//! replays are safe.
+//!
+//! Token amounts are stored as decimal text: SQLite integers are signed 64-bit
+//! and totals of `u64` amounts can exceed that. The `add_amount` SQL function
+//! sums them as `u128`.
+
+use std::path::Path;
+
+use anyhow::Result;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{ params, Connection, OptionalExtension, Row, Transaction };
+use serde::Serialize;
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::decode::{ fee_kind_name, IndexedEvent, StakingEvent };
+
+const SCHEMA: &str =
+    "
+    CREATE TABLE IF NOT EXISTS events (
+        signature TEXT NOT NULL,
+        idx INTEGER NOT NULL,
+        slot INTEGER NOT NULL,
+        block_time INTEGER,
+        pool TEXT NOT NULL,
+        user TEXT,
+        kind TEXT NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (signature, idx)
+    );
+    CREATE INDEX IF NOT EXISTS events_by_user ON events (pool, user, slot);
+
+    CREATE TABLE IF NOT EXISTS pools (
+        pool TEXT PRIMARY KEY,
+        pool_id TEXT,
+        creator TEXT,
+        stake_mint TEXT,
+        reward_mint TEXT,
+        stake_fee INTEGER,
+        unstake_fee INTEGER,
+        reward_per_slot TEXT,
+        start_slot INTEGER,
+        end_slot INTEGER,
+        total_staked TEXT NOT NULL DEFAULT '0',
+        funded TEXT NOT NULL DEFAULT '0',
+        rewards_paid TEXT NOT NULL DEFAULT '0',
+        refunded TEXT NOT NULL DEFAULT '0',
+        last_slot INTEGER NOT NULL DEFAULT 0
+    );
+
+    CREATE TABLE IF NOT EXISTS positions (
+        pool TEXT NOT NULL,
+        user TEXT NOT NULL,
+        staked_amount TEXT NOT NULL DEFAULT '0',
+        deposited TEXT NOT NULL DEFAULT '0',
+        deposits INTEGER NOT NULL DEFAULT 0,
+        withdrawn TEXT NOT NULL DEFAULT '0',
+        withdrawals INTEGER NOT NULL DEFAULT 0,
+        claimed TEXT NOT NULL DEFAULT '0',
+        claims INTEGER NOT NULL DEFAULT 0,
+        compounded TEXT NOT NULL DEFAULT '0',
+        compounds INTEGER NOT NULL DEFAULT 0,
+        last_slot INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (pool, user)
+    );
+
+    CREATE TABLE IF NOT EXISTS position_stakes (
+        pool TEXT NOT NULL,
+        user TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        staked_amount TEXT NOT NULL,
+        PRIMARY KEY (pool, user, position)
+    );
+
+    CREATE TABLE IF NOT EXISTS fees (
+        pool TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        mint TEXT NOT NULL,
+        total TEXT NOT NULL DEFAULT '0',
+        count INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (pool, kind, mint)
+    );
+
+    CREATE TABLE IF NOT EXISTS referrers (
+        pool TEXT NOT NULL,
+        referrer TEXT NOT NULL,
+        referees INTEGER NOT NULL DEFAULT 0,
+        volume TEXT NOT NULL DEFAULT '0',
+        stakes INTEGER NOT NULL DEFAULT 0,
+        claimed_stake TEXT NOT NULL DEFAULT '0',
+        claimed_reward TEXT NOT NULL DEFAULT '0',
+        PRIMARY KEY (pool, referrer)
+    );
+
+    CREATE TABLE IF NOT EXISTS sync_state (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        last_signature TEXT NOT NULL
+    );
+    ";
+
+/// Fee rows for lamport fees use this in place of a mint.
+const LAMPORTS: &str = "";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PoolSummary {
+    pub pool: String,
+    pub pool_id: Option<String>,
+    pub creator: Option<String>,
+    pub stake_mint: Option<String>,
+    pub reward_mint: Option<String>,
+    pub stake_fee: Option<u16>,
+    pub unstake_fee: Option<u16>,
+    pub reward_per_slot: Option<String>,
+    pub start_slot: Option<u64>,
+    pub end_slot: Option<u64>,
+    /// Total value locked, in stake tokens
+    pub tvl: String,
+    /// Wallets with a non-zero stake
+    pub stakers: u64,
+    /// Reward tokens deposited, including the initial funding
+    pub funded: String,
+    /// Rewards claimed or compounded
+    pub rewards_paid: String,
+    /// Rewards returned by `stop_reward`
+    pub refunded: String,
+    pub last_slot: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Position {
+    pub pool: String,
+    pub user: String,
+    pub staked_amount: String,
+    pub deposited: String,
+    pub deposits: u64,
+    /// Unstaked, including tickets cancelled back into the stake later
+    pub withdrawn: String,
+    pub withdrawals: u64,
+    pub claimed: String,
+    pub claims: u64,
+    pub compounded: String,
+    pub compounds: u64,
+    pub last_slot: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeeTotal {
+    pub kind: String,
+    /// Token mint, or `None` for lamports
+    pub mint: Option<String>,
+    pub total: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Referrer {
+    pub pool: String,
+    pub referrer: String,
+    /// Stake positions that named the referrer
+    pub referees: u64,
+    /// Stake tokens deposited by referees, before the stake fee
+    pub volume: String,
+    pub stakes: u64,
+    /// Earnings claimed, in stake tokens
+    pub claimed_stake: String,
+    /// Earnings claimed, in reward tokens
+    pub claimed_reward: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EventRecord {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub kind: String,
+    pub data: Value,
+}
+
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        // Lets `serve` read while `sync` writes from another process
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Self::init(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.create_scalar_function(
+            "add_amount",
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let total: String = ctx.get(0)?;
+                let amount: i64 = ctx.get(1)?;
+                let total = total
+                    .parse::<u128>()
+                    .map_err(|err| rusqlite::Error::UserFunctionError(err.into()))?;
+                // Amounts are bound as the bits of a u64
+                Ok((total + ((amount as u64) as u128)).to_string())
+            }
+        )?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Inserts and folds in the events of one transaction, returning how many
+    /// were new.
+    pub fn apply(&mut self, events: &[IndexedEvent]) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut applied = 0;
+        for event in events {
+            if insert_event(&tx, event)? {
+                fold_event(&tx, event)?;
+                applied += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(applied)
+    }
+
+    /// The newest signature `sync` has processed.
+    pub fn last_signature(&self) -> Result<Option<String>> {
+        Ok(
+            self.conn
+                .query_row("SELECT last_signature FROM sync_state WHERE id = 0", [], |row|
+                    row.get(0)
+                )
+                .optional()?
+        )
+    }
+
+    pub fn set_last_signature(&self, signature: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO sync_state (id, last_signature) VALUES (0, ?1)
+             ON CONFLICT (id) DO UPDATE SET last_signature = excluded.last_signature",
+            [signature]
+        )?;
+        Ok(())
+    }
+
+    pub fn pools(&self) -> Result<Vec<PoolSummary>> {
+        let mut stmt = self.conn.prepare(&format!("{} ORDER BY pools.pool", POOL_QUERY))?;
+        let pools = stmt.query_map([], pool_summary)?.collect::<rusqlite::Result<_>>()?;
+        Ok(pools)
+    }
+
+    pub fn pool(&self, pool: &Pubkey) -> Result<Option<PoolSummary>> {
+        let sql = format!("{} WHERE pools.pool = ?1", POOL_QUERY);
+        Ok(self.conn.query_row(&sql, [pool.to_string()], pool_summary).optional()?)
+    }
+
+    pub fn fees(&self, pool: &Pubkey) -> Result<Vec<FeeTotal>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT kind, mint, total, count FROM fees WHERE pool = ?1 ORDER BY kind, mint"
+        )?;
+        let fees = stmt
+            .query_map([pool.to_string()], |row| {
+                let mint: String = row.get(1)?;
+                Ok(FeeTotal {
+                    kind: row.get(0)?,
+                    mint: Some(mint).filter(|mint| mint != LAMPORTS),
+                    total: row.get(2)?,
+                    count: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(fees)
+    }
+
+    pub fn positions(&self, pool: &Pubkey) -> Result<Vec<Position>> {
+        let sql = format!("{} WHERE pool = ?1 ORDER BY user", POSITION_QUERY);
+        let mut stmt = self.conn.prepare(&sql)?;
+        let positions = stmt
+            .query_map([pool.to_string()], position)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(positions)
+    }
+
+    pub fn position(&self, pool: &Pubkey, user: &Pubkey) -> Result<Option<Position>> {
+        let sql = format!("{} WHERE pool = ?1 AND user = ?2", POSITION_QUERY);
+        Ok(
+            self.conn
+                .query_row(&sql, [pool.to_string(), user.to_string()], position)
+                .optional()?
+        )
+    }
+
+    /// A user's positions across every pool.
+    pub fn user_positions(&self, user: &Pubkey) -> Result<Vec<Position>> {
+        let sql = format!("{} WHERE user = ?1 ORDER BY pool", POSITION_QUERY);
+        let mut stmt = self.conn.prepare(&sql)?;
+        let positions = stmt
+            .query_map([user.to_string()], position)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(positions)
+    }
+
+    /// The pool's referrers, most referred volume first.
+    pub fn referrers(&self, pool: &Pubkey) -> Result<Vec<Referrer>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT pool, referrer, referees, volume, stakes, claimed_stake, claimed_reward
+             FROM referrers WHERE pool = ?1 ORDER BY CAST(volume AS REAL) DESC, referrer"
+        )?;
+        let referrers = stmt
+            .query_map([pool.to_string()], |row| {
+                Ok(Referrer {
+                    pool: row.get(0)?,
+                    referrer: row.get(1)?,
+                    referees: row.get(2)?,
+                    volume: row.get(3)?,
+                    stakes: row.get(4)?,
+                    claimed_stake: row.get(5)?,
+                    claimed_reward: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(referrers)
+    }
+
+    /// A user's events in a pool, oldest first.
+    pub fn user_events(&self, pool: &Pubkey, user: &Pubkey) -> Result<Vec<EventRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT signature, slot, block_time, kind, data FROM events
+             WHERE pool = ?1 AND user = ?2 ORDER BY slot, rowid"
+        )?;
+        let events = stmt
+            .query_map([pool.to_string(), user.to_string()], |row| {
+                let data: String = row.get(4)?;
+                Ok(EventRecord {
+                    signature: row.get(0)?,
+                    slot: row.get(1)?,
+                    block_time: row.get(2)?,
+                    kind: row.get(3)?,
+                    data: serde_json::from_str(&data).unwrap_or(Value::Null),
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(events)
+    }
+}
+
+const POOL_QUERY: &str =
+    "SELECT pools.pool, pool_id, creator, stake_mint, reward_mint, stake_fee, unstake_fee,
+            reward_per_slot, start_slot, end_slot, total_staked, funded, rewards_paid, refunded,
+            pools.last_slot,
+            (SELECT COUNT(*) FROM positions
+             WHERE positions.pool = pools.pool AND staked_amount != '0')
+     FROM pools";
+
+fn pool_summary(row: &Row) -> rusqlite::Result<PoolSummary> {
+    Ok(PoolSummary {
+        pool: row.get(0)?,
+        pool_id: row.get(1)?,
+        creator: row.get(2)?,
+        stake_mint: row.get(3)?,
+        reward_mint: row.get(4)?,
+        stake_fee: row.get(5)?,
+        unstake_fee: row.get(6)?,
+        reward_per_slot: row.get(7)?,
+        start_slot: row.get(8)?,
+        end_slot: row.get(9)?,
+        tvl: row.get(10)?,
+        funded: row.get(11)?,
+        rewards_paid: row.get(12)?,
+        refunded: row.get(13)?,
+        last_slot: row.get(14)?,
+        stakers: row.get(15)?,
+    })
+}
+
+const POSITION_QUERY: &str =
+    "SELECT pool, user, staked_amount, deposited, deposits, withdrawn, withdrawals,
+            claimed, claims, compounded, compounds, last_slot
+     FROM positions";
+
+fn position(row: &Row) -> rusqlite::Result<Position> {
+    Ok(Position {
+        pool: row.get(0)?,
+        user: row.get(1)?,
+        staked_amount: row.get(2)?,
+        deposited: row.get(3)?,
+        deposits: row.get(4)?,
+        withdrawn: row.get(5)?,
+        withdrawals: row.get(6)?,
+        claimed: row.get(7)?,
+        claims: row.get(8)?,
+        compounded: row.get(9)?,
+        compounds: row.get(10)?,
+        last_slot: row.get(11)?,
+    })
+}
+
+/// SQLite has no unsigned integers; `add_amount` reads the bits back as a `u64`.
+fn amount(value: u64) -> i64 {
+    value as i64
+}
+
+fn insert_event(tx: &Transaction, event: &IndexedEvent) -> Result<bool> {
+    let inserted = tx.execute(
+        "INSERT OR IGNORE INTO events (signature, idx, slot, block_time, pool, user, kind, data)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            event.signature,
+            event.index,
+            event.slot,
+            event.block_time,
+            event.event.pool().to_string(),
+            event.event.user().map(|user| user.to_string()),
+            event.event.name(),
+            event.event.to_json().to_string()
+        ]
+    )?;
+    Ok(inserted > 0)
+}
+
+fn fold_event(tx: &Transaction, indexed: &IndexedEvent) -> Result<()> {
+    let pool = indexed.event.pool().to_string();
+    tx.execute("INSERT OR IGNORE INTO pools (pool) VALUES (?1)", [&pool])?;
+    tx.execute("UPDATE pools SET last_slot = MAX(last_slot, ?2) WHERE pool = ?1", params![
+        pool,
+        indexed.slot
+    ])?;
+
+    match &indexed.event {
+        StakingEvent::PoolCreated(event) => {
+            tx.execute(
+                "UPDATE pools SET pool_id = ?2, creator = ?3, stake_mint = ?4, reward_mint = ?5,
+                     stake_fee = ?6, unstake_fee = ?7, reward_per_slot = ?8,
+                     funded = add_amount(funded, ?9)
+                 WHERE pool = ?1",
+                params![
+                    pool,
+                    event.pool_id,
+                    event.creator.to_string(),
+                    event.stake_mint.to_string(),
+                    event.reward_mint.to_string(),
+                    event.stake_fee,
+                    event.unstake_fee,
+                    event.reward_per_slot.to_string(),
+                    amount(event.initial_funding)
+                ]
+            )?;
+        }
+        StakingEvent::NewStartAndEndSlots(event) => {
+            tx.execute("UPDATE pools SET start_slot = ?2, end_slot = ?3 WHERE pool = ?1", params![
+                pool,
+                event.start_slot,
+                event.end_slot
+            ])?;
+        }
+        StakingEvent::RewardsStop(event) => {
+            tx.execute(
+                "UPDATE pools SET end_slot = ?2, refunded = add_amount(refunded, ?3) WHERE pool = ?1",
+                params![pool, event.end_slot, amount(event.refund)]
+            )?;
+        }
+        StakingEvent::RewardsFunded(event) => {
+            tx.execute("UPDATE pools SET funded = add_amount(funded, ?2) WHERE pool = ?1", params![
+                pool,
+                amount(event.amount)
+            ])?;
+        }
+        StakingEvent::Deposit(event) => {
+            update_position(tx, indexed, &event.staker, event.staked_amount, event.total_staked)?;
+            tx.execute(
+                "UPDATE positions SET deposited = add_amount(deposited, ?3), deposits = deposits + 1
+                 WHERE pool = ?1 AND user = ?2",
+                params![pool, event.staker.to_string(), amount(event.amount)]
+            )?;
+        }
+        StakingEvent::Withdraw(event) => {
+            update_position(tx, indexed, &event.user, event.staked_amount, event.total_staked)?;
+            tx.execute(
+                "UPDATE positions SET withdrawn = add_amount(withdrawn, ?3), withdrawals = withdrawals + 1
+                 WHERE pool = ?1 AND user = ?2",
+                params![pool, event.user.to_string(), amount(event.amount)]
+            )?;
+        }
+        StakingEvent::RewardClaim(event) => {
+            update_position(tx, indexed, &event.claimer, event.staked_amount, event.total_staked)?;
+            tx.execute(
+                "UPDATE positions SET claimed = add_amount(claimed, ?3), claims = claims + 1
+                 WHERE pool = ?1 AND user = ?2",
+                params![pool, event.claimer.to_string(), amount(event.amount)]
+            )?;
+            add_rewards_paid(tx, &pool, event.amount)?;
+        }
+        StakingEvent::Compound(event) => {
+            update_position(tx, indexed, &event.compounder, event.staked_amount, event.total_staked)?;
+            tx.execute(
+                "UPDATE positions SET compounded = add_amount(compounded, ?3), compounds = compounds + 1
+                 WHERE pool = ?1 AND user = ?2",
+                params![pool, event.compounder.to_string(), amount(event.amount)]
+            )?;
+            add_rewards_paid(tx, &pool, event.amount)?;
+        }
+        StakingEvent::TicketCancelled(event) => {
+            // Back into the stake it was unstaked from, without a new deposit
+            update_position(tx, indexed, &event.user, event.staked_amount, event.total_staked)?;
+        }
+        StakingEvent::PositionChanged(event) => {
+            let user = event.user.to_string();
+            tx.execute(
+                "INSERT INTO position_stakes (pool, user, position, staked_amount) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (pool, user, position) DO UPDATE SET staked_amount = excluded.staked_amount",
+                params![pool, user, event.position, event.staked_amount.to_string()]
+            )?;
+            // The user event before it recorded only this position's stake
+            let mut stmt = tx.prepare(
+                "SELECT staked_amount FROM position_stakes WHERE pool = ?1 AND user = ?2"
+            )?;
+            let mut staked_amount: u128 = 0;
+            for amount in stmt.query_map(params![pool, user], |row| row.get::<_, String>(0))? {
+                staked_amount += amount?.parse::<u128>()?;
+            }
+            tx.execute("UPDATE positions SET staked_amount = ?3 WHERE pool = ?1 AND user = ?2", params![
+                pool,
+                user,
+                staked_amount.to_string()
+            ])?;
+        }
+        StakingEvent::FeeCharged(event) => {
+            let mint = event.mint.map_or_else(|| LAMPORTS.to_string(), |mint| mint.to_string());
+            tx.execute("INSERT OR IGNORE INTO fees (pool, kind, mint) VALUES (?1, ?2, ?3)", params![
+                pool,
+                fee_kind_name(event.kind),
+                mint
+            ])?;
+            tx.execute(
+                "UPDATE fees SET total = add_amount(total, ?4), count = count + 1
+                 WHERE pool = ?1 AND kind = ?2 AND mint = ?3",
+                params![pool, fee_kind_name(event.kind), mint, amount(event.amount)]
+            )?;
+        }
+        StakingEvent::ReferredStake(event) => {
+            let referrer = insert_referrer(tx, &pool, &event.referrer)?;
+            tx.execute(
+                "UPDATE referrers SET referees = referees + ?3, volume = add_amount(volume, ?4),
+                     stakes = stakes + (?4 != 0)
+                 WHERE pool = ?1 AND referrer = ?2",
+                params![pool, referrer, event.new_referee as u64, amount(event.amount)]
+            )?;
+        }
+        StakingEvent::ReferralClaimed(event) => {
+            let referrer = insert_referrer(tx, &pool, &event.referrer)?;
+            tx.execute(
+                "UPDATE referrers SET claimed_stake = add_amount(claimed_stake, ?3),
+                     claimed_reward = add_amount(claimed_reward, ?4)
+                 WHERE pool = ?1 AND referrer = ?2",
+                params![pool, referrer, amount(event.stake_amount), amount(event.reward_amount)]
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn insert_referrer(tx: &Transaction, pool: &str, referrer: &Pubkey) -> Result<String> {
+    let referrer = referrer.to_string();
+    tx.execute("INSERT OR IGNORE INTO referrers (pool, referrer) VALUES (?1, ?2)", [pool, &referrer])?;
+    Ok(referrer)
+}
+
+/// Records the post-instruction stake carried by every user event.
+fn update_position(
+    tx: &Transaction,
+    indexed: &IndexedEvent,
+    user: &Pubkey,
+    staked_amount: u64,
+    total_staked: u64
+) -> Result<()> {
+    let pool = indexed.event.pool().to_string();
+    tx.execute("INSERT OR IGNORE INTO positions (pool, user) VALUES (?1, ?2)", [
+        &pool,
+        &user.to_string(),
+    ])?;
+    tx.execute(
+        "UPDATE positions SET staked_amount = ?3, last_slot = ?4 WHERE pool = ?1 AND user = ?2",
+        params![pool, user.to_string(), staked_amount.to_string(), indexed.slot]
+    )?;
+    tx.execute("UPDATE pools SET total_staked = ?2 WHERE pool = ?1", params![
+        pool,
+        total_staked.to_string()
+    ])?;
+    Ok(())
+}
+
+fn add_rewards_paid(tx: &Transaction, pool: &str, reward: u64) -> Result<()> {
+    tx.execute("UPDATE pools SET rewards_paid = add_amount(rewards_paid, ?2) WHERE pool = ?1", params![
+        pool,
+        amount(reward)
+    ])?;
+    Ok(())
+}
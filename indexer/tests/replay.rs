@@ -0,0 +1,302 @@
+//! Replays `fixtures/localnet.jsonl`, a recording of one pool's lifecycle:
+//!
+//! | slot | transaction                                            |
+//! |------|--------------------------------------------------------|
+//! | 100  | creator creates the pool (1%/2% fees, 300M funding)    |
+//! | 110  | rewards start at slot 120                              |
+//! | 130  | alice stakes 1M (990k credited)                        |
+//! | 140  | bob stakes 2M (1.98M credited)                         |
+//! | 200  | alice claims 30k                                       |
+//! | 210  | bob's unstake fails (its events must be ignored)       |
+//! | 220  | another program emits a lookalike `Deposit` (ignored)  |
+//! | 250  | bob compounds 40k reward into 80k stake                |
+//! | 300  | alice unstakes 490k, claiming 25k                      |
+//! | 320  | creator funds 1M more rewards                          |
+//! | 400  | rewards stop, refunding 300M                           |
+
+use brew_staking_indexer::decode::{ IndexedEvent, StakingEvent };
+use brew_staking_indexer::{ api, source, store::Store };
+use serde_json::json;
+use solana_sdk::pubkey::Pubkey;
+use staking_on_solana::events::{ Deposit, PositionChanged, ReferralClaimed, ReferredStake, TicketCancelled };
+use tiny_http::Method;
+
+const RECORDING: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/localnet.jsonl");
+
+const POOL: &str = "3gLESRnfLgzAqu6PwGhBwsiBsnQ7BAtyWHhZ5zNcDPMF";
+const CREATOR: &str = "DgX9xEoN7RZGWevFVCy13JuzKsnmAx9B3VLfvoJxwqKn";
+const ALICE: &str = "3x9az88Dkbxa6tkKByxqEn7jBTJCJCD4dVvou49L24ET";
+const BOB: &str = "9jLkNAaW9E47LQMHvjohy2uAAyr1331bAxgJKFRU7wF6";
+const STAKE_MINT: &str = "GgMHBRXySpmAfbVUgyBpXaNQxN2zaiL3ZnJHgCcV7CPx";
+
+fn key(address: &str) -> Pubkey {
+    address.parse().unwrap()
+}
+
+fn replayed() -> Store {
+    let mut store = Store::open_in_memory().unwrap();
+    source::replay(&mut store, &source::read_recording(RECORDING).unwrap()).unwrap();
+    store
+}
+
+#[test]
+fn replay_indexes_only_successful_program_events() {
+    let mut store = Store::open_in_memory().unwrap();
+    let transactions = source::read_recording(RECORDING).unwrap();
+    assert_eq!(transactions.len(), 11);
+
+    assert_eq!(source::replay(&mut store, &transactions).unwrap(), 19);
+
+    // Replaying the same transactions again changes nothing
+    let pool = store.pool(&key(POOL)).unwrap();
+    assert_eq!(source::replay(&mut store, &transactions).unwrap(), 0);
+    assert_eq!(store.pool(&key(POOL)).unwrap(), pool);
+}
+
+#[test]
+fn pool_summary_tracks_tvl_and_reward_flows() {
+    let store = replayed();
+    let pool = store.pool(&key(POOL)).unwrap().unwrap();
+
+    assert_eq!(pool.pool_id.as_deref(), Some("0"));
+    assert_eq!(pool.creator.as_deref(), Some(CREATOR));
+    assert_eq!(pool.stake_mint.as_deref(), Some(STAKE_MINT));
+    assert_eq!((pool.stake_fee, pool.unstake_fee), (Some(100), Some(200)));
+    assert_eq!((pool.start_slot, pool.end_slot), (Some(120), Some(400)));
+    assert_eq!(pool.tvl, "2560000");
+    assert_eq!(pool.stakers, 2);
+    assert_eq!(pool.funded, "301000000");
+    assert_eq!(pool.rewards_paid, "95000");
+    assert_eq!(pool.refunded, "300000000");
+    assert_eq!(pool.last_slot, 400);
+
+    assert_eq!(store.pools().unwrap(), vec![pool]);
+}
+
+#[test]
+fn positions_total_each_users_activity() {
+    let store = replayed();
+
+    let alice = store.position(&key(POOL), &key(ALICE)).unwrap().unwrap();
+    assert_eq!(alice.staked_amount, "500000");
+    assert_eq!((alice.deposited.as_str(), alice.deposits), ("990000", 1));
+    assert_eq!((alice.withdrawn.as_str(), alice.withdrawals), ("480200", 1));
+    assert_eq!((alice.claimed.as_str(), alice.claims), ("55000", 2));
+    assert_eq!((alice.compounded.as_str(), alice.compounds), ("0", 0));
+    assert_eq!(alice.last_slot, 300);
+
+    // The failed unstake left no trace
+    let bob = store.position(&key(POOL), &key(BOB)).unwrap().unwrap();
+    assert_eq!(bob.staked_amount, "2060000");
+    assert_eq!((bob.deposited.as_str(), bob.deposits), ("1980000", 1));
+    assert_eq!((bob.withdrawn.as_str(), bob.withdrawals), ("0", 0));
+    assert_eq!((bob.compounded.as_str(), bob.compounds), ("40000", 1));
+
+    assert_eq!(store.positions(&key(POOL)).unwrap().len(), 2);
+    assert_eq!(store.user_positions(&key(BOB)).unwrap(), vec![bob]);
+}
+
+#[test]
+fn positions_add_up_a_wallets_stake_positions() {
+    let mut store = replayed();
+    let (pool, carol) = (key(POOL), Pubkey::new_unique());
+    let indexed = |index: u32, slot: u64, event: StakingEvent| IndexedEvent {
+        signature: format!("positions-{}", slot),
+        index,
+        slot,
+        block_time: None,
+        event,
+    };
+    let deposit = |amount: u64, total_staked: u64| {
+        StakingEvent::Deposit(Deposit {
+            pool_config: pool,
+            staker: carol,
+            amount,
+            fee: 0,
+            staked_amount: amount,
+            total_staked,
+            slot: 0,
+        })
+    };
+    let position_changed = |position: u32, staked_amount: u64| {
+        StakingEvent::PositionChanged(PositionChanged {
+            pool_config: pool,
+            user: carol,
+            position,
+            staked_amount,
+            slot: 0,
+        })
+    };
+
+    // Each `Deposit` carries the stake of the position it went to
+    store
+        .apply(&[indexed(0, 500, deposit(1_000, 2_561_000)), indexed(1, 500, position_changed(0, 1_000))])
+        .unwrap();
+    store
+        .apply(&[indexed(0, 510, deposit(2_000, 2_563_000)), indexed(1, 510, position_changed(1, 2_000))])
+        .unwrap();
+
+    let position = store.position(&pool, &carol).unwrap().unwrap();
+    assert_eq!((position.staked_amount.as_str(), position.deposits), ("3000", 2));
+    assert_eq!(store.pool(&pool).unwrap().unwrap().tvl, "2563000");
+}
+
+#[test]
+fn cancelled_tickets_restake_without_a_deposit() {
+    let mut store = replayed();
+    let (pool, alice) = (key(POOL), key(ALICE));
+    let indexed = |index: u32, event: StakingEvent| IndexedEvent {
+        signature: "cancel-ticket".to_string(),
+        index,
+        slot: 500,
+        block_time: None,
+        event,
+    };
+
+    // As if alice's unstake at slot 300 had opened a ticket
+    store
+        .apply(&[
+            indexed(
+                0,
+                StakingEvent::TicketCancelled(TicketCancelled {
+                    pool_config: pool,
+                    user: alice,
+                    position: 0,
+                    ticket: Pubkey::new_unique(),
+                    amount: 480_200,
+                    staked_amount: 980_200,
+                    total_staked: 3_040_200,
+                    slot: 500,
+                })
+            ),
+            indexed(
+                1,
+                StakingEvent::PositionChanged(PositionChanged {
+                    pool_config: pool,
+                    user: alice,
+                    position: 0,
+                    staked_amount: 980_200,
+                    slot: 500,
+                })
+            ),
+        ])
+        .unwrap();
+
+    let position = store.position(&pool, &alice).unwrap().unwrap();
+    assert_eq!(position.staked_amount, "980200");
+    assert_eq!((position.deposited.as_str(), position.deposits), ("990000", 1));
+    assert_eq!(store.pool(&pool).unwrap().unwrap().tvl, "3040200");
+}
+
+#[test]
+fn fees_are_totalled_by_kind_and_mint() {
+    let store = replayed();
+    let fees: Vec<_> = store
+        .fees(&key(POOL))
+        .unwrap()
+        .into_iter()
+        .map(|fee| (fee.kind, fee.mint, fee.total, fee.count))
+        .collect();
+
+    assert_eq!(fees, [
+        ("deploy".to_string(), None, "100000000".to_string(), 1),
+        ("performance".to_string(), None, "25000".to_string(), 5),
+        ("stake".to_string(), Some(STAKE_MINT.to_string()), "30000".to_string(), 2),
+        ("unstake".to_string(), Some(STAKE_MINT.to_string()), "9800".to_string(), 1),
+    ]);
+}
+
+#[test]
+fn referrers_are_credited_with_referred_volume() {
+    let mut store = replayed();
+    let (pool, referrer) = (key(POOL), Pubkey::new_unique());
+    let indexed = |slot: u64, event: StakingEvent| IndexedEvent {
+        signature: format!("referrals-{}", slot),
+        index: 0,
+        slot,
+        block_time: None,
+        event,
+    };
+    let referred_stake = |user: Pubkey, amount: u64, new_referee: bool| {
+        StakingEvent::ReferredStake(ReferredStake {
+            pool_config: pool,
+            user,
+            position: 0,
+            referrer,
+            amount,
+            new_referee,
+            slot: 0,
+        })
+    };
+
+    let (carol, dave) = (Pubkey::new_unique(), Pubkey::new_unique());
+    store.apply(&[indexed(500, referred_stake(carol, 1_000, true))]).unwrap();
+    store.apply(&[indexed(510, referred_stake(dave, 2_000, true))]).unwrap();
+    store.apply(&[indexed(520, referred_stake(carol, 3_000, false))]).unwrap();
+    store
+        .apply(&[
+            indexed(
+                530,
+                StakingEvent::ReferralClaimed(ReferralClaimed {
+                    pool_config: pool,
+                    referrer,
+                    stake_amount: 60,
+                    reward_amount: 0,
+                    slot: 0,
+                })
+            ),
+        ])
+        .unwrap();
+
+    let referrers = store.referrers(&pool).unwrap();
+    assert_eq!(referrers.len(), 1);
+    let summary = &referrers[0];
+    assert_eq!((summary.referees, summary.volume.as_str(), summary.stakes), (2, "6000", 3));
+    assert_eq!((summary.claimed_stake.as_str(), summary.claimed_reward.as_str()), ("60", "0"));
+
+    let (status, body) = api::handle(&store, &Method::Get, &format!("/pools/{}/referrers", POOL));
+    assert_eq!(status, 200);
+    assert_eq!(body[0]["referrer"], referrer.to_string());
+}
+
+#[test]
+fn api_serves_pools_positions_and_history() {
+    let store = replayed();
+    let get = |url: &str| api::handle(&store, &Method::Get, url);
+
+    let (status, pools) = get("/pools");
+    assert_eq!(status, 200);
+    assert_eq!(pools[0]["pool"], POOL);
+    assert_eq!(pools[0]["tvl"], "2560000");
+
+    let (status, pool) = get(&format!("/pools/{}", POOL));
+    assert_eq!(status, 200);
+    assert_eq!(pool["fees"][0], json!({ "kind": "deploy", "mint": null, "total": "100000000", "count": 1 }));
+
+    let (status, position) = get(&format!("/pools/{}/users/{}", POOL, ALICE));
+    assert_eq!(status, 200);
+    assert_eq!(position["claimed"], "55000");
+
+    let (status, events) = get(&format!("/pools/{}/users/{}/events", POOL, ALICE));
+    assert_eq!(status, 200);
+    let kinds: Vec<_> = events
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|event| event["kind"] != "FeeCharged")
+        .map(|event| event["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds, ["Deposit", "RewardClaim", "RewardClaim", "Withdraw"]);
+    assert_eq!(events[8]["data"]["amount"], "480200");
+    assert_eq!(events[8]["slot"], 300);
+
+    let (status, positions) = get(&format!("/users/{}", BOB));
+    assert_eq!(status, 200);
+    assert_eq!(positions.as_array().unwrap().len(), 1);
+
+    assert_eq!(get(&format!("/pools/{}", Pubkey::new_unique())).0, 404);
+    assert_eq!(get(&format!("/pools/{}/users/{}", POOL, CREATOR)).0, 404);
+    assert_eq!(get("/pools/not-an-address").0, 400);
+    assert_eq!(get("/unknown").0, 404);
+    assert_eq!(api::handle(&store, &Method::Post, "/pools").0, 405);
+}
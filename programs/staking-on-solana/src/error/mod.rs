@@ -271,4 +271,142 @@ pub enum BrewStakingError {
     /// when the remainder after a withdraw stake account is less than min_stake
     #[msg("Stake account remainder too low")]
     StakeAccountRemainderTooLow, // 6085 0x17c5
+
+    #[msg("Calculation overflow")]
+    MathOverflow,
+
+    #[msg("Reward mint decimals must be 9 or less")]
+    InvalidRewardDecimals,
+
+    #[msg("Transfer hook mints must be allow-listed by the platform")]
+    HookMintNotAllowed,
+
+    #[msg("Fewer tokens reached the pool than the minimum")]
+    ReceivedBelowMinimum,
+
+    #[msg("A token account is required unless the mint is native SOL")]
+    TokenAccountRequired,
+
+    #[msg("Native SOL payouts need the user's wSOL account")]
+    WsolAccountRequired,
+
+    #[msg("Treasury fee share must be 10000 basis points or less")]
+    InvalidTreasuryFeeShare,
+
+    #[msg("Treasury token account is not owned by the platform treasury")]
+    InvalidTreasuryTokenAccount,
+
+    #[msg("Invalid reward performance fee")]
+    InvalidPerformanceFee,
+
+    #[msg("Reward-share pools need the treasury's reward token account")]
+    TreasuryRewardAccountRequired,
+
+    #[msg("Fee discount must be 10000 basis points or less")]
+    InvalidFeeDiscount,
+
+    #[msg("Stake amount is below the pool's minimum")]
+    StakeBelowMinimum,
+
+    #[msg("Stake would exceed the pool's per-wallet cap")]
+    WalletStakeCapExceeded,
+
+    #[msg("Stake would exceed the pool's total stake cap")]
+    PoolStakeCapExceeded,
+
+    #[msg("Minimum stake is above the per-wallet cap")]
+    InvalidStakeLimits,
+
+    #[msg("Allowlisted pools need a proof of the staker's allocation")]
+    AllowlistProofRequired,
+
+    #[msg("Allowlist proof does not match the pool's root")]
+    InvalidAllowlistProof,
+
+    #[msg("Stake would exceed the staker's allowlist allocation")]
+    AllocationExceeded,
+
+    #[msg("Boost multiplier must be between 10000 and 30000 basis points")]
+    InvalidBoostMultiplier,
+
+    #[msg("Pool has no boost collection")]
+    BoostNotEnabled,
+
+    #[msg("NFT is not a verified member of the pool's boost collection")]
+    InvalidBoostNft,
+
+    #[msg("An NFT is already locked for this stake")]
+    BoostAlreadyLocked,
+
+    #[msg("Lock multiplier must be 0 or between 10000 and 40000 basis points")]
+    InvalidLockMultiplier,
+
+    #[msg("Pool has no stake locks")]
+    LocksNotEnabled,
+
+    #[msg("Locks run from 1 to 52 weeks")]
+    InvalidLockWeeks,
+
+    #[msg("A lock can only be extended")]
+    LockCannotShorten,
+
+    #[msg("Stake is locked")]
+    StakeLocked,
+
+    #[msg("Pools with an allowlist or a per-wallet cap only take stakes in position 0")]
+    PositionsNotAllowed,
+
+    #[msg("Positions and their accounts don't match")]
+    InvalidPositions,
+
+    #[msg("Unbonding period can't be longer than 30 days")]
+    InvalidUnbondingPeriod,
+
+    #[msg("Vesting period can't be longer than 365 days, and forfeits need one")]
+    InvalidVestingPeriod,
+
+    #[msg("No vested rewards to withdraw")]
+    NothingVested,
+
+    #[msg("Rewards of vesting pools can't be compounded")]
+    VestingPoolCompound,
+
+    #[msg("Referral share can't be over 50%")]
+    InvalidReferralShare,
+
+    #[msg("Wallets can't refer themselves")]
+    SelfReferral,
+
+    #[msg("Referred stakes need their referrer's account")]
+    ReferrerAccountRequired,
+
+    #[msg("Referrer account of a stake without a referrer")]
+    InvalidReferrerAccount,
+
+    #[msg("No referral earnings to claim")]
+    NoReferralEarnings,
+
+    #[msg("Rewards go to the associated token account of the position's reward recipient")]
+    InvalidRewardRecipientAccount,
+
+    #[msg("Positions pay their rewards to different recipients")]
+    RewardRecipientMismatch,
+
+    #[msg("Pools and their state accounts don't match")]
+    InvalidPoolAccounts,
+
+    #[msg("Only positions with nothing staked and no boost NFT can be closed")]
+    PositionNotEmpty,
+
+    #[msg("Claim or withdraw the position's rewards first")]
+    UnclaimedReward,
+
+    #[msg("Pools can only be closed after their reward period")]
+    PoolNotEnded,
+
+    #[msg("Pools can only be closed once stakes, tickets, vesting rewards, referral earnings and boost NFTs are withdrawn")]
+    PoolNotEmpty,
+
+    #[msg("Withdraw or cancel the position's withdrawal tickets first")]
+    OpenWithdrawalTickets,
 }
@@ -1,33 +1,287 @@
 use anchor_lang::prelude::*;
 
+// Events are emitted with `emit_cpi!`, so they are recorded as inner
+// instructions and can't be lost to log truncation. Every event names the
+// pool it belongs to and the slot it happened in; user events also carry the
+// user's stake and the pool's total stake after the instruction.
+
+#[event]
+pub struct PoolCreated {
+    pub pool_config: Pubkey,
+    pub creator: Pubkey,
+    pub pool_id: String,
+    pub stake_mint: Pubkey,
+    pub reward_mint: Pubkey,
+    pub stake_fee: u16,
+    pub unstake_fee: u16,
+    pub reward_per_slot: u64,
+    pub duration: u16,
+    pub initial_funding: u64,
+    pub slot: u64,
+}
+
 #[event]
 pub struct NewStartAndEndSlots {
+    pub pool_config: Pubkey,
     pub start_slot: u64,
     pub end_slot: u64,
+    pub slot: u64,
 }
 
 #[event]
 pub struct RewardsStop {
+    pub pool_config: Pubkey,
     pub end_slot: u64,
+    /// Unearned reward tokens returned to the deployer
+    pub refund: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RewardsFunded {
+    pub pool_config: Pubkey,
+    pub funder: Pubkey,
+    pub amount: u64,
+    /// Reward tokens left in the pool after funding
+    pub reward_amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct PoolUpdated {
+    pub pool_config: Pubkey,
+    pub acc_token_per_share: u64,
+    pub should_total_paid: u64,
+    pub total_earned: u64,
+    pub slot: u64,
 }
 
 #[event]
 pub struct Deposit {
+    pub pool_config: Pubkey,
     pub staker: Pubkey,
+    /// Amount added to the user's stake
+    pub amount: u64,
+    /// Stake fee taken on top of `amount`
+    pub fee: u64,
+    pub staked_amount: u64,
+    pub total_staked: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct Withdraw {
+    pub pool_config: Pubkey,
+    pub user: Pubkey,
+    /// Amount sent back to the user, or into a withdrawal ticket on pools
+    /// with an unbonding period
     pub amount: u64,
+    /// Unstake fee taken out of the withdrawal
+    pub fee: u64,
+    pub staked_amount: u64,
+    pub total_staked: u64,
+    pub slot: u64,
+}
+
+/// Follows the `Deposit`, `Withdraw`, `RewardClaim`, `Compound` or
+/// `TicketCancelled` of a user with the position it touched. Their `staked_amount` is that position's stake
+#[event]
+pub struct PositionChanged {
+    pub pool_config: Pubkey,
+    pub user: Pubkey,
+    pub position: u32,
+    pub staked_amount: u64,
+    pub slot: u64,
 }
 
 #[event]
 pub struct RewardClaim {
+    pub pool_config: Pubkey,
     pub claimer: Pubkey,
     pub amount: u64,
+    pub staked_amount: u64,
+    pub total_staked: u64,
+    pub slot: u64,
 }
 
 #[event]
 pub struct Compound {
+    pub pool_config: Pubkey,
     pub compounder: Pubkey,
+    /// Reward harvested
     pub amount: u64,
+    /// Stake credited for it, after the swap when the pool mints differ
+    pub staked: u64,
+    pub staked_amount: u64,
+    pub total_staked: u64,
+    pub slot: u64,
 }
+
+#[event]
+pub struct BoostChanged {
+    pub pool_config: Pubkey,
+    pub user: Pubkey,
+    pub position: u32,
+    /// Locked NFT, or `None` after unlocking
+    pub mint: Option<Pubkey>,
+    pub boost_multiplier: u16,
+    pub boosted_amount: u64,
+    pub total_boosted: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct LockChanged {
+    pub pool_config: Pubkey,
+    pub user: Pubkey,
+    pub position: u32,
+    pub lock_end_slot: u64,
+    /// 0 once the lock has ended
+    pub lock_multiplier: u16,
+    pub boosted_amount: u64,
+    pub total_boosted: u64,
+    pub slot: u64,
+}
+
+/// Pending reward moved into a position's vesting schedule instead of being
+/// paid out. `withdraw_vested` pays it with a `RewardClaim`
+#[event]
+pub struct RewardVested {
+    pub pool_config: Pubkey,
+    pub user: Pubkey,
+    pub position: u32,
+    pub amount: u64,
+    /// Reward of the position still vesting, including `amount`
+    pub vesting_amount: u64,
+    pub vesting_end_slot: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RewardForfeited {
+    pub pool_config: Pubkey,
+    pub user: Pubkey,
+    pub position: u32,
+    /// Unvested reward shared out among the remaining stakers
+    pub amount: u64,
+    pub slot: u64,
+}
+
+/// A stake by a referred position, for attributing volume to its referrer
+#[event]
+pub struct ReferredStake {
+    pub pool_config: Pubkey,
+    pub user: Pubkey,
+    pub position: u32,
+    pub referrer: Pubkey,
+    /// Amount added to the stake
+    pub amount: u64,
+    /// This stake recorded the referrer
+    pub new_referee: bool,
+    pub slot: u64,
+}
+
+#[event]
+pub struct ReferralClaimed {
+    pub pool_config: Pubkey,
+    pub referrer: Pubkey,
+    pub stake_amount: u64,
+    pub reward_amount: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct RewardRecipientSet {
+    pub pool_config: Pubkey,
+    pub user: Pubkey,
+    pub position: u32,
+    /// `None` pays the staker again
+    pub recipient: Option<Pubkey>,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TicketOpened {
+    pub pool_config: Pubkey,
+    pub user: Pubkey,
+    pub position: u32,
+    pub ticket: Pubkey,
+    pub amount: u64,
+    pub unlock_slot: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct PositionClosed {
+    pub pool_config: Pubkey,
+    pub user: Pubkey,
+    pub position: u32,
+    pub slot: u64,
+}
+
+#[event]
+pub struct PoolClosed {
+    pub pool_config: Pubkey,
+    pub owner: Pubkey,
+    /// Reward tokens left in the pool, returned to the owner
+    pub refund: u64,
+    pub slot: u64,
+}
+
+#[event]
+pub struct TicketClosed {
+    pub pool_config: Pubkey,
+    pub user: Pubkey,
+    pub position: u32,
+    pub ticket: Pubkey,
+    pub amount: u64,
+    /// The tokens went back into the stake instead of to the user
+    pub cancelled: bool,
+    pub slot: u64,
+}
+
+/// A withdrawal ticket's tokens went back into its position. They never left
+/// the pool, so this is no new deposit
+#[event]
+pub struct TicketCancelled {
+    pub pool_config: Pubkey,
+    pub user: Pubkey,
+    pub position: u32,
+    pub ticket: Pubkey,
+    pub amount: u64,
+    pub staked_amount: u64,
+    pub total_staked: u64,
+    pub slot: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeeKind {
+    /// Lamports paid to the treasury for creating a pool
+    Deploy,
+    /// Lamports paid to the treasury for every user action
+    Performance,
+    /// Stake tokens paid to the pool owner or the treasury on deposit
+    Stake,
+    /// Stake tokens paid to the pool owner or the treasury on withdrawal
+    Unstake,
+    /// Reward tokens paid to the treasury out of a claim or compound
+    Reward,
+    /// Stake fee or reward tokens credited to a staker's referrer
+    Referral,
+}
+
+#[event]
+pub struct FeeCharged {
+    pub pool_config: Pubkey,
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    pub kind: FeeKind,
+    /// Token mint of the fee, or `None` for lamports
+    pub mint: Option<Pubkey>,
+    pub amount: u64,
+    pub slot: u64,
+}
+
 // #[event]
 // pub struct ChangeAuthorityEvent {
 //     pub state: Pubkey,
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use crate::state::*;
+
+pub fn handler(ctx: Context<AllowHookMint>, allowed: bool) -> Result<()> {
+    let allowance = &mut ctx.accounts.allowance;
+
+    allowance.platform = ctx.accounts.platform.key();
+    allowance.mint = ctx.accounts.mint.key();
+    allowance.allowed = allowed;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AllowHookMint<'info> {
+    #[account(
+        init_if_needed,
+        payer = treasury,
+        space = HOOK_MINT_ALLOWANCE_SIZE,
+        seeds = [HOOK_MINT_ALLOWANCE_SEED, platform.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub allowance: Account<'info, HookMintAllowance>,
+
+    #[account(has_one = treasury)]
+    pub platform: Account<'info, PlatformInfo>,
+
+    #[account(mut)]
+    pub treasury: Signer<'info>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub system_program: Program<'info, System>,
+}
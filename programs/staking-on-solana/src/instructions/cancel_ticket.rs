@@ -0,0 +1,77 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::utils::*;
+use crate::error::*;
+use crate::events::*;
+
+pub fn handler(ctx: Context<CancelTicket>) -> Result<()> {
+    let pool_config = &ctx.accounts.pool_config_account;
+    let pool_state = &mut ctx.accounts.pool_state_account;
+    let user_info = &mut ctx.accounts.user_info;
+    let ticket = &ctx.accounts.withdrawal_ticket;
+
+    let clock = Clock::get()?;
+
+    accounting::cancel_ticket(pool_config, pool_state, user_info, ticket, clock.slot)?;
+
+    emit_cpi!(TicketCancelled {
+        pool_config: pool_config.key(),
+        user: ctx.accounts.user.key(),
+        position: ticket.position,
+        ticket: ticket.key(),
+        amount: ticket.amount,
+        staked_amount: user_info.staked_amount,
+        total_staked: pool_state.total_staked,
+        slot: clock.slot,
+    });
+    emit_cpi!(TicketClosed {
+        pool_config: pool_config.key(),
+        user: ctx.accounts.user.key(),
+        position: ticket.position,
+        ticket: ticket.key(),
+        amount: ticket.amount,
+        cancelled: true,
+        slot: clock.slot,
+    });
+    emit_cpi!(PositionChanged {
+        pool_config: pool_config.key(),
+        user: ctx.accounts.user.key(),
+        position: ticket.position,
+        staked_amount: user_info.staked_amount,
+        slot: clock.slot,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelTicket<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub pool_config_account: Box<Account<'info, PoolConfig>>,
+
+    #[account(mut, address = pool_config_account.state_addr)]
+    pub pool_state_account: Box<Account<'info, PoolState>>,
+
+    #[account(
+        mut,
+        close = user,
+        constraint = withdrawal_ticket.pool_config == pool_config_account.key() @ BrewStakingError::InvalidDelayedUnstakeTicket,
+        constraint = withdrawal_ticket.beneficiary == user.key() @ BrewStakingError::WrongBeneficiary
+    )]
+    pub withdrawal_ticket: Box<Account<'info, WithdrawalTicket>>,
+
+    #[account(
+        mut,
+        seeds = [
+            pool_config_account.key().as_ref(),
+            user.key().as_ref(),
+            &position_seed(withdrawal_ticket.position),
+        ],
+        bump
+    )]
+    pub user_info: Box<Account<'info, UserInfo>>,
+}
@@ -0,0 +1,232 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::token_interface::{ Mint, TokenAccount, TokenInterface };
+
+use crate::state::*;
+use crate::utils::*;
+use crate::error::*;
+use crate::events::*;
+
+pub fn handler<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimAll<'info>>, positions: Vec<u32>) -> Result<()> {
+    let pool_config = &ctx.accounts.pool_config_account;
+    let pool_state = &mut ctx.accounts.pool_state_account;
+    let platform = &ctx.accounts.platform;
+    let discounts = accounting::fee_discounts(
+        ctx.accounts.pool_fee_exemption.as_deref(),
+        ctx.accounts.platform_fee_exemption.as_deref()
+    );
+
+    let clock = Clock::get()?;
+
+    // The positions' stake accounts come first, then any transfer hook accounts
+    require!(
+        !positions.is_empty() && positions.len() <= ctx.remaining_accounts.len(),
+        BrewStakingError::InvalidPositions
+    );
+    let (position_accounts, hook_accounts) = ctx.remaining_accounts.split_at(positions.len());
+
+    let pool_key = pool_config.key();
+    let claimer_key = ctx.accounts.claimer.key();
+    let mut reward: u64 = 0;
+    let mut reward_referral: u64 = 0;
+    let mut reward_recipient = None;
+    let mut staked_amounts = Vec::with_capacity(positions.len());
+    for (&position, account) in positions.iter().zip(position_accounts) {
+        let (address, _) = Pubkey::find_program_address(
+            &[pool_key.as_ref(), claimer_key.as_ref(), &position_seed(position)],
+            ctx.program_id
+        );
+        require!(account.key() == address && account.is_writable, BrewStakingError::InvalidPositions);
+
+        let mut user_info = Account::<UserInfo>::try_from(account)?;
+        // One payout can only go to one wallet
+        require!(
+            *reward_recipient.get_or_insert(user_info.reward_recipient) == user_info.reward_recipient,
+            BrewStakingError::RewardRecipientMismatch
+        );
+        let amounts = accounting::claim_reward(pool_config, pool_state, &mut user_info, clock.slot)?;
+        reward = reward.checked_add(amounts.reward).ok_or(BrewStakingError::MathOverflow)?;
+        // The referred positions must all share the one referrer account
+        let cut = accounting::referral_reward(
+            pool_config,
+            pool_state,
+            &user_info,
+            ctx.accounts.referrer_info.as_deref_mut().map(|info| &mut **info),
+            amounts.reward
+        )?;
+        reward_referral += cut;
+        if amounts.vested > 0 {
+            emit_cpi!(RewardVested {
+                pool_config: pool_key,
+                user: claimer_key,
+                position,
+                amount: amounts.vested,
+                vesting_amount: user_info.vesting_amount,
+                vesting_end_slot: user_info.vesting_end_slot,
+                slot: clock.slot,
+            });
+        }
+        staked_amounts.push((position, user_info.staked_amount));
+        user_info.exit(ctx.program_id)?;
+    }
+
+    // Claiming nothing is free
+    if reward == 0 {
+        return Ok(());
+    }
+
+    // Transfer Performance Fee from user to treasury
+    let performance_fee = accounting::flat_performance_fee(pool_config, platform, discounts);
+    let user_balance = ctx.accounts.claimer.to_account_info().lamports();
+    require!(user_balance > performance_fee, BrewStakingError::InsufficientDeployFee);
+
+    let cpi_program = ctx.accounts.system_program.to_account_info();
+    let cpi_accounts = system_program::Transfer {
+        from: ctx.accounts.claimer.to_account_info(),
+        to: ctx.accounts.treasury.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+    system_program::transfer(cpi_ctx, performance_fee)?;
+
+    if performance_fee > 0 {
+        emit_cpi!(FeeCharged {
+            pool_config: pool_config.key(),
+            payer: ctx.accounts.claimer.key(),
+            recipient: ctx.accounts.treasury.key(),
+            kind: FeeKind::Performance,
+            mint: None,
+            amount: performance_fee,
+            slot: clock.slot,
+        });
+    }
+
+    // Transfer the user his reward so far
+    let wsol_seeds: &[&[u8]] = &[
+        WSOL_SEED,
+        pool_key.as_ref(),
+        claimer_key.as_ref(),
+        &[ctx.bumps.wsol_account],
+    ];
+    let payout = payout::RewardPayout {
+        pool_config,
+        platform,
+        discounts,
+        user: ctx.accounts.claimer.to_account_info(),
+        admin: ctx.accounts.admin.to_account_info(),
+        pool_reward_token_vault: ctx.accounts.pool_reward_token_vault.to_account_info(),
+        treasury_reward_token_vault: ctx.accounts.treasury_reward_token_vault.as_ref().map(|vault| vault.to_account_info()),
+        referrer_info: ctx.accounts.referrer_info.as_ref().map(|info| info.key()),
+        reward_mint: &ctx.accounts.reward_mint,
+        token_program: &ctx.accounts.token_program,
+        system_program: &ctx.accounts.system_program,
+        remaining_accounts: hook_accounts,
+        slot: clock.slot,
+    };
+    let (reward, fee_events) = payout.charge_fees(reward, reward_referral)?;
+    payout.send(
+        reward,
+        reward_recipient.flatten(),
+        &ctx.accounts.user_reward_token_vault,
+        &ctx.accounts.wsol_account,
+        wsol_seeds
+    )?;
+    for event in fee_events {
+        emit_cpi!(event);
+    }
+
+    emit_cpi!(RewardClaim {
+        pool_config: pool_config.key(),
+        claimer: ctx.accounts.claimer.key(),
+        amount: reward,
+        staked_amount: staked_amounts.iter().map(|(_, staked_amount)| staked_amount).sum(),
+        total_staked: pool_state.total_staked,
+        slot: clock.slot,
+    });
+    for (position, staked_amount) in staked_amounts {
+        emit_cpi!(PositionChanged {
+            pool_config: pool_key,
+            user: claimer_key,
+            position,
+            staked_amount,
+            slot: clock.slot,
+        });
+    }
+
+    Ok(())
+}
+
+// Claims the rewards of several of the claimer's positions with one payout and
+// one performance fee. The positions' stake accounts are passed writable in
+// `remaining_accounts`, in the order of `positions`. They must share one
+// reward recipient
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimAll<'info> {
+    /// CHECK:
+    #[account(mut)]
+    pub claimer: Signer<'info>,
+
+    /// CHECK:
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// CHECK:
+    #[account(mut)]
+    pub treasury: AccountInfo<'info>,
+
+    pub pool_config_account: Account<'info, PoolConfig>,
+
+    #[account(mut, address = pool_config_account.state_addr)]
+    pub pool_state_account: Account<'info, PoolState>,
+
+    pub platform: Account<'info, PlatformInfo>,
+
+    /// The referrer's earnings account, required when a position's referrer
+    /// gets a share of its rewards. Every such position must have this referrer
+    #[account(mut, constraint = referrer_info.pool_config == pool_config_account.key())]
+    pub referrer_info: Option<Box<Account<'info, ReferrerInfo>>>,
+
+    /// The reward recipient's associated token account on positions that have
+    /// one. Omitted to be paid native SOL rewards
+    #[account(mut)]
+    pub user_reward_token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, address = pool_config_account.pool_reward_token_vault)]
+    pub pool_reward_token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Required on reward-share pools
+    #[account(
+        mut,
+        constraint = treasury_reward_token_vault.owner == platform.treasury @ BrewStakingError::InvalidTreasuryTokenAccount
+    )]
+    pub treasury_reward_token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = pool_config_account.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: created and closed again to pay out native SOL
+    #[account(
+        mut,
+        seeds = [WSOL_SEED, pool_config_account.key().as_ref(), claimer.key().as_ref()],
+        bump
+    )]
+    pub wsol_account: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// The user's fee discount from the pool owner, if any
+    #[account(
+        seeds = [FEE_EXEMPTION_SEED, pool_config_account.key().as_ref(), claimer.key().as_ref()],
+        bump
+    )]
+    pub pool_fee_exemption: Option<Account<'info, FeeExemption>>,
+
+    /// The user's fee discount from the platform, if any
+    #[account(
+        seeds = [FEE_EXEMPTION_SEED, platform.key().as_ref(), claimer.key().as_ref()],
+        bump
+    )]
+    pub platform_fee_exemption: Option<Account<'info, FeeExemption>>,
+
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,86 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{ Mint, TokenAccount, TokenInterface };
+
+use crate::state::*;
+use crate::utils::*;
+use crate::events::*;
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, ClaimReferral<'info>>) -> Result<()> {
+    let clock = Clock::get()?;
+
+    let (stake_amount, reward_amount) = accounting::claim_referral(
+        &mut ctx.accounts.pool_state_account,
+        &mut ctx.accounts.referrer_info
+    )?;
+
+    // Transfer the referrer's earnings out of the pool's vaults
+    token::transfer_checked(
+        &ctx.accounts.token_program,
+        ctx.accounts.pool_stake_token_vault.to_account_info(),
+        &ctx.accounts.stake_mint,
+        ctx.accounts.referrer_stake_token_vault.to_account_info(),
+        ctx.accounts.admin.to_account_info(),
+        ctx.remaining_accounts,
+        stake_amount
+    )?;
+    token::transfer_checked(
+        &ctx.accounts.token_program,
+        ctx.accounts.pool_reward_token_vault.to_account_info(),
+        &ctx.accounts.reward_mint,
+        ctx.accounts.referrer_reward_token_vault.to_account_info(),
+        ctx.accounts.admin.to_account_info(),
+        ctx.remaining_accounts,
+        reward_amount
+    )?;
+
+    emit_cpi!(ReferralClaimed {
+        pool_config: ctx.accounts.pool_config_account.key(),
+        referrer: ctx.accounts.referrer.key(),
+        stake_amount,
+        reward_amount,
+        slot: clock.slot,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClaimReferral<'info> {
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+    /// CHECK:
+    pub admin: Signer<'info>,
+
+    pub pool_config_account: Box<Account<'info, PoolConfig>>,
+
+    #[account(mut, address = pool_config_account.state_addr)]
+    pub pool_state_account: Box<Account<'info, PoolState>>,
+
+    #[account(
+        mut,
+        seeds = [REFERRER_SEED, pool_config_account.key().as_ref(), referrer.key().as_ref()],
+        bump
+    )]
+    pub referrer_info: Box<Account<'info, ReferrerInfo>>,
+
+    #[account(mut)]
+    pub referrer_stake_token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub referrer_reward_token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, address = pool_config_account.pool_stake_token_vault)]
+    pub pool_stake_token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, address = pool_config_account.pool_reward_token_vault)]
+    pub pool_reward_token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = pool_config_account.stake_mint)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(address = pool_config_account.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
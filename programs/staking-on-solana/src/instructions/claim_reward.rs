@@ -1,21 +1,58 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
-use anchor_spl::token::{ self, TokenAccount, Transfer };
+use anchor_spl::token_interface::{ Mint, TokenAccount, TokenInterface };
 
 use crate::state::*;
 use crate::utils::*;
 use crate::error::*;
 use crate::events::*;
 
-pub fn handler(ctx: Context<ClaimReward>) -> Result<()> {
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, ClaimReward<'info>>, position: u32) -> Result<()> {
     let pool_config = &ctx.accounts.pool_config_account;
     let pool_state = &mut ctx.accounts.pool_state_account;
     let user_info = &mut ctx.accounts.user_info;
+
+    let clock = Clock::get()?;
+
+    let amounts = accounting::claim_reward(pool_config, pool_state, user_info, clock.slot)?;
+    if amounts.vested > 0 {
+        emit_cpi!(RewardVested {
+            pool_config: pool_config.key(),
+            user: ctx.accounts.claimer.key(),
+            position,
+            amount: amounts.vested,
+            vesting_amount: user_info.vesting_amount,
+            vesting_end_slot: user_info.vesting_end_slot,
+            slot: clock.slot,
+        });
+    }
+
+    pay_reward(ctx, position, amounts.reward)
+}
+
+// Pays `reward` out of the pool to the claimer, less the performance fee.
+// Shared with `withdraw_vested`.
+pub fn pay_reward<'info>(ctx: Context<'_, '_, '_, 'info, ClaimReward<'info>>, position: u32, reward: u64) -> Result<()> {
+    let pool_config = &ctx.accounts.pool_config_account;
+    let pool_state = &mut ctx.accounts.pool_state_account;
+    let user_info = &ctx.accounts.user_info;
     let platform = &ctx.accounts.platform;
+    let discounts = accounting::fee_discounts(
+        ctx.accounts.pool_fee_exemption.as_deref(),
+        ctx.accounts.platform_fee_exemption.as_deref()
+    );
+
+    let clock = Clock::get()?;
+
+    // Claiming nothing is free
+    if reward == 0 {
+        return Ok(());
+    }
 
     // Transfer Performance Fee from user to treasury
+    let performance_fee = accounting::flat_performance_fee(pool_config, platform, discounts);
     let user_balance = ctx.accounts.claimer.to_account_info().lamports();
-    require!(user_balance > platform.performance_fee, BrewStakingError::InsufficientDeployFee);
+    require!(user_balance > performance_fee, BrewStakingError::InsufficientDeployFee);
 
     let cpi_program = ctx.accounts.system_program.to_account_info();
     let cpi_accounts = system_program::Transfer {
@@ -23,58 +60,87 @@ pub fn handler(ctx: Context<ClaimReward>) -> Result<()> {
         to: ctx.accounts.treasury.to_account_info(),
     };
     let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-    system_program::transfer(cpi_ctx, platform.performance_fee)?;
-
-    let _ = update_pool(pool_config, pool_state);
-
-    if user_info.staked_amount == 0 {
-        return Ok(());
+    system_program::transfer(cpi_ctx, performance_fee)?;
+
+    if performance_fee > 0 {
+        emit_cpi!(FeeCharged {
+            pool_config: pool_config.key(),
+            payer: ctx.accounts.claimer.key(),
+            recipient: ctx.accounts.treasury.key(),
+            kind: FeeKind::Performance,
+            mint: None,
+            amount: performance_fee,
+            slot: clock.slot,
+        });
     }
 
-    let precision_factor = get_precision_factor(pool_config);
+    // The referrer's cut of the reward stays in the vault until they claim it
+    let reward_referral = accounting::referral_reward(
+        pool_config,
+        pool_state,
+        user_info,
+        ctx.accounts.referrer_info.as_deref_mut().map(|info| &mut **info),
+        reward
+    )?;
 
     // Transfer the user his reward so far
-    let pending =
-        (user_info.staked_amount * pool_state.acc_token_per_share) / precision_factor -
-        user_info.reward_debt;
-
-    if pending > 0 {
-        require!(
-            available_reward_tokens(pool_config, pool_state) >= pending,
-            BrewStakingError::InsufficientReward
-        );
-
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.pool_reward_token_vault.to_account_info(),
-            to: ctx.accounts.user_reward_token_vault.to_account_info(),
-            authority: ctx.accounts.admin.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token::transfer(cpi_ctx, pending)?;
-
-        pool_state.reward_amount -= pending;
-
-        pool_state.total_earned = if pool_state.total_earned > pending {
-            pool_state.total_earned - pending
-        } else {
-            0
-        };
-        pool_state.paid_rewards += pending;
-
-        emit!(RewardClaim {
-            claimer: ctx.accounts.claimer.key(),
-            amount: pending,
-        });
+    let pool_key = pool_config.key();
+    let claimer_key = ctx.accounts.claimer.key();
+    let wsol_seeds: &[&[u8]] = &[
+        WSOL_SEED,
+        pool_key.as_ref(),
+        claimer_key.as_ref(),
+        &[ctx.bumps.wsol_account],
+    ];
+    let payout = payout::RewardPayout {
+        pool_config,
+        platform,
+        discounts,
+        user: ctx.accounts.claimer.to_account_info(),
+        admin: ctx.accounts.admin.to_account_info(),
+        pool_reward_token_vault: ctx.accounts.pool_reward_token_vault.to_account_info(),
+        treasury_reward_token_vault: ctx.accounts.treasury_reward_token_vault.as_ref().map(|vault| vault.to_account_info()),
+        referrer_info: ctx.accounts.referrer_info.as_ref().map(|info| info.key()),
+        reward_mint: &ctx.accounts.reward_mint,
+        token_program: &ctx.accounts.token_program,
+        system_program: &ctx.accounts.system_program,
+        remaining_accounts: ctx.remaining_accounts,
+        slot: clock.slot,
+    };
+    let (reward, fee_events) = payout.charge_fees(reward, reward_referral)?;
+    payout.send(
+        reward,
+        user_info.reward_recipient,
+        &ctx.accounts.user_reward_token_vault,
+        &ctx.accounts.wsol_account,
+        wsol_seeds
+    )?;
+    for event in fee_events {
+        emit_cpi!(event);
     }
 
-    user_info.reward_debt =
-        (user_info.staked_amount * pool_state.acc_token_per_share) / precision_factor;
+    emit_cpi!(RewardClaim {
+        pool_config: pool_config.key(),
+        claimer: ctx.accounts.claimer.key(),
+        amount: reward,
+        staked_amount: user_info.staked_amount,
+        total_staked: pool_state.total_staked,
+        slot: clock.slot,
+    });
+    emit_cpi!(PositionChanged {
+        pool_config: pool_config.key(),
+        user: ctx.accounts.claimer.key(),
+        position,
+        staked_amount: user_info.staked_amount,
+        slot: clock.slot,
+    });
 
     Ok(())
 }
 
+#[event_cpi]
 #[derive(Accounts)]
+#[instruction(position: u32)]
 pub struct ClaimReward<'info> {
     /// CHECK:
     #[account(mut)]
@@ -88,23 +154,74 @@ pub struct ClaimReward<'info> {
     #[account(mut)]
     pub treasury: AccountInfo<'info>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [pool_config_account.key().as_ref(), claimer.key().as_ref(), &position_seed(position)],
+        bump
+    )]
     pub user_info: Account<'info, UserInfo>,
 
+    /// The referrer's earnings account, required when the position's referrer
+    /// gets a share of its rewards
+    #[account(
+        mut,
+        seeds = [
+            REFERRER_SEED,
+            pool_config_account.key().as_ref(),
+            user_info.referrer.unwrap_or_default().as_ref(),
+        ],
+        bump
+    )]
+    pub referrer_info: Option<Box<Account<'info, ReferrerInfo>>>,
+
     pub pool_config_account: Account<'info, PoolConfig>,
 
-    #[account(mut)]
+    #[account(mut, address = pool_config_account.state_addr)]
     pub pool_state_account: Account<'info, PoolState>,
 
     pub platform: Account<'info, PlatformInfo>,
 
+    /// The reward recipient's associated token account on positions that have
+    /// one. Omitted to be paid native SOL rewards
     #[account(mut)]
-    pub user_reward_token_vault: Account<'info, TokenAccount>,
-
-    #[account(mut)]
-    pub pool_reward_token_vault: Account<'info, TokenAccount>,
-
-    pub token_program: Program<'info, token::Token>,
+    pub user_reward_token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, address = pool_config_account.pool_reward_token_vault)]
+    pub pool_reward_token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Required on reward-share pools
+    #[account(
+        mut,
+        constraint = treasury_reward_token_vault.owner == platform.treasury @ BrewStakingError::InvalidTreasuryTokenAccount
+    )]
+    pub treasury_reward_token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = pool_config_account.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: created and closed again to pay out native SOL
+    #[account(
+        mut,
+        seeds = [WSOL_SEED, pool_config_account.key().as_ref(), claimer.key().as_ref()],
+        bump
+    )]
+    pub wsol_account: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// The user's fee discount from the pool owner, if any
+    #[account(
+        seeds = [FEE_EXEMPTION_SEED, pool_config_account.key().as_ref(), claimer.key().as_ref()],
+        bump
+    )]
+    pub pool_fee_exemption: Option<Account<'info, FeeExemption>>,
+
+    /// The user's fee discount from the platform, if any
+    #[account(
+        seeds = [FEE_EXEMPTION_SEED, platform.key().as_ref(), claimer.key().as_ref()],
+        bump
+    )]
+    pub platform_fee_exemption: Option<Account<'info, FeeExemption>>,
 
     pub system_program: Program<'info, System>,
 }
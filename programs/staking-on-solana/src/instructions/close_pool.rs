@@ -0,0 +1,64 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{ Mint, TokenAccount, TokenInterface };
+
+use crate::state::*;
+use crate::utils::*;
+use crate::events::*;
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, ClosePool<'info>>) -> Result<()> {
+    let pool_config = &ctx.accounts.pool_config_account;
+    let pool_state = &mut ctx.accounts.pool_state_account;
+
+    let clock = Clock::get()?;
+    let refund = accounting::close_pool(pool_config, pool_state, clock.slot)?;
+
+    // Sweep the reward tokens nobody earned back to the owner. The vaults stay
+    // open, as the other pools of the same mints share them
+    token::transfer_checked(
+        &ctx.accounts.token_program,
+        ctx.accounts.pool_reward_token_vault.to_account_info(),
+        &ctx.accounts.reward_mint,
+        ctx.accounts.owner_reward_token_vault.to_account_info(),
+        ctx.accounts.admin.to_account_info(),
+        ctx.remaining_accounts,
+        refund
+    )?;
+
+    emit_cpi!(PoolClosed {
+        pool_config: pool_config.key(),
+        owner: ctx.accounts.owner.key(),
+        refund,
+        slot: clock.slot,
+    });
+
+    Ok(())
+}
+
+// Closes a finished pool once every stake, ticket, vesting reward, referral
+// earning and boost NFT is withdrawn, returning its rent and leftover reward to
+// the owner
+#[event_cpi]
+#[derive(Accounts)]
+pub struct ClosePool<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    /// CHECK:
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = owner, close = owner)]
+    pub pool_config_account: Box<Account<'info, PoolConfig>>,
+
+    #[account(mut, address = pool_config_account.state_addr, close = owner)]
+    pub pool_state_account: Box<Account<'info, PoolState>>,
+
+    #[account(mut, address = pool_config_account.pool_reward_token_vault)]
+    pub pool_reward_token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner_reward_token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = pool_config_account.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
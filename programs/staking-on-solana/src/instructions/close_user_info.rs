@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::utils::*;
+use crate::events::*;
+
+pub fn handler(ctx: Context<CloseUserInfo>, position: u32) -> Result<()> {
+    let pool_config = &ctx.accounts.pool_config_account;
+
+    let clock = Clock::get()?;
+
+    accounting::close_user_info(pool_config, &ctx.accounts.pool_state_account, &ctx.accounts.user_info, clock.slot)?;
+
+    emit_cpi!(PositionClosed {
+        pool_config: pool_config.key(),
+        user: ctx.accounts.user.key(),
+        position,
+        slot: clock.slot,
+    });
+
+    Ok(())
+}
+
+// Closes an empty stake position and returns its rent to the staker. Staking
+// into the position again opens a new one
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(position: u32)]
+pub struct CloseUserInfo<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub pool_config_account: Box<Account<'info, PoolConfig>>,
+
+    #[account(address = pool_config_account.state_addr)]
+    pub pool_state_account: Box<Account<'info, PoolState>>,
+
+    #[account(
+        mut,
+        close = user,
+        seeds = [pool_config_account.key().as_ref(), user.key().as_ref(), &position_seed(position)],
+        bump
+    )]
+    pub user_info: Box<Account<'info, UserInfo>>,
+}
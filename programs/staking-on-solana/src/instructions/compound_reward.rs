@@ -1,6 +1,6 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
-use anchor_spl::token::{ self, TokenAccount };
+use anchor_spl::token_interface::{ Mint, TokenAccount, TokenInterface };
 // use raydium_contract_instructions::amm_instruction;
 use amm_anchor::SwapBaseIn;
 
@@ -9,60 +9,82 @@ use crate::utils::*;
 use crate::error::*;
 use crate::events::*;
 
-pub fn handler(ctx: Context<CompoundReward>) -> Result<()> {
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, CompoundReward<'info>>, position: u32) -> Result<()> {
     let pool_config = &mut ctx.accounts.pool_config_account;
     let pool_state = &mut ctx.accounts.pool_state_account;
     let user_info = &mut ctx.accounts.user_info;
     let platform = &ctx.accounts.platform;
+    let discounts = accounting::fee_discounts(
+        ctx.accounts.pool_fee_exemption.as_deref().map(|exemption| &**exemption),
+        ctx.accounts.platform_fee_exemption.as_deref().map(|exemption| &**exemption)
+    );
+
+    let clock = Clock::get()?;
+
+    let harvested = accounting::harvest_for_compound(pool_config, pool_state, user_info, clock.slot)?;
+
+    // Compounding nothing is free
+    if harvested > 0 {
+        // Transfer Performance Fee from user to treasury
+        let performance_fee = accounting::flat_performance_fee(pool_config, platform, discounts);
+        let user_balance = ctx.accounts.user.to_account_info().lamports();
+        require!(user_balance > performance_fee, BrewStakingError::InsufficientDeployFee);
+
+        let cpi_program = ctx.accounts.system_program.to_account_info();
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.user.to_account_info(),
+            to: ctx.accounts.treasury.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        system_program::transfer(cpi_ctx, performance_fee)?;
+
+        if performance_fee > 0 {
+            emit_cpi!(FeeCharged {
+                pool_config: pool_config.key(),
+                payer: ctx.accounts.user.key(),
+                recipient: ctx.accounts.treasury.key(),
+                kind: FeeKind::Performance,
+                mint: None,
+                amount: performance_fee,
+                slot: clock.slot,
+            });
+        }
+    }
 
-    // Transfer Performance Fee from user to treasury
-    let user_balance = ctx.accounts.user.to_account_info().lamports();
-    require!(user_balance > platform.performance_fee, BrewStakingError::InsufficientDeployFee);
-
-    let cpi_program = ctx.accounts.system_program.to_account_info();
-    let cpi_accounts = system_program::Transfer {
-        from: ctx.accounts.user.to_account_info(),
-        to: ctx.accounts.treasury.to_account_info(),
+    // The referrer's cut of the reward stays in the vault until they claim it
+    let reward_referral = accounting::referral_reward(
+        pool_config,
+        pool_state,
+        user_info,
+        ctx.accounts.referrer_info.as_deref_mut().map(|info| &mut **info),
+        harvested
+    )?;
+
+    // The fees come out of the reward as on a claim; the rest is restaked
+    let payout = payout::RewardPayout {
+        pool_config,
+        platform,
+        discounts,
+        user: ctx.accounts.user.to_account_info(),
+        admin: ctx.accounts.admin.to_account_info(),
+        pool_reward_token_vault: ctx.accounts.pool_reward_token_vault.to_account_info(),
+        treasury_reward_token_vault: ctx.accounts.treasury_reward_token_vault.as_ref().map(|vault| vault.to_account_info()),
+        referrer_info: ctx.accounts.referrer_info.as_ref().map(|info| info.key()),
+        reward_mint: &ctx.accounts.reward_mint,
+        token_program: &ctx.accounts.token_program,
+        system_program: &ctx.accounts.system_program,
+        remaining_accounts: ctx.remaining_accounts,
+        slot: clock.slot,
     };
-    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-    system_program::transfer(cpi_ctx, platform.performance_fee)?;
-
-    let _ = update_pool(pool_config, pool_state);
-
-    if user_info.staked_amount == 0 {
-        return Ok(());
+    let (mut pending, fee_events) = payout.charge_fees(harvested, reward_referral)?;
+    for event in fee_events {
+        emit_cpi!(event);
     }
 
-    let precision_factor = get_precision_factor(pool_config);
-
-    // Transfer the user his reward so far
-    let mut pending =
-        (user_info.staked_amount * pool_state.acc_token_per_share) / precision_factor -
-        user_info.reward_debt;
-
     if pending > 0 {
-        require!(
-            available_reward_tokens(pool_config, pool_state) >= pending,
-            BrewStakingError::InsufficientReward
-        );
-
-        if pool_state.total_earned > pending {
-            pool_state.total_earned -= pending;
-        } else {
-            pool_state.total_earned = 0;
-        }
-
-        pool_state.paid_rewards += pending;
-
-        emit!(Compound {
-            compounder: ctx.accounts.user.key(),
-            amount: pending,
-        });
-
-        // swap stake token to reward token
+        // swap reward token to stake token
         if pool_config.stake_mint != pool_config.reward_mint {
-            let pool_reward_balance_before = ctx.accounts.pool_reward_token_vault.amount;
-            msg!("pool_reward_balance_before {}", pool_reward_balance_before);
+            let pool_stake_balance_before = ctx.accounts.pool_stake_token_vault.amount;
 
             let minimum_amount_out = 1;
             /*
@@ -149,29 +171,41 @@ pub fn handler(ctx: Context<CompoundReward>) -> Result<()> {
 
             // Create a CpiContext with the specified accounts and program
             let cpi_ctx = CpiContext::new(swap_base_in_program, swap_base_in_accounts);
-            let _ = amm_anchor::swap_base_in(cpi_ctx, pending, minimum_amount_out);
+            amm_anchor::swap_base_in(cpi_ctx, pending, minimum_amount_out)?;
 
-            let pool_reward_balance_after = ctx.accounts.pool_reward_token_vault.amount;
-            msg!("pool_reward_balance_after {}", pool_reward_balance_after);
-
-            pending = pool_reward_balance_after - pool_reward_balance_before;
+            // Restake what the swap actually paid into the stake vault
+            ctx.accounts.pool_stake_token_vault.reload()?;
+            pending = ctx.accounts.pool_stake_token_vault.amount - pool_stake_balance_before;
         }
+    }
 
-        pool_state.total_staked += pending;
-        user_info.staked_amount += pending;
+    accounting::restake_reward(pool_config, pool_state, user_info, pending)?;
 
-        emit!(Deposit {
-            staker: ctx.accounts.user.key(),
-            amount: pending,
+    if harvested > 0 {
+        emit_cpi!(Compound {
+            pool_config: pool_config.key(),
+            compounder: ctx.accounts.user.key(),
+            amount: harvested,
+            staked: pending,
+            staked_amount: user_info.staked_amount,
+            total_staked: pool_state.total_staked,
+            slot: clock.slot,
+        });
+        emit_cpi!(PositionChanged {
+            pool_config: pool_config.key(),
+            user: ctx.accounts.user.key(),
+            position,
+            staked_amount: user_info.staked_amount,
+            slot: clock.slot,
         });
     }
 
-    user_info.reward_debt =
-        (user_info.staked_amount * pool_state.acc_token_per_share) / precision_factor;
     Ok(())
 }
 
+#[event_cpi]
 #[derive(Accounts)]
+#[instruction(position: u32)]
 pub struct CompoundReward<'info> {
     /// CHECK:
     #[account(mut)]
@@ -187,24 +221,51 @@ pub struct CompoundReward<'info> {
 
     pub pool_config_account: Box<Account<'info, PoolConfig>>,
 
-    #[account(mut)]
+    #[account(mut, address = pool_config_account.state_addr)]
     pub pool_state_account: Box<Account<'info, PoolState>>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [pool_config_account.key().as_ref(), user.key().as_ref(), &position_seed(position)],
+        bump
+    )]
     pub user_info: Box<Account<'info, UserInfo>>,
 
+    /// The referrer's earnings account, required when the position's referrer
+    /// gets a share of its rewards
+    #[account(
+        mut,
+        seeds = [
+            REFERRER_SEED,
+            pool_config_account.key().as_ref(),
+            user_info.referrer.unwrap_or_default().as_ref(),
+        ],
+        bump
+    )]
+    pub referrer_info: Option<Box<Account<'info, ReferrerInfo>>>,
+
     pub platform: Account<'info, PlatformInfo>,
 
-    #[account(mut)]
-    pub pool_stake_token_vault: Box<Account<'info, TokenAccount>>,
+    #[account(mut, address = pool_config_account.pool_stake_token_vault)]
+    pub pool_stake_token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    #[account(mut)]
-    pub pool_reward_token_vault: Box<Account<'info, TokenAccount>>,
+    #[account(mut, address = pool_config_account.pool_reward_token_vault)]
+    pub pool_reward_token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Required on reward-share pools
+    #[account(
+        mut,
+        constraint = treasury_reward_token_vault.owner == platform.treasury @ BrewStakingError::InvalidTreasuryTokenAccount
+    )]
+    pub treasury_reward_token_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(address = pool_config_account.reward_mint)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
 
     // #[account(mut)]
     // pub treasury_stake_token_vault: Box<Account<'info, TokenAccount>>,
 
-    pub token_program: Program<'info, token::Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 
     // Raydium Swap Accounts
     /// CHECK: Safe. amm program
@@ -254,5 +315,19 @@ pub struct CompoundReward<'info> {
     #[account(address = spl_token::ID)]
     pub spl_token_program: AccountInfo<'info>,
 
+    /// The user's fee discount from the pool owner, if any
+    #[account(
+        seeds = [FEE_EXEMPTION_SEED, pool_config_account.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub pool_fee_exemption: Option<Box<Account<'info, FeeExemption>>>,
+
+    /// The user's fee discount from the platform, if any
+    #[account(
+        seeds = [FEE_EXEMPTION_SEED, platform.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub platform_fee_exemption: Option<Box<Account<'info, FeeExemption>>>,
+
     pub system_program: Program<'info, System>,
 }
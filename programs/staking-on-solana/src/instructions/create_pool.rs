@@ -1,23 +1,34 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
-use anchor_spl::token::{ self, Mint, TokenAccount, Transfer };
+use anchor_spl::token_interface::{ Mint, TokenAccount, TokenInterface };
 // use spl_associated_token_account::{ get_associated_token_address, create_associated_token_account };
 
 use crate::state::*;
+use crate::utils::*;
 use crate::error::*;
+use crate::events::*;
 
-pub fn handler(
-    ctx: Context<CreatePool>,
+#[allow(clippy::too_many_arguments)]
+pub fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreatePool<'info>>,
     pool_id: String,
     stake_fee: u16,
     unstake_fee: u16,
     initial_funding: u64,
     reward_per_slot: u64,
-    duration: u16
+    duration: u16,
+    performance_fee_model: PerformanceFeeModel
 ) -> Result<()> {
     // Validate stake and unstake fees
     require!(stake_fee <= MAX_FEE, BrewStakingError::InvalidStakeFee);
     require!(unstake_fee <= MAX_FEE, BrewStakingError::InvalidUnstakeFee);
+    // The reward precision factor is 10^(9 - reward decimals)
+    require!(
+        ctx.accounts.reward_mint.decimals <= 9,
+        BrewStakingError::InvalidRewardDecimals
+    );
+    token::check_transfer_hook(&ctx.accounts.stake_mint, ctx.accounts.stake_hook_allowance.as_deref())?;
+    token::check_transfer_hook(&ctx.accounts.reward_mint, ctx.accounts.reward_hook_allowance.as_deref())?;
 
     let pool_config = &mut ctx.accounts.pool_config_account;
     let platform = &ctx.accounts.platform;
@@ -36,6 +47,8 @@ pub fn handler(
     pool_config.pool_reward_token_vault = ctx.accounts.pool_reward_token_vault.key();
     pool_config.pool_stake_token_vault = ctx.accounts.pool_stake_token_vault.key();
     pool_config.state_addr = ctx.accounts.pool_state_account.key();
+    pool_config.token_program = ctx.accounts.token_program.key();
+    pool_config.performance_fee_model = performance_fee_model;
 
     // let creator_reward_token_vault = get_associated_token_address(
     //     &ctx.accounts.creator.key(),
@@ -43,15 +56,17 @@ pub fn handler(
     // );
 
     // Transfer reward token from creator to pool account
-    let cpi_accounts = Transfer {
-        // from: creator_reward_token_vault.,
-        from: ctx.accounts.creator_reward_token_vault.to_account_info(),
-        to: ctx.accounts.pool_reward_token_vault.to_account_info(),
-        authority: ctx.accounts.creator.to_account_info(),
-    };
-    let cpi_program = ctx.accounts.token_program.to_account_info();
-    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-    token::transfer(cpi_ctx, initial_funding)?;
+    token::transfer_checked(
+        &ctx.accounts.token_program,
+        ctx.accounts.creator_reward_token_vault.to_account_info(),
+        &ctx.accounts.reward_mint,
+        ctx.accounts.pool_reward_token_vault.to_account_info(),
+        ctx.accounts.creator.to_account_info(),
+        ctx.remaining_accounts,
+        initial_funding
+    )?;
+    // A transfer fee mint withholds part of the funding
+    let initial_funding = token::amount_received(&ctx.accounts.reward_mint, initial_funding)?;
 
     let pool_state = &mut ctx.accounts.pool_state_account;
     pool_state.reward_amount = initial_funding;
@@ -69,9 +84,39 @@ pub fn handler(
     let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
     system_program::transfer(cpi_ctx, platform.deploy_fee)?;
 
+    let clock = Clock::get()?;
+    let pool_config = &ctx.accounts.pool_config_account;
+
+    emit_cpi!(PoolCreated {
+        pool_config: pool_config.key(),
+        creator: pool_config.owner,
+        pool_id: pool_config.pool_id.clone(),
+        stake_mint: pool_config.stake_mint,
+        reward_mint: pool_config.reward_mint,
+        stake_fee,
+        unstake_fee,
+        reward_per_slot,
+        duration,
+        initial_funding,
+        slot: clock.slot,
+    });
+
+    if platform.deploy_fee > 0 {
+        emit_cpi!(FeeCharged {
+            pool_config: pool_config.key(),
+            payer: pool_config.owner,
+            recipient: ctx.accounts.treasury.key(),
+            kind: FeeKind::Deploy,
+            mint: None,
+            amount: platform.deploy_fee,
+            slot: clock.slot,
+        });
+    }
+
     Ok(())
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 #[instruction(pool_id: String)]
 pub struct CreatePool<'info> {
@@ -96,9 +141,11 @@ pub struct CreatePool<'info> {
     #[account(mut)]
     pub treasury: Signer<'info>,
 
-    pub stake_mint: Box<Account<'info, Mint>>,
+    #[account(mint::token_program = token_program)]
+    pub stake_mint: Box<InterfaceAccount<'info, Mint>>,
 
-    pub reward_mint: Box<Account<'info, Mint>>,
+    #[account(mint::token_program = token_program)]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
 
     // #[account(
     //     init,
@@ -109,7 +156,7 @@ pub struct CreatePool<'info> {
     //     bump
     // )]
     #[account(mut)]
-    pub pool_stake_token_vault: Box<Account<'info, TokenAccount>>,
+    pub pool_stake_token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     // #[account(
     //     init,
@@ -120,14 +167,28 @@ pub struct CreatePool<'info> {
     //     bump
     // )]
     #[account(mut)]
-    pub pool_reward_token_vault: Box<Account<'info, TokenAccount>>,
+    pub pool_reward_token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut)]
-    pub creator_reward_token_vault: Box<Account<'info, TokenAccount>>,
+    pub creator_reward_token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Required when the stake mint has a transfer hook
+    #[account(
+        seeds = [HOOK_MINT_ALLOWANCE_SEED, platform.key().as_ref(), stake_mint.key().as_ref()],
+        bump
+    )]
+    pub stake_hook_allowance: Option<Box<Account<'info, HookMintAllowance>>>,
+
+    /// Required when the reward mint has a transfer hook
+    #[account(
+        seeds = [HOOK_MINT_ALLOWANCE_SEED, platform.key().as_ref(), reward_mint.key().as_ref()],
+        bump
+    )]
+    pub reward_hook_allowance: Option<Box<Account<'info, HookMintAllowance>>>,
 
     pub system_program: Program<'info, System>,
 
-    pub token_program: Program<'info, token::Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 // impl<'info> CreatePool<'info> {
@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+
+use crate::utils::*;
+use crate::error::*;
+use crate::events::*;
+
+use super::unstake::{ self, Unstake };
+
+// Unstakes the whole position and pays out its reward, then optionally closes
+// the position. Takes the same accounts as `unstake`, so `min_withdrawn` comes
+// first where `unstake` has its amount. A position with nothing staked skips
+// the unstake, and `close` is ignored while `close_position` would still
+// refuse the position, e.g. for the ticket this exit opened or a reward still
+// vesting.
+pub fn handler<'info>(
+    mut ctx: Context<'_, '_, '_, 'info, Unstake<'info>>,
+    min_withdrawn: u64,
+    position: u32,
+    close: bool
+) -> Result<()> {
+    let staked_amount = ctx.accounts.user_info.staked_amount;
+    if staked_amount > 0 {
+        unstake::withdraw(&mut ctx, staked_amount, min_withdrawn, position)?;
+    } else {
+        // There's nothing to hold in a ticket, so don't open an empty one
+        require!(ctx.accounts.withdrawal_ticket.is_none(), BrewStakingError::InvalidDelayedUnstakeTicket);
+    }
+
+    let clock = Clock::get()?;
+    let closable = close &&
+        accounting::close_user_info(
+            &ctx.accounts.pool_config_account,
+            &ctx.accounts.pool_state_account,
+            &ctx.accounts.user_info,
+            clock.slot
+        ).is_ok();
+    if closable {
+        ctx.accounts.user_info.close(ctx.accounts.user.to_account_info())?;
+
+        emit_cpi!(PositionClosed {
+            pool_config: ctx.accounts.pool_config_account.key(),
+            user: ctx.accounts.user.key(),
+            position,
+            slot: clock.slot,
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::utils::*;
+use crate::events::*;
+
+pub fn handler(ctx: Context<ExtendLock>, lock_weeks: u16, position: u32) -> Result<()> {
+    let pool_config = &ctx.accounts.pool_config_account;
+    let pool_state = &mut ctx.accounts.pool_state_account;
+    let user_info = &mut ctx.accounts.user_info;
+
+    let clock = Clock::get()?;
+
+    accounting::extend_lock(pool_config, pool_state, user_info, lock_weeks, clock.slot)?;
+
+    emit_cpi!(LockChanged {
+        pool_config: pool_config.key(),
+        user: ctx.accounts.user.key(),
+        position,
+        lock_end_slot: user_info.lock_end_slot,
+        lock_multiplier: user_info.lock_multiplier,
+        boosted_amount: user_info.boosted_amount,
+        total_boosted: pool_state.total_boosted,
+        slot: clock.slot,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(lock_weeks: u16, position: u32)]
+pub struct ExtendLock<'info> {
+    pub user: Signer<'info>,
+
+    pub pool_config_account: Box<Account<'info, PoolConfig>>,
+
+    #[account(mut, address = pool_config_account.state_addr)]
+    pub pool_state_account: Box<Account<'info, PoolState>>,
+
+    #[account(
+        mut,
+        seeds = [pool_config_account.key().as_ref(), user.key().as_ref(), &position_seed(position)],
+        bump
+    )]
+    pub user_info: Box<Account<'info, UserInfo>>,
+}
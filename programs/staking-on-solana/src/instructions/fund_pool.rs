@@ -0,0 +1,57 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{ Mint, TokenAccount, TokenInterface };
+
+use crate::state::*;
+use crate::utils::*;
+use crate::events::*;
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, FundPool<'info>>, amount: u64) -> Result<()> {
+    // Transfer reward token from funder to pool account
+    token::transfer_checked(
+        &ctx.accounts.token_program,
+        ctx.accounts.funder_reward_token_vault.to_account_info(),
+        &ctx.accounts.reward_mint,
+        ctx.accounts.pool_reward_token_vault.to_account_info(),
+        ctx.accounts.funder.to_account_info(),
+        ctx.remaining_accounts,
+        amount
+    )?;
+    // A transfer fee mint withholds part of the funding
+    let amount = token::amount_received(&ctx.accounts.reward_mint, amount)?;
+
+    let pool_state = &mut ctx.accounts.pool_state_account;
+    accounting::fund_pool(pool_state, amount)?;
+
+    emit_cpi!(RewardsFunded {
+        pool_config: ctx.accounts.pool_config_account.key(),
+        funder: ctx.accounts.funder.key(),
+        amount,
+        reward_amount: pool_state.reward_amount,
+        slot: Clock::get()?.slot,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FundPool<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    pub pool_config_account: Account<'info, PoolConfig>,
+
+    #[account(mut, address = pool_config_account.state_addr)]
+    pub pool_state_account: Account<'info, PoolState>,
+
+    #[account(mut)]
+    pub funder_reward_token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, address = pool_config_account.pool_reward_token_vault)]
+    pub pool_reward_token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = pool_config_account.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::utils::*;
+
+pub fn handler(ctx: Context<GetPoolInfo>) -> Result<PoolInfo> {
+    let pool_config = &ctx.accounts.pool_config_account;
+    let pool_state = &ctx.accounts.pool_state_account;
+
+    let clock = Clock::get()?;
+
+    accounting::pool_info(pool_config, pool_state, clock.slot)
+}
+
+#[derive(Accounts)]
+pub struct GetPoolInfo<'info> {
+    pub pool_config_account: Account<'info, PoolConfig>,
+
+    #[account(address = pool_config_account.state_addr)]
+    pub pool_state_account: Account<'info, PoolState>,
+}
@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::utils::*;
+
+pub fn handler(ctx: Context<GetUserPosition>, _position: u32) -> Result<UserPosition> {
+    let pool_config = &ctx.accounts.pool_config_account;
+    let pool_state = &ctx.accounts.pool_state_account;
+    let user_info = &ctx.accounts.user_info;
+    let discounts = accounting::fee_discounts(
+        ctx.accounts.pool_fee_exemption.as_deref(),
+        ctx.accounts.platform_fee_exemption.as_deref()
+    );
+
+    let clock = Clock::get()?;
+
+    accounting::user_position(pool_config, pool_state, &ctx.accounts.platform, user_info, discounts, clock.slot)
+}
+
+#[derive(Accounts)]
+#[instruction(position: u32)]
+pub struct GetUserPosition<'info> {
+    /// CHECK: only used to derive the stake and fee exemption accounts
+    pub user: UncheckedAccount<'info>,
+
+    pub pool_config_account: Account<'info, PoolConfig>,
+
+    #[account(address = pool_config_account.state_addr)]
+    pub pool_state_account: Account<'info, PoolState>,
+
+    pub platform: Account<'info, PlatformInfo>,
+
+    #[account(
+        seeds = [pool_config_account.key().as_ref(), user.key().as_ref(), &position_seed(position)],
+        bump
+    )]
+    pub user_info: Account<'info, UserInfo>,
+
+    /// The user's fee discount from the pool owner, if any
+    #[account(
+        seeds = [FEE_EXEMPTION_SEED, pool_config_account.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub pool_fee_exemption: Option<Account<'info, FeeExemption>>,
+
+    /// The user's fee discount from the platform, if any
+    #[account(
+        seeds = [FEE_EXEMPTION_SEED, platform.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub platform_fee_exemption: Option<Account<'info, FeeExemption>>,
+}
@@ -1,14 +1,26 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{ self };
+use anchor_spl::token_interface::TokenInterface;
 
 use crate::state::*;
+use crate::error::*;
+
+pub fn handler(
+    ctx: Context<Initialize>,
+    deploy_fee: u64,
+    performance_fee: u64,
+    treasury_fee_share: u16,
+    reward_performance_fee: u16
+) -> Result<()> {
+    require!(treasury_fee_share as u64 <= PERCENT_PRECISION, BrewStakingError::InvalidTreasuryFeeShare);
+    require!(reward_performance_fee <= MAX_FEE, BrewStakingError::InvalidPerformanceFee);
 
-pub fn handler(ctx: Context<Initialize>, deploy_fee: u64, performance_fee: u64) -> Result<()> {
     let platform = &mut ctx.accounts.platform;
 
     platform.treasury = ctx.accounts.treasury.key();
     platform.deploy_fee = deploy_fee;
     platform.performance_fee = performance_fee;
+    platform.treasury_fee_share = treasury_fee_share;
+    platform.reward_performance_fee = reward_performance_fee;
 
     Ok(())
 }
@@ -33,5 +45,5 @@ pub struct Initialize<'info> {
 
     pub system_program: Program<'info, System>,
 
-    pub token_program: Program<'info, token::Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
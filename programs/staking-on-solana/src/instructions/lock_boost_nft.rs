@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{ Mint, TokenAccount, TokenInterface };
+
+use crate::state::*;
+use crate::utils::*;
+use crate::error::*;
+use crate::events::*;
+
+pub fn handler(ctx: Context<LockBoostNft>, position: u32) -> Result<()> {
+    let pool_config = &ctx.accounts.pool_config_account;
+    let pool_state = &mut ctx.accounts.pool_state_account;
+    let user_info = &mut ctx.accounts.user_info;
+    let nft_mint = &ctx.accounts.nft_mint;
+
+    let boost_collection = pool_config.boost_collection.ok_or(BrewStakingError::BoostNotEnabled)?;
+    require!(user_info.boost_mint.is_none(), BrewStakingError::BoostAlreadyLocked);
+    require!(nft_mint.decimals == 0 && nft_mint.supply == 1, BrewStakingError::InvalidBoostNft);
+    let collection = metadata::verified_collection(&ctx.accounts.nft_metadata, &nft_mint.key())?;
+    require!(collection == Some(boost_collection), BrewStakingError::InvalidBoostNft);
+
+    // Move the NFT into the escrow
+    token::transfer_checked(
+        &ctx.accounts.nft_token_program,
+        ctx.accounts.user_nft_account.to_account_info(),
+        nft_mint,
+        ctx.accounts.boost_escrow.to_account_info(),
+        ctx.accounts.user.to_account_info(),
+        &[],
+        1
+    )?;
+
+    let clock = Clock::get()?;
+
+    accounting::set_boost(pool_config, pool_state, user_info, pool_config.boost_multiplier, clock.slot)?;
+    user_info.boost_mint = Some(nft_mint.key());
+
+    emit_cpi!(BoostChanged {
+        pool_config: pool_config.key(),
+        user: ctx.accounts.user.key(),
+        position,
+        mint: user_info.boost_mint,
+        boost_multiplier: user_info.boost_multiplier,
+        boosted_amount: user_info.boosted_amount,
+        total_boosted: pool_state.total_boosted,
+        slot: clock.slot,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(position: u32)]
+pub struct LockBoostNft<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub pool_config_account: Box<Account<'info, PoolConfig>>,
+
+    #[account(mut, address = pool_config_account.state_addr)]
+    pub pool_state_account: Box<Account<'info, PoolState>>,
+
+    #[account(
+        mut,
+        seeds = [pool_config_account.key().as_ref(), user.key().as_ref(), &position_seed(position)],
+        bump
+    )]
+    pub user_info: Box<Account<'info, UserInfo>>,
+
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: checked against the mint's metadata address and parsed in the handler
+    pub nft_metadata: UncheckedAccount<'info>,
+
+    #[account(mut, token::mint = nft_mint, token::authority = user)]
+    pub user_nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Holds the NFT while it is locked, with the user's stake as authority
+    #[account(
+        init,
+        payer = user,
+        seeds = [BOOST_ESCROW_SEED, user_info.key().as_ref()],
+        bump,
+        token::mint = nft_mint,
+        token::authority = user_info,
+        token::token_program = nft_token_program
+    )]
+    pub boost_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub nft_token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
@@ -2,11 +2,39 @@ pub mod initialize;
 pub mod create_pool;
 pub mod stake;
 pub mod unstake;
+pub mod exit;
 pub mod compound_reward;
 pub mod claim_reward;
+pub mod claim_all;
 pub mod start_reward;
 pub mod stop_reward;
 pub mod pending_reward;
+pub mod get_pool_info;
+pub mod get_user_position;
+pub mod fund_pool;
+pub mod allow_hook_mint;
+pub mod set_pool_fee_exemption;
+pub mod set_platform_fee_exemption;
+pub mod set_stake_limits;
+pub mod set_allowlist;
+pub mod set_boost_collection;
+pub mod lock_boost_nft;
+pub mod unlock_boost_nft;
+pub mod set_lock_multiplier;
+pub mod extend_lock;
+pub mod expire_lock;
+pub mod set_unbonding_period;
+pub mod withdraw_ticket;
+pub mod cancel_ticket;
+pub mod set_vesting;
+pub mod withdraw_vested;
+pub mod set_referral;
+pub mod claim_referral;
+pub mod set_reward_recipient;
+pub mod update_pool;
+pub mod update_pools;
+pub mod close_user_info;
+pub mod close_pool;
 
 pub use initialize::*;
 pub use create_pool::*;
@@ -14,6 +42,32 @@ pub use stake::*;
 pub use unstake::*;
 pub use compound_reward::*;
 pub use claim_reward::*;
+pub use claim_all::*;
 pub use start_reward::*;
 pub use stop_reward::*;
 pub use pending_reward::*;
+pub use get_pool_info::*;
+pub use get_user_position::*;
+pub use fund_pool::*;
+pub use allow_hook_mint::*;
+pub use set_pool_fee_exemption::*;
+pub use set_platform_fee_exemption::*;
+pub use set_stake_limits::*;
+pub use set_allowlist::*;
+pub use set_boost_collection::*;
+pub use lock_boost_nft::*;
+pub use unlock_boost_nft::*;
+pub use set_lock_multiplier::*;
+pub use extend_lock::*;
+pub use expire_lock::*;
+pub use set_unbonding_period::*;
+pub use withdraw_ticket::*;
+pub use cancel_ticket::*;
+pub use set_vesting::*;
+pub use set_referral::*;
+pub use claim_referral::*;
+pub use set_reward_recipient::*;
+pub use update_pool::*;
+pub use update_pools::*;
+pub use close_user_info::*;
+pub use close_pool::*;
@@ -0,0 +1,18 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+
+pub fn handler(ctx: Context<SetAllowlist>, allowlist_root: Option<[u8; 32]>) -> Result<()> {
+    // Stakes made under an earlier root are kept
+    ctx.accounts.pool_config_account.allowlist_root = allowlist_root;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetAllowlist<'info> {
+    #[account(mut, has_one = owner)]
+    pub pool_config_account: Account<'info, PoolConfig>,
+
+    pub owner: Signer<'info>,
+}
@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::utils::*;
+
+pub fn handler(
+    ctx: Context<SetBoostCollection>,
+    boost_collection: Option<Pubkey>,
+    boost_multiplier: u16
+) -> Result<()> {
+    // Locked NFTs keep the multiplier they were locked with
+    accounting::set_boost_collection(&mut ctx.accounts.pool_config_account, boost_collection, boost_multiplier)
+}
+
+#[derive(Accounts)]
+pub struct SetBoostCollection<'info> {
+    #[account(mut, has_one = owner)]
+    pub pool_config_account: Account<'info, PoolConfig>,
+
+    pub owner: Signer<'info>,
+}
@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::utils::*;
+
+pub fn handler(ctx: Context<SetLockMultiplier>, lock_multiplier: u16) -> Result<()> {
+    // Running locks keep the weight they were locked with
+    accounting::set_lock_multiplier(&mut ctx.accounts.pool_config_account, lock_multiplier)
+}
+
+#[derive(Accounts)]
+pub struct SetLockMultiplier<'info> {
+    #[account(mut, has_one = owner)]
+    pub pool_config_account: Account<'info, PoolConfig>,
+
+    pub owner: Signer<'info>,
+}
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::error::*;
+
+pub fn handler(ctx: Context<SetPoolFeeExemption>, wallet: Pubkey, discount: u16) -> Result<()> {
+    require!(discount as u64 <= PERCENT_PRECISION, BrewStakingError::InvalidFeeDiscount);
+
+    let exemption = &mut ctx.accounts.fee_exemption;
+
+    exemption.scope = ctx.accounts.pool_config_account.key();
+    exemption.wallet = wallet;
+    exemption.discount = discount;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct SetPoolFeeExemption<'info> {
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = FEE_EXEMPTION_SIZE,
+        seeds = [FEE_EXEMPTION_SEED, pool_config_account.key().as_ref(), wallet.as_ref()],
+        bump
+    )]
+    pub fee_exemption: Account<'info, FeeExemption>,
+
+    #[account(has_one = owner)]
+    pub pool_config_account: Account<'info, PoolConfig>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
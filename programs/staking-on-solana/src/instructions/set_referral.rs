@@ -0,0 +1,16 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::utils::*;
+
+pub fn handler(ctx: Context<SetReferral>, referral_share: u16, referral_source: ReferralSource) -> Result<()> {
+    accounting::set_referral(&mut ctx.accounts.pool_config_account, referral_share, referral_source)
+}
+
+#[derive(Accounts)]
+pub struct SetReferral<'info> {
+    #[account(mut, has_one = owner)]
+    pub pool_config_account: Account<'info, PoolConfig>,
+
+    pub owner: Signer<'info>,
+}
@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::utils::*;
+use crate::events::*;
+
+pub fn handler(ctx: Context<SetRewardRecipient>, position: u32, recipient: Option<Pubkey>) -> Result<()> {
+    let user_info = &mut ctx.accounts.user_info;
+
+    let clock = Clock::get()?;
+
+    accounting::set_reward_recipient(user_info, &ctx.accounts.user.key(), recipient);
+
+    emit_cpi!(RewardRecipientSet {
+        pool_config: ctx.accounts.pool_config_account.key(),
+        user: ctx.accounts.user.key(),
+        position,
+        recipient: user_info.reward_recipient,
+        slot: clock.slot,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(position: u32)]
+pub struct SetRewardRecipient<'info> {
+    pub user: Signer<'info>,
+
+    pub pool_config_account: Box<Account<'info, PoolConfig>>,
+
+    #[account(
+        mut,
+        seeds = [pool_config_account.key().as_ref(), user.key().as_ref(), &position_seed(position)],
+        bump
+    )]
+    pub user_info: Box<Account<'info, UserInfo>>,
+}
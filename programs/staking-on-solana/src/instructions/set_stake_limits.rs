@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::utils::*;
+
+pub fn handler(
+    ctx: Context<SetStakeLimits>,
+    min_stake: u64,
+    max_stake_per_wallet: u64,
+    max_total_staked: u64
+) -> Result<()> {
+    // Lowering a cap below what is already staked only blocks new stakes
+    accounting::set_stake_limits(
+        &mut ctx.accounts.pool_config_account,
+        min_stake,
+        max_stake_per_wallet,
+        max_total_staked
+    )
+}
+
+#[derive(Accounts)]
+pub struct SetStakeLimits<'info> {
+    #[account(mut, has_one = owner)]
+    pub pool_config_account: Account<'info, PoolConfig>,
+
+    pub owner: Signer<'info>,
+}
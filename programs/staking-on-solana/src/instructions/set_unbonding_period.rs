@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::utils::*;
+
+pub fn handler(ctx: Context<SetUnbondingPeriod>, unbonding_slots: u64) -> Result<()> {
+    // Open tickets keep the unlock slot they were opened with
+    accounting::set_unbonding_period(&mut ctx.accounts.pool_config_account, unbonding_slots)
+}
+
+#[derive(Accounts)]
+pub struct SetUnbondingPeriod<'info> {
+    #[account(mut, has_one = owner)]
+    pub pool_config_account: Account<'info, PoolConfig>,
+
+    pub owner: Signer<'info>,
+}
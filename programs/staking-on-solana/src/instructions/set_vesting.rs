@@ -0,0 +1,16 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::utils::*;
+
+pub fn handler(ctx: Context<SetVesting>, vesting_slots: u64, forfeit_unvested: bool) -> Result<()> {
+    accounting::set_vesting(&mut ctx.accounts.pool_config_account, vesting_slots, forfeit_unvested)
+}
+
+#[derive(Accounts)]
+pub struct SetVesting<'info> {
+    #[account(mut, has_one = owner)]
+    pub pool_config_account: Account<'info, PoolConfig>,
+
+    pub owner: Signer<'info>,
+}
@@ -1,144 +1,326 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
-use anchor_spl::token::{ self, TokenAccount, Transfer };
+use anchor_spl::token_interface::{ Mint, TokenAccount, TokenInterface };
 
 use crate::state::*;
 use crate::utils::*;
 use crate::error::*;
 use crate::events::*;
 
-pub fn handler(ctx: Context<Stake>, stake_amount: u64) -> Result<()> {
+pub fn handler<'info>(
+    ctx: Context<'_, '_, '_, 'info, Stake<'info>>,
+    stake_amount: u64,
+    min_received: u64,
+    allowlist_proof: Option<AllowlistProof>,
+    lock_weeks: u16,
+    position: u32,
+    referrer: Option<Pubkey>
+) -> Result<()> {
     let pool_config = &mut ctx.accounts.pool_config_account;
     let pool_state = &mut ctx.accounts.pool_state_account;
     let user_info = &mut ctx.accounts.user_info;
     let platform = &ctx.accounts.platform;
+    let discounts = accounting::fee_discounts(
+        ctx.accounts.pool_fee_exemption.as_deref(),
+        ctx.accounts.platform_fee_exemption.as_deref()
+    );
 
     let clock = Clock::get()?;
 
-    // msg!("@current slot {}", clock.slot);
-
+    // Allocations and per-wallet caps are checked against a single position
     require!(
-        pool_config.start_slot > 0 && pool_config.start_slot < clock.slot,
-        BrewStakingError::PoolNotStarted
+        position == 0 || (pool_config.allowlist_root.is_none() && pool_config.max_stake_per_wallet == 0),
+        BrewStakingError::PositionsNotAllowed
     );
 
-    // Transfer Performance Fee from user to treasury
-    let user_balance = ctx.accounts.staker.to_account_info().lamports();
-    require!(user_balance > platform.performance_fee, BrewStakingError::InsufficientDeployFee);
-
-    let cpi_program = ctx.accounts.system_program.to_account_info();
-    let cpi_accounts = system_program::Transfer {
-        from: ctx.accounts.staker.to_account_info(),
-        to: ctx.accounts.treasury.to_account_info(),
-    };
-    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-    system_program::transfer(cpi_ctx, platform.performance_fee)?;
-
-    let _ = update_pool(pool_config, pool_state);
-
-    let precision_factor = get_precision_factor(pool_config);
-    // msg!("@precision_factor :  {}", precision_factor);
-    // msg!("@user_info.staked_amount :  {}", user_info.staked_amount);
-
-    // If user already staked before
-    if user_info.staked_amount > 0 {
-        // msg!("@@@ user staked before, claim reward");
-        // Transfer the user his reward so far
-        let pending =
-            (user_info.staked_amount * pool_state.acc_token_per_share) / precision_factor -
-            user_info.reward_debt;
-        // msg!("@@@acc_token_per_share :  {}", pool_state.acc_token_per_share);
-        // msg!("@@@reward_debt :  {}", user_info.reward_debt);
-        // msg!("@@@pending :  {}", pending);
-
-        if pending > 0 {
+    // Allowlisted pools only take stakes from listed wallets
+    let allocation = match pool_config.allowlist_root {
+        Some(root) => {
+            let allowlist_proof = allowlist_proof.ok_or(BrewStakingError::AllowlistProofRequired)?;
+            let leaf = merkle::allowlist_leaf(&ctx.accounts.staker.key(), allowlist_proof.allocation);
             require!(
-                available_reward_tokens(pool_config, pool_state) >= pending,
-                BrewStakingError::InsufficientReward
+                merkle::verify_proof(&root, leaf, &allowlist_proof.proof),
+                BrewStakingError::InvalidAllowlistProof
             );
+            Some(allowlist_proof.allocation)
+        }
+        None => None,
+    };
 
-            let cpi_accounts = Transfer {
-                from: ctx.accounts.pool_reward_token_vault.to_account_info(),
-                to: ctx.accounts.user_reward_token_vault.to_account_info(),
-                authority: ctx.accounts.admin.to_account_info(),
-            };
-            let cpi_program = ctx.accounts.token_program.to_account_info();
-            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-            token::transfer(cpi_ctx, pending)?;
-
-            pool_state.reward_amount -= pending;
-
-            // msg!("@@@total_earned before :  {}", pool_state.total_earned);
-            pool_state.total_earned = if pool_state.total_earned > pending {
-                pool_state.total_earned - pending
-            } else {
-                0
-            };
-            // msg!("@@@total_earned after :  {}", pool_state.total_earned);
-            // msg!("@@@paid_rewards before :  {}", pool_state.paid_rewards);
-            pool_state.paid_rewards += pending;
-            // msg!("@@@paid_rewards after :  {}", pool_state.paid_rewards);
-            emit!(RewardClaim {
-                claimer: ctx.accounts.staker.key(),
-                amount: pending,
+    // Without a stake token account the stake is paid in native SOL, from the
+    // same lamports as the performance fee
+    let native_deposit = ctx.accounts.user_stake_token_vault.is_none();
+    if native_deposit {
+        require!(token::is_native_mint(&pool_config.stake_mint), BrewStakingError::TokenAccountRequired);
+    }
+    let deposit_lamports = if native_deposit { stake_amount } else { 0 };
+
+    let performance_fee = accounting::flat_performance_fee(pool_config, platform, discounts);
+    let user_balance = ctx.accounts.staker.to_account_info().lamports();
+    let lamports_needed = performance_fee
+        .checked_add(deposit_lamports)
+        .ok_or(BrewStakingError::MathOverflow)?;
+    require!(user_balance > lamports_needed, BrewStakingError::InsufficientDeployFee);
+
+    // Transfer Token from staker to pool account. Only what reaches the vault
+    // is credited: a fee-on-transfer mint delivers less than `stake_amount`
+    if native_deposit {
+        // Lamports sent to the vault directly are not part of this deposit
+        token::sync_native(&ctx.accounts.token_program, ctx.accounts.pool_stake_token_vault.to_account_info())?;
+    }
+    ctx.accounts.pool_stake_token_vault.reload()?;
+    let vault_balance_before = ctx.accounts.pool_stake_token_vault.amount;
+    match &ctx.accounts.user_stake_token_vault {
+        Some(user_stake_token_vault) =>
+            token::transfer_checked(
+                &ctx.accounts.token_program,
+                user_stake_token_vault.to_account_info(),
+                &ctx.accounts.stake_mint,
+                ctx.accounts.pool_stake_token_vault.to_account_info(),
+                ctx.accounts.staker.to_account_info(),
+                ctx.remaining_accounts,
+                stake_amount
+            )?,
+        None =>
+            token::wrap_sol(
+                &ctx.accounts.system_program,
+                &ctx.accounts.token_program,
+                ctx.accounts.staker.to_account_info(),
+                ctx.accounts.pool_stake_token_vault.to_account_info(),
+                stake_amount
+            )?,
+    }
+    ctx.accounts.pool_stake_token_vault.reload()?;
+    let received = ctx.accounts.pool_stake_token_vault.amount
+        .checked_sub(vault_balance_before)
+        .ok_or(BrewStakingError::MathOverflow)?;
+    require!(received >= min_received, BrewStakingError::ReceivedBelowMinimum);
+
+    let new_referee = accounting::set_referrer(user_info, &ctx.accounts.staker.key(), referrer)?;
+    user_info.allocation = allocation;
+
+    let amounts = accounting::stake(
+        pool_config,
+        pool_state,
+        user_info,
+        received,
+        lock_weeks,
+        discounts.pool_fees,
+        clock.slot
+    )?;
+
+    // Transfer Performance Fee from user to treasury, unless nothing moved
+    if received > 0 || amounts.reward > 0 {
+        let cpi_program = ctx.accounts.system_program.to_account_info();
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.staker.to_account_info(),
+            to: ctx.accounts.treasury.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        system_program::transfer(cpi_ctx, performance_fee)?;
+
+        if performance_fee > 0 {
+            emit_cpi!(FeeCharged {
+                pool_config: pool_config.key(),
+                payer: ctx.accounts.staker.key(),
+                recipient: ctx.accounts.treasury.key(),
+                kind: FeeKind::Performance,
+                mint: None,
+                amount: performance_fee,
+                slot: clock.slot,
             });
         }
     }
 
-    // Transfer Token from staker to pool account
-    let cpi_accounts = Transfer {
-        from: ctx.accounts.user_stake_token_vault.to_account_info(),
-        to: ctx.accounts.pool_stake_token_vault.to_account_info(),
-        authority: ctx.accounts.staker.to_account_info(),
+    // Referrers get their cut of the stake fee or of the reward. It stays in
+    // the pool's vaults until they claim it
+    let stake_referral = accounting::referral_cut(pool_config, user_info, ReferralSource::StakeFee, amounts.fee);
+    let reward_referral = accounting::referral_cut(pool_config, user_info, ReferralSource::Rewards, amounts.reward);
+    match (user_info.referrer, ctx.accounts.referrer_info.as_deref_mut()) {
+        (Some(referrer), Some(referrer_info)) => {
+            referrer_info.pool_config = pool_config.key();
+            referrer_info.referrer = referrer;
+            if new_referee {
+                referrer_info.referred_count += 1;
+            }
+            if stake_referral > 0 {
+                accounting::credit_referrer(pool_state, referrer_info, ReferralSource::StakeFee, stake_referral)?;
+                emit_cpi!(FeeCharged {
+                    pool_config: pool_config.key(),
+                    payer: ctx.accounts.staker.key(),
+                    recipient: referrer_info.key(),
+                    kind: FeeKind::Referral,
+                    mint: Some(pool_config.stake_mint),
+                    amount: stake_referral,
+                    slot: clock.slot,
+                });
+            }
+            // The reward payout below reports this cut
+            if reward_referral > 0 {
+                accounting::credit_referrer(pool_state, referrer_info, ReferralSource::Rewards, reward_referral)?;
+            }
+            if new_referee || amounts.staked > 0 {
+                emit_cpi!(ReferredStake {
+                    pool_config: pool_config.key(),
+                    user: ctx.accounts.staker.key(),
+                    position,
+                    referrer,
+                    amount: amounts.staked,
+                    new_referee,
+                    slot: clock.slot,
+                });
+            }
+        }
+        (Some(_), None) => return err!(BrewStakingError::ReferrerAccountRequired),
+        (None, Some(_)) => return err!(BrewStakingError::InvalidReferrerAccount),
+        (None, None) => {}
+    }
+
+    // Transfer the user his reward so far
+    let pool_key = pool_config.key();
+    let staker_key = ctx.accounts.staker.key();
+    let wsol_seeds: &[&[u8]] = &[
+        WSOL_SEED,
+        pool_key.as_ref(),
+        staker_key.as_ref(),
+        &[ctx.bumps.wsol_account],
+    ];
+    let payout = payout::RewardPayout {
+        pool_config,
+        platform,
+        discounts,
+        user: ctx.accounts.staker.to_account_info(),
+        admin: ctx.accounts.admin.to_account_info(),
+        pool_reward_token_vault: ctx.accounts.pool_reward_token_vault.to_account_info(),
+        treasury_reward_token_vault: ctx.accounts.treasury_reward_token_vault.as_ref().map(|vault| vault.to_account_info()),
+        referrer_info: ctx.accounts.referrer_info.as_ref().map(|info| info.key()),
+        reward_mint: &ctx.accounts.reward_mint,
+        token_program: &ctx.accounts.token_program,
+        system_program: &ctx.accounts.system_program,
+        remaining_accounts: ctx.remaining_accounts,
+        slot: clock.slot,
     };
-    let cpi_program = ctx.accounts.token_program.to_account_info();
-    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-    token::transfer(cpi_ctx, stake_amount)?;
+    let (reward, fee_events) = payout.charge_fees(amounts.reward, reward_referral)?;
+    payout.send(
+        reward,
+        user_info.reward_recipient,
+        &ctx.accounts.user_reward_token_vault,
+        &ctx.accounts.wsol_account,
+        wsol_seeds
+    )?;
+    for event in fee_events {
+        emit_cpi!(event);
+    }
+    if reward > 0 {
+        emit_cpi!(RewardClaim {
+            pool_config: pool_config.key(),
+            claimer: ctx.accounts.staker.key(),
+            amount: reward,
+            staked_amount: user_info.staked_amount,
+            total_staked: pool_state.total_staked,
+            slot: clock.slot,
+        });
+    }
 
-    // Transfer stake fee from pool to pool owner
-    let stake_fee = (stake_amount * (pool_config.stake_fee as u64)) / PERCENT_PRECISION;
+    // Transfer stake fee from pool to pool owner and treasury
+    let (creator_fee, treasury_fee) = accounting::split_fee(
+        pool_config,
+        amounts.fee - stake_referral,
+        ctx.accounts.platform.treasury_fee_share
+    )?;
+    for (recipient, fee) in [
+        (&ctx.accounts.creator_stake_token_vault, creator_fee),
+        (&ctx.accounts.treasury_stake_token_vault, treasury_fee),
+    ] {
+        token::transfer_checked(
+            &ctx.accounts.token_program,
+            ctx.accounts.pool_stake_token_vault.to_account_info(),
+            &ctx.accounts.stake_mint,
+            recipient.to_account_info(),
+            ctx.accounts.admin.to_account_info(),
+            ctx.remaining_accounts,
+            fee
+        )?;
+
+        if fee > 0 {
+            emit_cpi!(FeeCharged {
+                pool_config: pool_config.key(),
+                payer: ctx.accounts.staker.key(),
+                recipient: recipient.key(),
+                kind: FeeKind::Stake,
+                mint: Some(pool_config.stake_mint),
+                amount: fee,
+                slot: clock.slot,
+            });
+        }
+    }
 
-    let cpi_accounts = Transfer {
-        from: ctx.accounts.pool_stake_token_vault.to_account_info(),
-        to: ctx.accounts.creator_stake_token_vault.to_account_info(),
-        authority: ctx.accounts.admin.to_account_info(),
-    };
-    let cpi_program = ctx.accounts.token_program.to_account_info();
-    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-    token::transfer(cpi_ctx, stake_fee)?;
-
-    // Update user and pool info
-    msg!("@stake_amount :  {}", stake_amount);
-    msg!("@stake_fee :  {}", stake_fee);
-    let real_amount = stake_amount - stake_fee;
-
-    user_info.staked_amount += real_amount;
-    msg!("@user_info.staked_amount :  {}", user_info.staked_amount);
-    user_info.reward_debt =
-        (user_info.staked_amount * pool_state.acc_token_per_share) / precision_factor;
-    msg!("@pool_state.acc_token_per_share :  {}", pool_state.acc_token_per_share);
-    msg!("@user_info.reward_debt :  {}", user_info.reward_debt);
-    pool_state.total_staked += real_amount;
-    msg!("@pool_state.total_staked :  {}", pool_state.total_staked);
-    emit!(Deposit {
+    if amounts.vested > 0 {
+        emit_cpi!(RewardVested {
+            pool_config: pool_config.key(),
+            user: ctx.accounts.staker.key(),
+            position,
+            amount: amounts.vested,
+            vesting_amount: user_info.vesting_amount,
+            vesting_end_slot: user_info.vesting_end_slot,
+            slot: clock.slot,
+        });
+    }
+    emit_cpi!(Deposit {
+        pool_config: pool_config.key(),
         staker: ctx.accounts.staker.key(),
-        amount: real_amount,
+        amount: amounts.staked,
+        fee: amounts.fee,
+        staked_amount: user_info.staked_amount,
+        total_staked: pool_state.total_staked,
+        slot: clock.slot,
+    });
+    emit_cpi!(PositionChanged {
+        pool_config: pool_config.key(),
+        user: ctx.accounts.staker.key(),
+        position,
+        staked_amount: user_info.staked_amount,
+        slot: clock.slot,
     });
 
     Ok(())
 }
 
+#[event_cpi]
 #[derive(Accounts)]
+#[instruction(
+    stake_amount: u64,
+    min_received: u64,
+    allowlist_proof: Option<AllowlistProof>,
+    lock_weeks: u16,
+    position: u32,
+    referrer: Option<Pubkey>
+)]
 pub struct Stake<'info> {
     #[account(
         init_if_needed,
         payer = staker,
         space = USER_INFO_SIZE,
-        seeds = [pool_config_account.key().as_ref(), staker.key().as_ref()],
+        seeds = [pool_config_account.key().as_ref(), staker.key().as_ref(), &position_seed(position)],
         bump
     )]
     pub user_info: Account<'info, UserInfo>,
 
+    /// The referrer's earnings account, required once the position has a
+    /// referrer
+    #[account(
+        init_if_needed,
+        payer = staker,
+        space = REFERRER_INFO_SIZE,
+        seeds = [
+            REFERRER_SEED,
+            pool_config_account.key().as_ref(),
+            user_info.referrer.or(referrer).unwrap_or_default().as_ref(),
+        ],
+        bump
+    )]
+    pub referrer_info: Option<Box<Account<'info, ReferrerInfo>>>,
+
     #[account(mut)]
     pub staker: Signer<'info>,
 
@@ -155,30 +337,72 @@ pub struct Stake<'info> {
     #[account(mut)]
     pub pool_config_account: Account<'info, PoolConfig>,
 
-    #[account(mut)]
+    #[account(mut, address = pool_config_account.state_addr)]
     pub pool_state_account: Account<'info, PoolState>,
 
+    /// Omitted to stake native SOL
     #[account(mut)]
-    pub user_stake_token_vault: Account<'info, TokenAccount>,
+    pub user_stake_token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
 
+    /// The reward recipient's associated token account on positions that have
+    /// one. Omitted to be paid native SOL rewards
     #[account(mut)]
-    pub user_reward_token_vault: Account<'info, TokenAccount>,
+    pub user_reward_token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
 
-    #[account(mut)]
-    pub pool_stake_token_vault: Account<'info, TokenAccount>,
+    /// Deposits are measured as this vault's balance change
+    #[account(mut, address = pool_config_account.pool_stake_token_vault)]
+    pub pool_stake_token_vault: InterfaceAccount<'info, TokenAccount>,
 
-    #[account(mut)]
-    pub pool_reward_token_vault: Account<'info, TokenAccount>,
+    #[account(mut, address = pool_config_account.pool_reward_token_vault)]
+    pub pool_reward_token_vault: InterfaceAccount<'info, TokenAccount>,
 
     #[account(mut)]
-    pub creator_stake_token_vault: Account<'info, TokenAccount>,
+    pub creator_stake_token_vault: InterfaceAccount<'info, TokenAccount>,
 
-    #[account(mut)]
-    pub treasury_stake_token_vault: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = treasury_stake_token_vault.owner == platform.treasury @ BrewStakingError::InvalidTreasuryTokenAccount
+    )]
+    pub treasury_stake_token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Required on reward-share pools
+    #[account(
+        mut,
+        constraint = treasury_reward_token_vault.owner == platform.treasury @ BrewStakingError::InvalidTreasuryTokenAccount
+    )]
+    pub treasury_reward_token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = pool_config_account.stake_mint)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(address = pool_config_account.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: created and closed again to pay out native SOL
+    #[account(
+        mut,
+        seeds = [WSOL_SEED, pool_config_account.key().as_ref(), staker.key().as_ref()],
+        bump
+    )]
+    pub wsol_account: Option<UncheckedAccount<'info>>,
+
+    /// The user's fee discount from the pool owner, if any
+    #[account(
+        seeds = [FEE_EXEMPTION_SEED, pool_config_account.key().as_ref(), staker.key().as_ref()],
+        bump
+    )]
+    pub pool_fee_exemption: Option<Account<'info, FeeExemption>>,
+
+    /// The user's fee discount from the platform, if any
+    #[account(
+        seeds = [FEE_EXEMPTION_SEED, platform.key().as_ref(), staker.key().as_ref()],
+        bump
+    )]
+    pub platform_fee_exemption: Option<Account<'info, FeeExemption>>,
 
     pub system_program: Program<'info, System>,
 
-    pub token_program: Program<'info, token::Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 // impl<'info> Stake<'info> {
@@ -1,41 +1,33 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{ self };
+use anchor_spl::token_interface::TokenInterface;
 
 use crate::state::*;
-use crate::error::*;
+use crate::utils::*;
 use crate::events::*;
 
 pub fn handler(ctx: Context<StartReward>) -> Result<()> {
     let pool_config = &mut ctx.accounts.pool_config_account;
     let pool_state = &mut ctx.accounts.pool_state_account;
 
-    require!(pool_config.start_slot == 0, BrewStakingError::PoolAlreadyStarted);
-
-    // msg!("insufficient rewadrs {}", insufficient_rewards(pool_config, pool_state));
-    // require!(
-    //     insufficient_rewards(pool_config, pool_state) == 0,
-    //     BrewStakingError::RewardNotDeposited
-    // );
     let clock = Clock::get()?;
     // CHECK
-    // Calculate start and end slot
-    pool_config.start_slot = clock.slot + 10;
-    pool_config.end_slot = pool_config.start_slot + (pool_config.duration as u64) * SLOTS_PER_DAY;
-
-    pool_state.last_reward_slot = pool_config.start_slot;
+    accounting::start_reward(pool_config, pool_state, clock.slot)?;
 
     // msg!("current slot {}", clock.slot);
     // msg!("pool_config.start_slot {}", pool_config.start_slot);
     // msg!("pool_config.end_slot {}", pool_config.end_slot);
 
-    emit!(NewStartAndEndSlots {
+    emit_cpi!(NewStartAndEndSlots {
+        pool_config: pool_config.key(),
         start_slot: pool_config.start_slot,
         end_slot: pool_config.end_slot,
+        slot: clock.slot,
     });
 
     Ok(())
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct StartReward<'info> {
     /// CHECK:
@@ -45,8 +37,8 @@ pub struct StartReward<'info> {
     #[account(mut)]
     pub pool_config_account: Account<'info, PoolConfig>,
 
-    #[account(mut)]
+    #[account(mut, address = pool_config_account.state_addr)]
     pub pool_state_account: Account<'info, PoolState>,
 
-    pub token_program: Program<'info, token::Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
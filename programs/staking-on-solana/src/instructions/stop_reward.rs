@@ -1,65 +1,62 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{ self, TokenAccount, Transfer };
+use anchor_spl::token_interface::{ Mint, TokenAccount, TokenInterface };
 
 use crate::state::*;
 use crate::utils::*;
 // use crate::error::*;
 use crate::events::*;
 
-pub fn handler(ctx: Context<StopReward>) -> Result<()> {
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, StopReward<'info>>) -> Result<()> {
     let pool_config = &mut ctx.accounts.pool_config_account;
     let pool_state = &mut ctx.accounts.pool_state_account;
 
-    let _ = update_pool(pool_config, pool_state);
-
-    let mut remain_rewards =
-        available_reward_tokens(pool_config, pool_state) + pool_state.paid_rewards;
+    let clock = Clock::get()?;
+    let remain_rewards = accounting::stop_reward(pool_config, pool_state, clock.slot)?;
 
-    if remain_rewards > pool_state.should_total_paid {
-        remain_rewards = remain_rewards - pool_state.should_total_paid;
+    if remain_rewards > 0 {
         // transfer remaining reward to deployer
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.pool_reward_token_vault.to_account_info(),
-            to: ctx.accounts.deployer_reward_token_vault.to_account_info(),
-            authority: ctx.accounts.deployer.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token::transfer(cpi_ctx, remain_rewards)?;
-
-        if pool_state.total_earned > remain_rewards {
-            pool_state.total_earned -= remain_rewards;
-        } else {
-            pool_state.total_earned = 0;
-        }
+        token::transfer_checked(
+            &ctx.accounts.token_program,
+            ctx.accounts.pool_reward_token_vault.to_account_info(),
+            &ctx.accounts.reward_mint,
+            ctx.accounts.deployer_reward_token_vault.to_account_info(),
+            ctx.accounts.deployer.to_account_info(),
+            ctx.remaining_accounts,
+            remain_rewards
+        )?;
     }
 
-    let clock = Clock::get()?;
-    pool_config.end_slot = clock.slot;
-
-    emit!(RewardsStop {
+    emit_cpi!(RewardsStop {
+        pool_config: pool_config.key(),
         end_slot: pool_config.end_slot,
+        refund: remain_rewards,
+        slot: clock.slot,
     });
 
     Ok(())
 }
 
+#[event_cpi]
 #[derive(Accounts)]
 pub struct StopReward<'info> {
     /// CHECK:
     #[account(mut)]
     pub deployer: AccountInfo<'info>,
 
+    #[account(mut)]
     pub pool_config_account: Account<'info, PoolConfig>,
 
-    #[account(mut)]
-    pub pool_reward_token_vault: Account<'info, TokenAccount>,
+    #[account(mut, address = pool_config_account.pool_reward_token_vault)]
+    pub pool_reward_token_vault: InterfaceAccount<'info, TokenAccount>,
 
     #[account(mut)]
-    pub deployer_reward_token_vault: Account<'info, TokenAccount>,
+    pub deployer_reward_token_vault: InterfaceAccount<'info, TokenAccount>,
 
-    #[account(mut)]
+    #[account(mut, address = pool_config_account.state_addr)]
     pub pool_state_account: Account<'info, PoolState>,
 
-    pub token_program: Program<'info, token::Token>,
+    #[account(address = pool_config_account.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
@@ -0,0 +1,90 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{ Mint, TokenAccount, TokenInterface };
+
+use crate::state::*;
+use crate::utils::*;
+use crate::error::*;
+use crate::events::*;
+
+pub fn handler(ctx: Context<UnlockBoostNft>, position: u32) -> Result<()> {
+    let pool_config = &ctx.accounts.pool_config_account;
+    let pool_state = &mut ctx.accounts.pool_state_account;
+    let user_info = &mut ctx.accounts.user_info;
+
+    let clock = Clock::get()?;
+
+    accounting::set_boost(pool_config, pool_state, user_info, 0, clock.slot)?;
+    user_info.boost_mint = None;
+
+    // The stake account signs for the escrow
+    let pool_config_key = pool_config.key();
+    let user_key = ctx.accounts.user.key();
+    let position_seed = position_seed(position);
+    let user_info_seeds: &[&[u8]] = &[
+        pool_config_key.as_ref(),
+        user_key.as_ref(),
+        &position_seed,
+        &[ctx.bumps.user_info],
+    ];
+    token::transfer_checked_signed(
+        &ctx.accounts.nft_token_program,
+        ctx.accounts.boost_escrow.to_account_info(),
+        &ctx.accounts.nft_mint,
+        ctx.accounts.user_nft_account.to_account_info(),
+        user_info.to_account_info(),
+        &[],
+        1,
+        &[user_info_seeds]
+    )?;
+    token::close_signed(
+        &ctx.accounts.nft_token_program,
+        ctx.accounts.boost_escrow.to_account_info(),
+        ctx.accounts.user.to_account_info(),
+        user_info.to_account_info(),
+        &[user_info_seeds]
+    )?;
+
+    emit_cpi!(BoostChanged {
+        pool_config: pool_config_key,
+        user: user_key,
+        position,
+        mint: None,
+        boost_multiplier: 0,
+        boosted_amount: user_info.boosted_amount,
+        total_boosted: pool_state.total_boosted,
+        slot: clock.slot,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+#[instruction(position: u32)]
+pub struct UnlockBoostNft<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub pool_config_account: Box<Account<'info, PoolConfig>>,
+
+    #[account(mut, address = pool_config_account.state_addr)]
+    pub pool_state_account: Box<Account<'info, PoolState>>,
+
+    #[account(
+        mut,
+        seeds = [pool_config_account.key().as_ref(), user.key().as_ref(), &position_seed(position)],
+        bump
+    )]
+    pub user_info: Box<Account<'info, UserInfo>>,
+
+    #[account(constraint = user_info.boost_mint == Some(nft_mint.key()) @ BrewStakingError::InvalidBoostNft)]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, token::mint = nft_mint, token::authority = user)]
+    pub user_nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, seeds = [BOOST_ESCROW_SEED, user_info.key().as_ref()], bump)]
+    pub boost_escrow: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub nft_token_program: Interface<'info, TokenInterface>,
+}
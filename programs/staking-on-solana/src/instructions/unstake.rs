@@ -1,24 +1,41 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
-use anchor_spl::token::{ self, TokenAccount, Transfer };
+use anchor_spl::token_interface::{ Mint, TokenAccount, TokenInterface };
 
 use crate::state::*;
 use crate::utils::*;
 use crate::error::*;
 use crate::events::*;
 
-pub fn handler(ctx: Context<Unstake>, unstake_amount: u64) -> Result<()> {
-    let pool_config = &ctx.accounts.pool_config_account;
+pub fn handler<'info>(
+    mut ctx: Context<'_, '_, '_, 'info, Unstake<'info>>,
+    unstake_amount: u64,
+    position: u32
+) -> Result<()> {
+    withdraw(&mut ctx, unstake_amount, 0, position)
+}
+
+// Unstakes and pays out like `unstake`, failing when less than `min_withdrawn`
+// would be withdrawn or ticketed after the unstake fee
+pub fn withdraw<'info>(
+    ctx: &mut Context<'_, '_, '_, 'info, Unstake<'info>>,
+    unstake_amount: u64,
+    min_withdrawn: u64,
+    position: u32
+) -> Result<()> {
+    let pool_config = &mut ctx.accounts.pool_config_account;
     let pool_state = &mut ctx.accounts.pool_state_account;
     let user_info = &mut ctx.accounts.user_info;
     let platform = &ctx.accounts.platform;
-
-    require!(unstake_amount > 0, BrewStakingError::UnstakeAmountTooSmall);
-    require!(user_info.staked_amount > unstake_amount, BrewStakingError::UnstakeAmountTooHigh);
+    let discounts = accounting::fee_discounts(
+        ctx.accounts.pool_fee_exemption.as_deref(),
+        ctx.accounts.platform_fee_exemption.as_deref()
+    );
 
     // Transfer Performance Fee from user to treasury
+    let performance_fee = accounting::flat_performance_fee(pool_config, platform, discounts);
     let user_balance = ctx.accounts.user.to_account_info().lamports();
-    require!(user_balance > platform.performance_fee, BrewStakingError::InsufficientDeployFee);
+    require!(user_balance > performance_fee, BrewStakingError::InsufficientDeployFee);
 
     let cpi_program = ctx.accounts.system_program.to_account_info();
     let cpi_accounts = system_program::Transfer {
@@ -26,87 +43,218 @@ pub fn handler(ctx: Context<Unstake>, unstake_amount: u64) -> Result<()> {
         to: ctx.accounts.treasury.to_account_info(),
     };
     let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-    system_program::transfer(cpi_ctx, platform.performance_fee)?;
-
-    let _ = update_pool(pool_config, pool_state);
-
-    let precision_factor = get_precision_factor(pool_config);
-
-    // If user already staked before
-    if user_info.staked_amount > 0 {
-        // Transfer the user his reward so far
-        let pending =
-            (user_info.staked_amount * pool_state.acc_token_per_share) / precision_factor -
-            user_info.reward_debt;
-
-        if pending > 0 {
-            require!(
-                available_reward_tokens(pool_config, pool_state) >= pending,
-                BrewStakingError::InsufficientReward
-            );
-
-            let cpi_accounts = Transfer {
-                from: ctx.accounts.pool_reward_token_vault.to_account_info(),
-                to: ctx.accounts.user_reward_token_vault.to_account_info(),
-                authority: ctx.accounts.admin.to_account_info(),
-            };
-            let cpi_program = ctx.accounts.token_program.to_account_info();
-            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-            token::transfer(cpi_ctx, pending)?;
-
-            pool_state.reward_amount -= pending;
-
-            pool_state.total_earned = if pool_state.total_earned > pending {
-                pool_state.total_earned - pending
-            } else {
-                0
-            };
-            pool_state.paid_rewards += pending;
-
-            emit!(RewardClaim {
-                claimer: ctx.accounts.user.key(),
-                amount: pending,
-            });
-        }
-    }
-
-    let mut real_amount = unstake_amount;
-    if user_info.staked_amount < unstake_amount {
-        real_amount = user_info.staked_amount;
+    system_program::transfer(cpi_ctx, performance_fee)?;
+
+    let clock = Clock::get()?;
+
+    if performance_fee > 0 {
+        emit_cpi!(FeeCharged {
+            pool_config: pool_config.key(),
+            payer: ctx.accounts.user.key(),
+            recipient: ctx.accounts.treasury.key(),
+            kind: FeeKind::Performance,
+            mint: None,
+            amount: performance_fee,
+            slot: clock.slot,
+        });
     }
-    // Transfer unstake fee from pool to pool owner
-    let unstake_fee = (real_amount * (pool_config.unstake_fee as u64)) / PERCENT_PRECISION;
 
-    let cpi_accounts = Transfer {
-        from: ctx.accounts.pool_stake_token_vault.to_account_info(),
-        to: ctx.accounts.creator_stake_token_vault.to_account_info(),
-        authority: ctx.accounts.admin.to_account_info(),
+    let amounts = accounting::unstake(
+        pool_config,
+        pool_state,
+        user_info,
+        unstake_amount,
+        discounts.pool_fees,
+        clock.slot
+    )?;
+    require!(amounts.withdrawn >= min_withdrawn, BrewStakingError::ReceivedBelowMinimum);
+
+    // Pools with an unbonding period hold the withdrawal in a ticket instead
+    let unbonding = pool_config.unbonding_slots > 0;
+    require!(
+        unbonding == ctx.accounts.withdrawal_ticket.is_some(),
+        BrewStakingError::InvalidDelayedUnstakeTicket
+    );
+    let withdrawn = if unbonding { 0 } else { amounts.withdrawn };
+
+    // The referrer's cut of the reward stays in the vault until they claim it
+    let reward_referral = accounting::referral_reward(
+        pool_config,
+        pool_state,
+        user_info,
+        ctx.accounts.referrer_info.as_deref_mut().map(|info| &mut **info),
+        amounts.reward
+    )?;
+
+    // Transfer the user his reward so far
+    let pool_key = pool_config.key();
+    let user_key = ctx.accounts.user.key();
+    let wsol_seeds: &[&[u8]] = &[WSOL_SEED, pool_key.as_ref(), user_key.as_ref(), &[ctx.bumps.wsol_account]];
+    let payout = payout::RewardPayout {
+        pool_config,
+        platform,
+        discounts,
+        user: ctx.accounts.user.to_account_info(),
+        admin: ctx.accounts.admin.to_account_info(),
+        pool_reward_token_vault: ctx.accounts.pool_reward_token_vault.to_account_info(),
+        treasury_reward_token_vault: ctx.accounts.treasury_reward_token_vault.as_ref().map(|vault| vault.to_account_info()),
+        referrer_info: ctx.accounts.referrer_info.as_ref().map(|info| info.key()),
+        reward_mint: &ctx.accounts.reward_mint,
+        token_program: &ctx.accounts.token_program,
+        system_program: &ctx.accounts.system_program,
+        remaining_accounts: ctx.remaining_accounts,
+        slot: clock.slot,
     };
-    let cpi_program = ctx.accounts.token_program.to_account_info();
-    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-    token::transfer(cpi_ctx, unstake_fee)?;
+    let (reward, fee_events) = payout.charge_fees(amounts.reward, reward_referral)?;
+    payout.send(
+        reward,
+        user_info.reward_recipient,
+        &ctx.accounts.user_reward_token_vault,
+        &ctx.accounts.wsol_account,
+        wsol_seeds
+    )?;
+    for event in fee_events {
+        emit_cpi!(event);
+    }
+    if reward > 0 {
+        emit_cpi!(RewardClaim {
+            pool_config: pool_config.key(),
+            claimer: ctx.accounts.user.key(),
+            amount: reward,
+            staked_amount: user_info.staked_amount,
+            total_staked: pool_state.total_staked,
+            slot: clock.slot,
+        });
+    }
 
-    // Transfer unstake amount from pool to user
-    let cpi_accounts = Transfer {
-        from: ctx.accounts.pool_stake_token_vault.to_account_info(),
-        to: ctx.accounts.user_stake_token_vault.to_account_info(),
-        authority: ctx.accounts.admin.to_account_info(),
-    };
-    let cpi_program = ctx.accounts.token_program.to_account_info();
-    let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-    token::transfer(cpi_ctx, real_amount - unstake_fee)?;
+    // Transfer unstake fee from pool to pool owner and treasury
+    let (creator_fee, treasury_fee) = accounting::split_fee(
+        pool_config,
+        amounts.fee,
+        ctx.accounts.platform.treasury_fee_share
+    )?;
+    for (recipient, fee) in [
+        (&ctx.accounts.creator_stake_token_vault, creator_fee),
+        (&ctx.accounts.treasury_stake_token_vault, treasury_fee),
+    ] {
+        token::transfer_checked(
+            &ctx.accounts.token_program,
+            ctx.accounts.pool_stake_token_vault.to_account_info(),
+            &ctx.accounts.stake_mint,
+            recipient.to_account_info(),
+            ctx.accounts.admin.to_account_info(),
+            ctx.remaining_accounts,
+            fee
+        )?;
+
+        if fee > 0 {
+            emit_cpi!(FeeCharged {
+                pool_config: pool_config.key(),
+                payer: ctx.accounts.user.key(),
+                recipient: recipient.key(),
+                kind: FeeKind::Unstake,
+                mint: Some(pool_config.stake_mint),
+                amount: fee,
+                slot: clock.slot,
+            });
+        }
+    }
 
-    // Update user and pool info
-    user_info.staked_amount -= real_amount;
-    user_info.reward_debt =
-        (user_info.staked_amount * pool_state.acc_token_per_share) / precision_factor;
+    // Transfer unstake amount from pool to user. Native SOL is collected in
+    // the user's wSOL account, which is closed into their wallet
+    let (withdraw_to, native_withdraw) = token::payout_account(
+        &ctx.accounts.user_stake_token_vault,
+        &ctx.accounts.stake_mint,
+        &ctx.accounts.wsol_account
+    )?;
+    let native_withdraw = native_withdraw && withdrawn > 0;
+    if native_withdraw {
+        token::open_wsol_account(
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            &ctx.accounts.stake_mint,
+            withdraw_to.clone(),
+            ctx.accounts.user.to_account_info(),
+            wsol_seeds
+        )?;
+    }
+    token::transfer_checked(
+        &ctx.accounts.token_program,
+        ctx.accounts.pool_stake_token_vault.to_account_info(),
+        &ctx.accounts.stake_mint,
+        withdraw_to.clone(),
+        ctx.accounts.admin.to_account_info(),
+        ctx.remaining_accounts,
+        withdrawn
+    )?;
+    if native_withdraw {
+        token::close_wsol_account(
+            &ctx.accounts.token_program,
+            withdraw_to,
+            ctx.accounts.user.to_account_info(),
+            wsol_seeds
+        )?;
+    }
 
-    pool_state.total_staked -= real_amount;
+    if amounts.vested > 0 {
+        emit_cpi!(RewardVested {
+            pool_config: pool_config.key(),
+            user: ctx.accounts.user.key(),
+            position,
+            amount: amounts.vested,
+            vesting_amount: user_info.vesting_amount,
+            vesting_end_slot: user_info.vesting_end_slot,
+            slot: clock.slot,
+        });
+    }
+    if amounts.forfeited > 0 {
+        emit_cpi!(RewardForfeited {
+            pool_config: pool_config.key(),
+            user: ctx.accounts.user.key(),
+            position,
+            amount: amounts.forfeited,
+            slot: clock.slot,
+        });
+    }
+    emit_cpi!(Withdraw {
+        pool_config: pool_config.key(),
+        user: ctx.accounts.user.key(),
+        amount: amounts.withdrawn,
+        fee: amounts.fee,
+        staked_amount: user_info.staked_amount,
+        total_staked: pool_state.total_staked,
+        slot: clock.slot,
+    });
+    if let Some(ticket) = &mut ctx.accounts.withdrawal_ticket {
+        ticket.pool_config = pool_config.key();
+        ticket.beneficiary = ctx.accounts.user.key();
+        ticket.position = position;
+        accounting::open_ticket(pool_config, pool_state, user_info, ticket, amounts.withdrawn, clock.slot)?;
+
+        emit_cpi!(TicketOpened {
+            pool_config: pool_config.key(),
+            user: ctx.accounts.user.key(),
+            position,
+            ticket: ticket.key(),
+            amount: ticket.amount,
+            unlock_slot: ticket.unlock_slot,
+            slot: clock.slot,
+        });
+    }
+    emit_cpi!(PositionChanged {
+        pool_config: pool_config.key(),
+        user: ctx.accounts.user.key(),
+        position,
+        staked_amount: user_info.staked_amount,
+        slot: clock.slot,
+    });
 
     Ok(())
 }
 
+#[event_cpi]
 #[derive(Accounts)]
+#[instruction(unstake_amount: u64, position: u32)]
 pub struct Unstake<'info> {
     /// CHECK:
     #[account(mut)]
@@ -118,35 +266,104 @@ pub struct Unstake<'info> {
     #[account(mut)]
     pub treasury: AccountInfo<'info>,
 
+    #[account(mut)]
     pub pool_config_account: Account<'info, PoolConfig>,
 
-    #[account(mut)]
+    #[account(mut, address = pool_config_account.state_addr)]
     pub pool_state_account: Account<'info, PoolState>,
 
     pub platform: Account<'info, PlatformInfo>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [pool_config_account.key().as_ref(), user.key().as_ref(), &position_seed(position)],
+        bump
+    )]
     pub user_info: Account<'info, UserInfo>,
 
+    /// Required on pools with an unbonding period
+    #[account(
+        init,
+        payer = user,
+        space = WITHDRAWAL_TICKET_SIZE,
+        seeds = [WITHDRAWAL_TICKET_SEED, user_info.key().as_ref(), &user_info.ticket_count.to_le_bytes()],
+        bump
+    )]
+    pub withdrawal_ticket: Option<Box<Account<'info, WithdrawalTicket>>>,
+
+    /// The referrer's earnings account, required when the position's referrer
+    /// gets a share of its rewards
+    #[account(
+        mut,
+        seeds = [
+            REFERRER_SEED,
+            pool_config_account.key().as_ref(),
+            user_info.referrer.unwrap_or_default().as_ref(),
+        ],
+        bump
+    )]
+    pub referrer_info: Option<Box<Account<'info, ReferrerInfo>>>,
+
+    /// Omitted to withdraw native SOL
     #[account(mut)]
-    pub user_stake_token_vault: Account<'info, TokenAccount>,
+    pub user_stake_token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
 
+    /// The reward recipient's associated token account on positions that have
+    /// one. Omitted to be paid native SOL rewards
     #[account(mut)]
-    pub user_reward_token_vault: Account<'info, TokenAccount>,
+    pub user_reward_token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
 
-    #[account(mut)]
-    pub pool_stake_token_vault: Account<'info, TokenAccount>,
+    #[account(mut, address = pool_config_account.pool_stake_token_vault)]
+    pub pool_stake_token_vault: InterfaceAccount<'info, TokenAccount>,
 
-    #[account(mut)]
-    pub pool_reward_token_vault: Account<'info, TokenAccount>,
+    #[account(mut, address = pool_config_account.pool_reward_token_vault)]
+    pub pool_reward_token_vault: InterfaceAccount<'info, TokenAccount>,
 
     #[account(mut)]
-    pub creator_stake_token_vault: Account<'info, TokenAccount>,
-
-    #[account(mut)]
-    pub treasury_stake_token_vault: Account<'info, TokenAccount>,
-
-    pub token_program: Program<'info, token::Token>,
+    pub creator_stake_token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = treasury_stake_token_vault.owner == platform.treasury @ BrewStakingError::InvalidTreasuryTokenAccount
+    )]
+    pub treasury_stake_token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Required on reward-share pools
+    #[account(
+        mut,
+        constraint = treasury_reward_token_vault.owner == platform.treasury @ BrewStakingError::InvalidTreasuryTokenAccount
+    )]
+    pub treasury_reward_token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(address = pool_config_account.stake_mint)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(address = pool_config_account.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: created and closed again to pay out native SOL
+    #[account(
+        mut,
+        seeds = [WSOL_SEED, pool_config_account.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub wsol_account: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// The user's fee discount from the pool owner, if any
+    #[account(
+        seeds = [FEE_EXEMPTION_SEED, pool_config_account.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub pool_fee_exemption: Option<Account<'info, FeeExemption>>,
+
+    /// The user's fee discount from the platform, if any
+    #[account(
+        seeds = [FEE_EXEMPTION_SEED, platform.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub platform_fee_exemption: Option<Account<'info, FeeExemption>>,
 
     pub system_program: Program<'info, System>,
 }
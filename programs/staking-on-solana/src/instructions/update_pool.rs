@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::utils::*;
+use crate::events::*;
+
+pub fn handler(ctx: Context<UpdatePool>) -> Result<()> {
+    let pool_config = &ctx.accounts.pool_config_account;
+    let pool_state = &mut ctx.accounts.pool_state_account;
+
+    let clock = Clock::get()?;
+
+    update_pool(pool_config, pool_state, clock.slot)?;
+
+    emit_cpi!(PoolUpdated {
+        pool_config: pool_config.key(),
+        acc_token_per_share: pool_state.acc_token_per_share,
+        should_total_paid: pool_state.should_total_paid,
+        total_earned: pool_state.total_earned,
+        slot: clock.slot,
+    });
+
+    Ok(())
+}
+
+// Anyone can bring an idle pool's reward accounting up to the current slot
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdatePool<'info> {
+    pub pool_config_account: Box<Account<'info, PoolConfig>>,
+
+    #[account(mut, address = pool_config_account.state_addr)]
+    pub pool_state_account: Box<Account<'info, PoolState>>,
+}
@@ -0,0 +1,44 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::utils::*;
+use crate::error::*;
+use crate::events::*;
+
+pub fn handler<'info>(ctx: Context<'_, '_, 'info, 'info, UpdatePools<'info>>) -> Result<()> {
+    // Each pool's config account is followed by its writable state account
+    require!(
+        !ctx.remaining_accounts.is_empty() && ctx.remaining_accounts.len().is_multiple_of(2),
+        BrewStakingError::InvalidPoolAccounts
+    );
+
+    let clock = Clock::get()?;
+
+    for accounts in ctx.remaining_accounts.chunks_exact(2) {
+        let pool_config = Account::<PoolConfig>::try_from(&accounts[0])?;
+        require!(
+            accounts[1].key() == pool_config.state_addr && accounts[1].is_writable,
+            BrewStakingError::InvalidPoolAccounts
+        );
+
+        let mut pool_state = Account::<PoolState>::try_from(&accounts[1])?;
+        update_pool(&pool_config, &mut pool_state, clock.slot)?;
+        pool_state.exit(ctx.program_id)?;
+
+        emit_cpi!(PoolUpdated {
+            pool_config: pool_config.key(),
+            acc_token_per_share: pool_state.acc_token_per_share,
+            should_total_paid: pool_state.should_total_paid,
+            total_earned: pool_state.total_earned,
+            slot: clock.slot,
+        });
+    }
+
+    Ok(())
+}
+
+// `update_pool` for several pools at once. The pools are passed in
+// `remaining_accounts` as (config, writable state) pairs
+#[event_cpi]
+#[derive(Accounts)]
+pub struct UpdatePools<'info> {}
@@ -0,0 +1,125 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{ Mint, TokenAccount, TokenInterface };
+
+use crate::state::*;
+use crate::utils::*;
+use crate::error::*;
+use crate::events::*;
+
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, WithdrawTicket<'info>>) -> Result<()> {
+    let pool_config = &ctx.accounts.pool_config_account;
+    let pool_state = &mut ctx.accounts.pool_state_account;
+    let user_info = &mut ctx.accounts.user_info;
+    let ticket = &ctx.accounts.withdrawal_ticket;
+
+    let clock = Clock::get()?;
+
+    accounting::withdraw_ticket(pool_state, user_info, ticket, clock.slot)?;
+
+    let (withdraw_to, native_withdraw) = token::payout_account(
+        &ctx.accounts.user_stake_token_vault,
+        &ctx.accounts.stake_mint,
+        &ctx.accounts.wsol_account
+    )?;
+    let native_payout = native_withdraw && ticket.amount > 0;
+    let pool_key = pool_config.key();
+    let user_key = ctx.accounts.user.key();
+    let wsol_seeds: &[&[u8]] = &[WSOL_SEED, pool_key.as_ref(), user_key.as_ref(), &[ctx.bumps.wsol_account]];
+    if native_payout {
+        token::open_wsol_account(
+            &ctx.accounts.system_program,
+            &ctx.accounts.token_program,
+            &ctx.accounts.stake_mint,
+            withdraw_to.clone(),
+            ctx.accounts.user.to_account_info(),
+            wsol_seeds
+        )?;
+    }
+
+    // Transfer the unbonded amount from pool to user
+    token::transfer_checked(
+        &ctx.accounts.token_program,
+        ctx.accounts.pool_stake_token_vault.to_account_info(),
+        &ctx.accounts.stake_mint,
+        withdraw_to.clone(),
+        ctx.accounts.admin.to_account_info(),
+        ctx.remaining_accounts,
+        ticket.amount
+    )?;
+
+    if native_payout {
+        token::close_wsol_account(
+            &ctx.accounts.token_program,
+            withdraw_to,
+            ctx.accounts.user.to_account_info(),
+            wsol_seeds
+        )?;
+    }
+
+    emit_cpi!(TicketClosed {
+        pool_config: pool_config.key(),
+        user: ctx.accounts.user.key(),
+        position: ticket.position,
+        ticket: ticket.key(),
+        amount: ticket.amount,
+        cancelled: false,
+        slot: clock.slot,
+    });
+
+    Ok(())
+}
+
+#[event_cpi]
+#[derive(Accounts)]
+pub struct WithdrawTicket<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    /// CHECK:
+    pub admin: Signer<'info>,
+
+    pub pool_config_account: Box<Account<'info, PoolConfig>>,
+
+    #[account(mut, address = pool_config_account.state_addr)]
+    pub pool_state_account: Box<Account<'info, PoolState>>,
+
+    #[account(
+        mut,
+        close = user,
+        constraint = withdrawal_ticket.pool_config == pool_config_account.key() @ BrewStakingError::InvalidDelayedUnstakeTicket,
+        constraint = withdrawal_ticket.beneficiary == user.key() @ BrewStakingError::WrongBeneficiary
+    )]
+    pub withdrawal_ticket: Box<Account<'info, WithdrawalTicket>>,
+
+    #[account(
+        mut,
+        seeds = [
+            pool_config_account.key().as_ref(),
+            user.key().as_ref(),
+            &position_seed(withdrawal_ticket.position),
+        ],
+        bump
+    )]
+    pub user_info: Box<Account<'info, UserInfo>>,
+
+    /// Omitted to withdraw native SOL
+    #[account(mut)]
+    pub user_stake_token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, address = pool_config_account.pool_stake_token_vault)]
+    pub pool_stake_token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = pool_config_account.stake_mint)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: created and closed again to pay out native SOL
+    #[account(
+        mut,
+        seeds = [WSOL_SEED, pool_config_account.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub wsol_account: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
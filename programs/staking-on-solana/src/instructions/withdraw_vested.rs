@@ -0,0 +1,19 @@
+use anchor_lang::prelude::*;
+
+use crate::utils::*;
+
+use super::claim_reward::{ self, ClaimReward };
+
+// Pays out the position's vested reward like a claim, fees included. Takes the
+// same accounts as `claim_reward`.
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, ClaimReward<'info>>, position: u32) -> Result<()> {
+    let clock = Clock::get()?;
+
+    let amount = accounting::withdraw_vested(
+        &mut ctx.accounts.pool_state_account,
+        &mut ctx.accounts.user_info,
+        clock.slot
+    )?;
+
+    claim_reward::pay_reward(ctx, position, amount)
+}
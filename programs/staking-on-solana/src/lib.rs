@@ -1,12 +1,14 @@
 use anchor_lang::prelude::*;
 
 use instructions::*;
+use state::{ AllowlistProof, PerformanceFeeModel, ReferralSource };
+use utils::{ PoolInfo, UserPosition };
 
 mod instructions;
-mod state;
-mod utils;
-mod error;
-mod events;
+pub mod state;
+pub mod utils;
+pub mod error;
+pub mod events;
 
 declare_id!("9X5si3xhU4nFVh7FkGaC3n251xoN5JBoys9AEnrfkzxh");
 
@@ -17,19 +19,29 @@ pub mod staking_on_solana {
     pub fn initialize(
         ctx: Context<Initialize>,
         deploy_fee: u64,
-        performance_fee: u64
+        performance_fee: u64,
+        treasury_fee_share: u16,
+        reward_performance_fee: u16
     ) -> Result<()> {
-        instructions::initialize::handler(ctx, deploy_fee, performance_fee)
+        instructions::initialize::handler(
+            ctx,
+            deploy_fee,
+            performance_fee,
+            treasury_fee_share,
+            reward_performance_fee
+        )
     }
 
-    pub fn create_pool(
-        ctx: Context<CreatePool>,
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_pool<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreatePool<'info>>,
         pool_id: String,
         stake_fee: u16,
         unstake_fee: u16,
         initial_funding: u64,
         reward_per_slot: u64,
-        duration: u16
+        duration: u16,
+        performance_fee_model: PerformanceFeeModel
     ) -> Result<()> {
         instructions::create_pool::handler(
             ctx,
@@ -38,35 +50,189 @@ pub mod staking_on_solana {
             unstake_fee,
             initial_funding,
             reward_per_slot,
-            duration
+            duration,
+            performance_fee_model
         )
     }
 
-    pub fn stake(ctx: Context<Stake>, stake_amount: u64) -> Result<()> {
-        instructions::stake::handler(ctx, stake_amount)
+    pub fn stake<'info>(
+        ctx: Context<'_, '_, '_, 'info, Stake<'info>>,
+        stake_amount: u64,
+        min_received: u64,
+        allowlist_proof: Option<AllowlistProof>,
+        lock_weeks: u16,
+        position: u32,
+        referrer: Option<Pubkey>
+    ) -> Result<()> {
+        instructions::stake::handler(ctx, stake_amount, min_received, allowlist_proof, lock_weeks, position, referrer)
+    }
+
+    pub fn unstake<'info>(
+        ctx: Context<'_, '_, '_, 'info, Unstake<'info>>,
+        unstake_amount: u64,
+        position: u32
+    ) -> Result<()> {
+        instructions::unstake::handler(ctx, unstake_amount, position)
+    }
+
+    pub fn exit<'info>(
+        ctx: Context<'_, '_, '_, 'info, Unstake<'info>>,
+        min_withdrawn: u64,
+        position: u32,
+        close: bool
+    ) -> Result<()> {
+        instructions::exit::handler(ctx, min_withdrawn, position, close)
     }
 
-    pub fn unstake(ctx: Context<Unstake>, unstake_amount: u64) -> Result<()> {
-        instructions::unstake::handler(ctx, unstake_amount)
+    pub fn claim_reward<'info>(ctx: Context<'_, '_, '_, 'info, ClaimReward<'info>>, position: u32) -> Result<()> {
+        instructions::claim_reward::handler(ctx, position)
     }
 
-    pub fn claim_reward(ctx: Context<ClaimReward>) -> Result<()> {
-        instructions::claim_reward::handler(ctx)
+    pub fn claim_all<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimAll<'info>>, positions: Vec<u32>) -> Result<()> {
+        instructions::claim_all::handler(ctx, positions)
     }
 
     pub fn start_reward(ctx: Context<StartReward>) -> Result<()> {
         instructions::start_reward::handler(ctx)
     }
 
-    pub fn stop_reward(ctx: Context<StopReward>) -> Result<()> {
+    pub fn stop_reward<'info>(ctx: Context<'_, '_, '_, 'info, StopReward<'info>>) -> Result<()> {
         instructions::stop_reward::handler(ctx)
     }
 
-    pub fn compound_reward(ctx: Context<CompoundReward>) -> Result<()> {
-        instructions::compound_reward::handler(ctx)
+    pub fn compound_reward<'info>(
+        ctx: Context<'_, '_, '_, 'info, CompoundReward<'info>>,
+        position: u32
+    ) -> Result<()> {
+        instructions::compound_reward::handler(ctx, position)
     }
 
     pub fn pending_reward(ctx: Context<PendingReward>) -> Result<u64> {
         instructions::pending_reward::handler(ctx)
     }
+
+    pub fn get_pool_info(ctx: Context<GetPoolInfo>) -> Result<PoolInfo> {
+        instructions::get_pool_info::handler(ctx)
+    }
+
+    pub fn get_user_position(ctx: Context<GetUserPosition>, position: u32) -> Result<UserPosition> {
+        instructions::get_user_position::handler(ctx, position)
+    }
+
+    pub fn fund_pool<'info>(ctx: Context<'_, '_, '_, 'info, FundPool<'info>>, amount: u64) -> Result<()> {
+        instructions::fund_pool::handler(ctx, amount)
+    }
+
+    pub fn allow_hook_mint(ctx: Context<AllowHookMint>, allowed: bool) -> Result<()> {
+        instructions::allow_hook_mint::handler(ctx, allowed)
+    }
+
+    pub fn set_pool_fee_exemption(
+        ctx: Context<SetPoolFeeExemption>,
+        wallet: Pubkey,
+        discount: u16
+    ) -> Result<()> {
+        instructions::set_pool_fee_exemption::handler(ctx, wallet, discount)
+    }
+
+    pub fn set_platform_fee_exemption(
+        ctx: Context<SetPlatformFeeExemption>,
+        wallet: Pubkey,
+        discount: u16
+    ) -> Result<()> {
+        instructions::set_platform_fee_exemption::handler(ctx, wallet, discount)
+    }
+
+    pub fn set_stake_limits(
+        ctx: Context<SetStakeLimits>,
+        min_stake: u64,
+        max_stake_per_wallet: u64,
+        max_total_staked: u64
+    ) -> Result<()> {
+        instructions::set_stake_limits::handler(ctx, min_stake, max_stake_per_wallet, max_total_staked)
+    }
+
+    pub fn set_allowlist(ctx: Context<SetAllowlist>, allowlist_root: Option<[u8; 32]>) -> Result<()> {
+        instructions::set_allowlist::handler(ctx, allowlist_root)
+    }
+
+    pub fn set_boost_collection(
+        ctx: Context<SetBoostCollection>,
+        boost_collection: Option<Pubkey>,
+        boost_multiplier: u16
+    ) -> Result<()> {
+        instructions::set_boost_collection::handler(ctx, boost_collection, boost_multiplier)
+    }
+
+    pub fn lock_boost_nft(ctx: Context<LockBoostNft>, position: u32) -> Result<()> {
+        instructions::lock_boost_nft::handler(ctx, position)
+    }
+
+    pub fn unlock_boost_nft(ctx: Context<UnlockBoostNft>, position: u32) -> Result<()> {
+        instructions::unlock_boost_nft::handler(ctx, position)
+    }
+
+    pub fn set_lock_multiplier(ctx: Context<SetLockMultiplier>, lock_multiplier: u16) -> Result<()> {
+        instructions::set_lock_multiplier::handler(ctx, lock_multiplier)
+    }
+
+    pub fn extend_lock(ctx: Context<ExtendLock>, lock_weeks: u16, position: u32) -> Result<()> {
+        instructions::extend_lock::handler(ctx, lock_weeks, position)
+    }
+
+    pub fn expire_lock(ctx: Context<ExpireLock>, position: u32) -> Result<()> {
+        instructions::expire_lock::handler(ctx, position)
+    }
+
+    pub fn set_unbonding_period(ctx: Context<SetUnbondingPeriod>, unbonding_slots: u64) -> Result<()> {
+        instructions::set_unbonding_period::handler(ctx, unbonding_slots)
+    }
+
+    pub fn withdraw_ticket<'info>(ctx: Context<'_, '_, '_, 'info, WithdrawTicket<'info>>) -> Result<()> {
+        instructions::withdraw_ticket::handler(ctx)
+    }
+
+    pub fn cancel_ticket(ctx: Context<CancelTicket>) -> Result<()> {
+        instructions::cancel_ticket::handler(ctx)
+    }
+
+    pub fn set_vesting(ctx: Context<SetVesting>, vesting_slots: u64, forfeit_unvested: bool) -> Result<()> {
+        instructions::set_vesting::handler(ctx, vesting_slots, forfeit_unvested)
+    }
+
+    pub fn withdraw_vested<'info>(ctx: Context<'_, '_, '_, 'info, ClaimReward<'info>>, position: u32) -> Result<()> {
+        instructions::withdraw_vested::handler(ctx, position)
+    }
+
+    pub fn set_referral(ctx: Context<SetReferral>, referral_share: u16, referral_source: ReferralSource) -> Result<()> {
+        instructions::set_referral::handler(ctx, referral_share, referral_source)
+    }
+
+    pub fn claim_referral<'info>(ctx: Context<'_, '_, '_, 'info, ClaimReferral<'info>>) -> Result<()> {
+        instructions::claim_referral::handler(ctx)
+    }
+
+    pub fn set_reward_recipient(
+        ctx: Context<SetRewardRecipient>,
+        position: u32,
+        recipient: Option<Pubkey>
+    ) -> Result<()> {
+        instructions::set_reward_recipient::handler(ctx, position, recipient)
+    }
+
+    pub fn update_pool(ctx: Context<UpdatePool>) -> Result<()> {
+        instructions::update_pool::handler(ctx)
+    }
+
+    pub fn update_pools<'info>(ctx: Context<'_, '_, 'info, 'info, UpdatePools<'info>>) -> Result<()> {
+        instructions::update_pools::handler(ctx)
+    }
+
+    pub fn close_user_info(ctx: Context<CloseUserInfo>, position: u32) -> Result<()> {
+        instructions::close_user_info::handler(ctx, position)
+    }
+
+    pub fn close_pool<'info>(ctx: Context<'_, '_, '_, 'info, ClosePool<'info>>) -> Result<()> {
+        instructions::close_pool::handler(ctx)
+    }
 }
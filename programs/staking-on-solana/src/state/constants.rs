@@ -1,3 +1,29 @@
 pub const PERCENT_PRECISION: u64 = 10000;
 pub const SLOTS_PER_DAY: u64 = 216000;
+pub const SLOTS_PER_WEEK: u64 = 7 * SLOTS_PER_DAY;
 pub const MAX_FEE: u16 = 2000;
+pub const HOOK_MINT_ALLOWANCE_SEED: &[u8] = b"hook_mint";
+pub const WSOL_SEED: &[u8] = b"wsol";
+pub const FEE_EXEMPTION_SEED: &[u8] = b"fee_exemption";
+pub const BOOST_ESCROW_SEED: &[u8] = b"boost_escrow";
+pub const WITHDRAWAL_TICKET_SEED: &[u8] = b"ticket";
+pub const REFERRER_SEED: &[u8] = b"referrer";
+/// Largest NFT boost a pool can set: 3x
+pub const MAX_BOOST_MULTIPLIER: u16 = 30000;
+/// Longest stake lock
+pub const MAX_LOCK_WEEKS: u16 = 52;
+/// Largest weight a pool can give the longest lock: 4x
+pub const MAX_LOCK_MULTIPLIER: u16 = 40000;
+/// Longest unbonding period: 30 days
+pub const MAX_UNBONDING_SLOTS: u64 = 30 * SLOTS_PER_DAY;
+/// Longest reward vesting period: 365 days
+pub const MAX_VESTING_SLOTS: u64 = 365 * SLOTS_PER_DAY;
+/// Largest referral share: 50%
+pub const MAX_REFERRAL_SHARE: u16 = 5000;
+
+/// Metaplex Token Metadata, which records the collection of boost NFTs
+pub mod token_metadata {
+    use anchor_lang::prelude::*;
+
+    declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+}
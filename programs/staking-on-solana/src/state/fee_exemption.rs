@@ -0,0 +1,14 @@
+use anchor_lang::prelude::*;
+
+/// Discount on a wallet's fees, set by the pool owner for one pool or by the
+/// platform treasury for every pool.
+#[account]
+pub struct FeeExemption {
+    /// The pool config or the platform account that granted it
+    pub scope: Pubkey,
+    pub wallet: Pubkey,
+    /// In basis points; 10000 waives the fees entirely
+    pub discount: u16,
+}
+
+pub const FEE_EXEMPTION_SIZE: usize = 8 + 32 + 32 + 2;
@@ -0,0 +1,12 @@
+use anchor_lang::prelude::*;
+
+/// Lets pools use a Token-2022 mint with a transfer hook. Created by the
+/// platform treasury, one per mint.
+#[account]
+pub struct HookMintAllowance {
+    pub platform: Pubkey,
+    pub mint: Pubkey,
+    pub allowed: bool,
+}
+
+pub const HOOK_MINT_ALLOWANCE_SIZE: usize = 8 + 32 + 32 + 1;
@@ -2,10 +2,18 @@ pub mod platform_info;
 pub mod pool_config;
 pub mod pool_state;
 pub mod user_info;
+pub mod hook_mint_allowance;
+pub mod fee_exemption;
+pub mod withdrawal_ticket;
+pub mod referrer_info;
 pub mod constants;
 
 pub use platform_info::*;
 pub use pool_config::*;
 pub use pool_state::*;
 pub use user_info::*;
+pub use hook_mint_allowance::*;
+pub use fee_exemption::*;
+pub use withdrawal_ticket::*;
+pub use referrer_info::*;
 pub use constants::*;
@@ -3,8 +3,15 @@ use anchor_lang::prelude::*;
 #[account]
 pub struct PlatformInfo {
     pub deploy_fee: u64,
+    /// Lamports charged per user action, for pools on `PerformanceFeeModel::Flat`
     pub performance_fee: u64,
     pub treasury: Pubkey,
+    /// Share of every pool's stake and unstake fees sent to the treasury, in
+    /// basis points. The pool owner gets the rest
+    pub treasury_fee_share: u16,
+    /// Cut of claimed and compounded rewards sent to the treasury, in basis
+    /// points, for pools on `PerformanceFeeModel::RewardShare`
+    pub reward_performance_fee: u16,
 }
 
-pub const PLATFORM_INFO_SIZE: usize = 8 + 8 + 8 + 32;
+pub const PLATFORM_INFO_SIZE: usize = 8 + 8 + 8 + 32 + 2 + 2;
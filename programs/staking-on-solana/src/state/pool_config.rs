@@ -17,7 +17,70 @@ pub struct PoolConfig {
     pub pool_stake_token_vault: Pubkey,
     pub pool_reward_token_vault: Pubkey,
     pub state_addr: Pubkey,
+    /// Token program of both mints: classic SPL Token or Token-2022
+    pub token_program: Pubkey,
+    /// Stake and unstake fees paid to the pool owner so far
+    pub creator_fees: u64,
+    /// Stake and unstake fees paid to the platform treasury so far
+    pub treasury_fees: u64,
+    pub performance_fee_model: PerformanceFeeModel,
+    /// Smallest deposit `stake` accepts, 0 for none
+    pub min_stake: u64,
+    /// Most a single wallet can have staked, 0 for no cap
+    pub max_stake_per_wallet: u64,
+    /// Most the whole pool can have staked, 0 for no cap
+    pub max_total_staked: u64,
+    /// Merkle root of the (wallet, allocation) allowlist; only listed wallets
+    /// can stake, up to their allocation. `None` for an open pool
+    pub allowlist_root: Option<[u8; 32]>,
+    /// Verified Metaplex collection whose NFTs boost a stake, `None` for no boosts
+    pub boost_collection: Option<Pubkey>,
+    /// Boost for locking one of its NFTs, in basis points of the stake
+    pub boost_multiplier: u16,
+    /// Weight of a `MAX_LOCK_WEEKS` lock in basis points, shorter locks scale
+    /// down linearly to 1x. 0 when the pool has no locks
+    pub lock_multiplier: u16,
+    /// Slots unstaked tokens wait in a withdrawal ticket. 0 pays them out
+    /// right away
+    pub unbonding_slots: u64,
+    /// Slots rewards vest over before they can be withdrawn. 0 pays them out
+    /// right away
+    pub vesting_slots: u64,
+    /// Unstaking gives up the matching share of the still vesting reward to
+    /// the other stakers
+    pub forfeit_unvested: bool,
+    /// Share of `referral_source` paid to a staker's referrer, in basis
+    /// points. 0 when the pool has no referrals
+    pub referral_share: u16,
+    pub referral_source: ReferralSource,
+}
+
+/// How a pool pays the platform's performance fee.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PerformanceFeeModel {
+    /// `PlatformInfo.performance_fee` lamports for every action that moves
+    /// tokens
+    Flat,
+    /// `PlatformInfo.reward_performance_fee` basis points of every reward paid
+    /// out or compounded, in reward tokens
+    RewardShare,
+}
+
+/// What referrers get their share of.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReferralSource {
+    /// The stake fee of their referees' deposits, in stake tokens
+    StakeFee,
+    /// The rewards paid out to their referees, in reward tokens
+    Rewards,
+}
+
+/// A wallet's allocation on an allowlisted pool and the Merkle proof of it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AllowlistProof {
+    pub allocation: u64,
+    pub proof: Vec<[u8; 32]>,
 }
 
 pub const POOL_CONFIG_SIZE: usize =
-    8 + 32 + (4 + 2) + 2 + 8 + 8 + 8 + 2 + 2 + 32 + 32 + 1 + 1 + 32 + 32 + 32;
+    8 + 32 + (4 + 2) + 2 + 8 + 8 + 8 + 2 + 2 + 32 + 32 + 1 + 1 + 32 + 32 + 32 + 32 + 8 + 8 + 1 + 8 + 8 + 8 + (1 + 32) + (1 + 32) + 2 + 2 + 8 + 8 + 1 + 2 + 1;
@@ -9,6 +9,18 @@ pub struct PoolState {
     pub should_total_paid: u64,
     pub paid_rewards: u64,
     pub total_earned: u64,
+    /// Sum of every user's `boosted_amount`; rewards are shared by this
+    pub total_boosted: u64,
+    /// Unstaked tokens still held for open withdrawal tickets
+    pub total_unbonding: u64,
+    /// Reward tokens in users' vesting schedules, vested or not, until withdrawn
+    pub total_vesting: u64,
+    /// Referral earnings not claimed yet, in stake tokens
+    pub referral_stake_owed: u64,
+    /// Referral earnings not claimed yet, in reward tokens
+    pub referral_reward_owed: u64,
+    /// Positions with a boost NFT in escrow
+    pub open_boosts: u64,
 }
 
-pub const POOL_STATE_SIZE: usize = 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8;
+pub const POOL_STATE_SIZE: usize = 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8;
@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+/// A referrer's earnings on one pool. The tokens stay in the pool's vaults
+/// until `claim_referral`.
+#[account]
+pub struct ReferrerInfo {
+    pub pool_config: Pubkey,
+    pub referrer: Pubkey,
+    /// Stake positions that named this referrer
+    pub referred_count: u64,
+    /// Unclaimed share of referees' stake fees, in stake tokens
+    pub stake_earned: u64,
+    /// Unclaimed share of referees' rewards, in reward tokens
+    pub reward_earned: u64,
+}
+
+pub const REFERRER_INFO_SIZE: usize = 8 + 32 + 32 + 8 + 8 + 8;
@@ -5,6 +5,46 @@ pub struct UserInfo {
     pub staked_amount: u64,
     pub reward_debt: u64,
     pub deposit_slot: u64,
+    /// Stake weight in reward distribution: `staked_amount` times the boost
+    pub boosted_amount: u64,
+    /// Boost of the locked NFT in basis points, 0 without one
+    pub boost_multiplier: u16,
+    /// Mint of the NFT locked in the boost escrow
+    pub boost_mint: Option<Pubkey>,
+    /// Reward earned before the boost last changed, paid with the next payout
+    pub reward_credit: u64,
+    /// Slot the stake unlocks at; nothing can be unstaked before it
+    pub lock_end_slot: u64,
+    /// Weight of the lock in basis points, 0 without one. Kept until the lock
+    /// ends
+    pub lock_multiplier: u16,
+    /// Withdrawal tickets opened so far; seeds the next one
+    pub ticket_count: u64,
+    /// Withdrawal tickets not withdrawn or cancelled yet
+    pub open_tickets: u64,
+    /// Reward still vesting, released linearly until `vesting_end_slot`
+    pub vesting_amount: u64,
+    /// Reward that has vested, ready for `withdraw_vested`
+    pub vested_amount: u64,
+    /// Slot `vesting_amount` was last released at
+    pub vesting_start_slot: u64,
+    pub vesting_end_slot: u64,
+    /// Wallet that referred this position, set once on stake
+    pub referrer: Option<Pubkey>,
+    /// Wallet rewards are paid to instead of the staker
+    pub reward_recipient: Option<Pubkey>,
+    /// Allowlist allocation proven with the last stake. Compounding can't grow
+    /// the stake past it either
+    pub allocation: Option<u64>,
 }
 
-pub const USER_INFO_SIZE: usize = 8 + 8 + 8 + 8;
+pub const USER_INFO_SIZE: usize = 8 + 8 + 8 + 8 + 8 + 2 + (1 + 32) + 8 + 8 + 2 + 8 + 8 + 8 + 8 + 8 + 8 + (1 + 32) + (1 + 32) + (1 + 8);
+
+/// Last seed of a user's stake position in a pool. Position 0 has an empty
+/// seed, so its address is `[pool_config, staker]`.
+pub fn position_seed(position: u32) -> Vec<u8> {
+    match position {
+        0 => vec![],
+        position => position.to_le_bytes().to_vec(),
+    }
+}
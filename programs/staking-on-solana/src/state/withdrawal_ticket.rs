@@ -0,0 +1,16 @@
+use anchor_lang::prelude::*;
+
+/// Unstaked tokens waiting out the pool's unbonding period. They stay in the
+/// pool's stake vault until withdrawn or restaked.
+#[account]
+pub struct WithdrawalTicket {
+    pub pool_config: Pubkey,
+    pub beneficiary: Pubkey,
+    /// Position the tokens were unstaked from, and go back to on cancel
+    pub position: u32,
+    /// Stake tokens owed, after the unstake fee
+    pub amount: u64,
+    pub unlock_slot: u64,
+}
+
+pub const WITHDRAWAL_TICKET_SIZE: usize = 8 + 32 + 32 + 4 + 8 + 8;
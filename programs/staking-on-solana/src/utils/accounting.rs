@@ -0,0 +1,979 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use super::*;
+
+// Pool and user bookkeeping for each user-facing instruction. Handlers call
+// these first and then move tokens by the returned amounts.
+
+pub struct StakeAmounts {
+    // Pending reward paid out before the deposit
+    pub reward: u64,
+    // Pending reward moved into the user's vesting schedule instead
+    pub vested: u64,
+    // Stake fee, split between the pool owner and the treasury
+    pub fee: u64,
+    // Amount credited to the user's stake
+    pub staked: u64,
+}
+
+pub struct UnstakeAmounts {
+    // Pending reward paid out before the withdrawal
+    pub reward: u64,
+    // Pending reward moved into the user's vesting schedule instead
+    pub vested: u64,
+    // Unvested reward given up to the remaining stakers
+    pub forfeited: u64,
+    // Unstake fee, split between the pool owner and the treasury
+    pub fee: u64,
+    // Amount sent back to the user
+    pub withdrawn: u64,
+}
+
+pub struct ClaimAmounts {
+    // Pending reward paid out
+    pub reward: u64,
+    // Pending reward moved into the user's vesting schedule instead
+    pub vested: u64,
+}
+
+// Returned by the `get_pool_info` view, as of the slot it runs in.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PoolInfo {
+    // Stake tokens earning rewards
+    pub tvl: u64,
+    pub total_boosted: u64,
+    // Reward tokens paid out per slot, 0 outside the reward period
+    pub reward_rate: u64,
+    pub acc_token_per_share: u64,
+    // Slots left in the reward period, the whole period before it starts
+    pub remaining_slots: u64,
+    // Slots the reward tokens not yet earned last at the current rate
+    pub runway_slots: u64,
+    // Reward tokens the pool is short of paying out the whole period
+    pub insufficient_rewards: u64,
+    pub slot: u64,
+}
+
+// Returned by the `get_user_position` view, as of the slot it runs in.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct UserPosition {
+    pub staked_amount: u64,
+    pub boosted_amount: u64,
+    pub pending_reward: u64,
+    pub locked: bool,
+    pub lock_end_slot: u64,
+    // Fees of unstaking the whole stake now: the unstake fee in stake tokens,
+    // the flat performance fee in lamports, and the treasury's and referrer's
+    // parts of the pending reward
+    pub unstake_fee: u64,
+    pub performance_fee: u64,
+    pub reward_fee: u64,
+    pub referral_fee: u64,
+    pub slot: u64,
+}
+
+// A user's fee discounts in basis points. Pool owners can only waive the
+// pool's stake and unstake fees; the platform can waive every fee.
+#[derive(Clone, Copy, Default)]
+pub struct FeeDiscounts {
+    pub pool_fees: u16,
+    pub performance_fee: u16,
+}
+
+pub fn fee_discounts(
+    pool_exemption: Option<&FeeExemption>,
+    platform_exemption: Option<&FeeExemption>
+) -> FeeDiscounts {
+    let pool = pool_exemption.map_or(0, |exemption| exemption.discount);
+    let platform = platform_exemption.map_or(0, |exemption| exemption.discount);
+    FeeDiscounts { pool_fees: pool.max(platform), performance_fee: platform }
+}
+
+pub fn start_reward(pool_config: &mut PoolConfig, pool_state: &mut PoolState, current_slot: u64) -> Result<()> {
+    require!(pool_config.start_slot == 0, BrewStakingError::PoolAlreadyStarted);
+
+    // Calculate start and end slot
+    pool_config.start_slot = current_slot.checked_add(10).ok_or(BrewStakingError::MathOverflow)?;
+    pool_config.end_slot = pool_config.start_slot
+        .checked_add((pool_config.duration as u64) * SLOTS_PER_DAY)
+        .ok_or(BrewStakingError::MathOverflow)?;
+
+    pool_state.last_reward_slot = pool_config.start_slot;
+
+    Ok(())
+}
+
+// Ends the reward period and returns the reward tokens that were never earned.
+// The period can only be cut short: stopping again, or after it ended, keeps
+// the earlier end slot.
+pub fn stop_reward(pool_config: &mut PoolConfig, pool_state: &mut PoolState, current_slot: u64) -> Result<u64> {
+    require!(pool_config.start_slot > 0, BrewStakingError::PoolNotStarted);
+
+    update_pool(pool_config, pool_state, current_slot)?;
+
+    // Everything not earned by stakers goes back
+    let refund = pool_state.reward_amount
+        .saturating_add(pool_state.paid_rewards)
+        .saturating_sub(pool_state.should_total_paid);
+    pool_state.reward_amount -= refund;
+
+    pool_config.end_slot = pool_config.end_slot.min(current_slot);
+
+    Ok(refund)
+}
+
+pub fn set_stake_limits(
+    pool_config: &mut PoolConfig,
+    min_stake: u64,
+    max_stake_per_wallet: u64,
+    max_total_staked: u64
+) -> Result<()> {
+    require!(
+        max_stake_per_wallet == 0 || min_stake <= max_stake_per_wallet,
+        BrewStakingError::InvalidStakeLimits
+    );
+    require!(
+        max_total_staked == 0 || min_stake <= max_total_staked,
+        BrewStakingError::InvalidStakeLimits
+    );
+
+    pool_config.min_stake = min_stake;
+    pool_config.max_stake_per_wallet = max_stake_per_wallet;
+    pool_config.max_total_staked = max_total_staked;
+
+    Ok(())
+}
+
+pub fn fund_pool(pool_state: &mut PoolState, amount: u64) -> Result<()> {
+    pool_state.reward_amount = pool_state.reward_amount
+        .checked_add(amount)
+        .ok_or(BrewStakingError::MathOverflow)?;
+    Ok(())
+}
+
+pub fn pending_reward(user_info: &UserInfo, pool_state: &PoolState, precision_factor: u64) -> Result<u64> {
+    let accumulated = mul_div(
+        user_info.boosted_amount,
+        pool_state.acc_token_per_share,
+        precision_factor
+    )?;
+    accumulated
+        .saturating_sub(user_info.reward_debt)
+        .checked_add(user_info.reward_credit)
+        .ok_or(error!(BrewStakingError::MathOverflow))
+}
+
+// Reward the user could claim at `current_slot`, without touching the pool.
+pub fn pending_reward_at(
+    pool_config: &PoolConfig,
+    pool_state: &PoolState,
+    user_info: &UserInfo,
+    current_slot: u64
+) -> Result<u64> {
+    let mut pool_state = pool_state.clone();
+    let mut user_info = user_info.clone();
+    release_ended_lock(pool_config, &mut pool_state, &mut user_info, current_slot)?;
+    update_pool(pool_config, &mut pool_state, current_slot)?;
+    pending_reward(&user_info, &pool_state, get_precision_factor(pool_config))
+}
+
+pub fn pool_info(pool_config: &PoolConfig, pool_state: &PoolState, current_slot: u64) -> Result<PoolInfo> {
+    let mut pool_state = pool_state.clone();
+    update_pool(pool_config, &mut pool_state, current_slot)?;
+
+    let remaining_slots = if pool_config.start_slot == 0 {
+        (pool_config.duration as u64) * SLOTS_PER_DAY
+    } else {
+        get_multiplier(current_slot.max(pool_config.start_slot), pool_config.end_slot, pool_config.end_slot)
+    };
+    let reward_rate = if pool_config.start_slot > 0 && remaining_slots > 0 { pool_config.reward_per_slot } else { 0 };
+    // Earned rewards are owed to the stakers already
+    let unearned = available_reward_tokens(pool_config, &pool_state).saturating_sub(pool_state.total_earned);
+    let runway_slots = match pool_config.reward_per_slot {
+        0 => 0,
+        reward_per_slot => (unearned / reward_per_slot).min(remaining_slots),
+    };
+
+    Ok(PoolInfo {
+        tvl: pool_state.total_staked,
+        total_boosted: pool_state.total_boosted,
+        reward_rate,
+        acc_token_per_share: pool_state.acc_token_per_share,
+        remaining_slots,
+        runway_slots,
+        insufficient_rewards: insufficient_rewards(pool_config, &pool_state),
+        slot: current_slot,
+    })
+}
+
+pub fn user_position(
+    pool_config: &PoolConfig,
+    pool_state: &PoolState,
+    platform: &PlatformInfo,
+    user_info: &UserInfo,
+    discounts: FeeDiscounts,
+    current_slot: u64
+) -> Result<UserPosition> {
+    let pending = pending_reward_at(pool_config, pool_state, user_info, current_slot)?;
+    let unstake_fee = fee_amount(user_info.staked_amount, pool_config.unstake_fee);
+    // Vesting pools vest the reward instead of paying it out
+    let paid = if pool_config.vesting_slots > 0 { 0 } else { pending };
+
+    Ok(UserPosition {
+        staked_amount: user_info.staked_amount,
+        boosted_amount: user_info.boosted_amount,
+        pending_reward: pending,
+        locked: current_slot < user_info.lock_end_slot,
+        lock_end_slot: user_info.lock_end_slot,
+        unstake_fee: discounted(unstake_fee, discounts.pool_fees),
+        performance_fee: flat_performance_fee(pool_config, platform, discounts),
+        reward_fee: reward_performance_fee(pool_config, platform, paid, discounts),
+        referral_fee: referral_cut(pool_config, user_info, ReferralSource::Rewards, paid),
+        slot: current_slot,
+    })
+}
+
+pub fn fee_amount(amount: u64, fee: u16) -> u64 {
+    // Fees are capped at PERCENT_PRECISION, so the fee never exceeds the amount
+    (((amount as u128) * (fee as u128)) / (PERCENT_PRECISION as u128)) as u64
+}
+
+// `fee` less `discount` basis points of it.
+pub fn discounted(fee: u64, discount: u16) -> u64 {
+    fee - fee_amount(fee, discount)
+}
+
+// Lamports a user action pays under the flat performance fee model.
+pub fn flat_performance_fee(pool_config: &PoolConfig, platform: &PlatformInfo, discounts: FeeDiscounts) -> u64 {
+    match pool_config.performance_fee_model {
+        PerformanceFeeModel::Flat => discounted(platform.performance_fee, discounts.performance_fee),
+        PerformanceFeeModel::RewardShare => 0,
+    }
+}
+
+// Part of a reward payout sent to the treasury under the reward-share model.
+pub fn reward_performance_fee(
+    pool_config: &PoolConfig,
+    platform: &PlatformInfo,
+    reward: u64,
+    discounts: FeeDiscounts
+) -> u64 {
+    match pool_config.performance_fee_model {
+        PerformanceFeeModel::Flat => 0,
+        PerformanceFeeModel::RewardShare => {
+            let fee = fee_amount(reward, platform.reward_performance_fee);
+            discounted(fee, discounts.performance_fee)
+        }
+    }
+}
+
+// Splits a stake or unstake fee into the pool owner's and the treasury's
+// parts, and adds them to the pool's fee totals.
+pub fn split_fee(pool_config: &mut PoolConfig, fee: u64, treasury_fee_share: u16) -> Result<(u64, u64)> {
+    let treasury_fee = fee_amount(fee, treasury_fee_share);
+    let creator_fee = fee - treasury_fee;
+
+    pool_config.creator_fees = pool_config.creator_fees
+        .checked_add(creator_fee)
+        .ok_or(BrewStakingError::MathOverflow)?;
+    pool_config.treasury_fees = pool_config.treasury_fees
+        .checked_add(treasury_fee)
+        .ok_or(BrewStakingError::MathOverflow)?;
+
+    Ok((creator_fee, treasury_fee))
+}
+
+// Fails when the user's stake or the pool's total stake is over the pool's
+// caps, or the user's stake over their allowlist allocation. Called once `added`
+// has been credited; adding nothing always passes, so positions over a lowered
+// cap can still harvest.
+fn check_stake_caps(
+    pool_config: &PoolConfig,
+    pool_state: &PoolState,
+    user_info: &UserInfo,
+    added: u64
+) -> Result<()> {
+    if added == 0 {
+        return Ok(());
+    }
+    require!(
+        pool_config.max_stake_per_wallet == 0 ||
+            user_info.staked_amount <= pool_config.max_stake_per_wallet,
+        BrewStakingError::WalletStakeCapExceeded
+    );
+    if let (Some(_), Some(allocation)) = (pool_config.allowlist_root, user_info.allocation) {
+        require!(user_info.staked_amount <= allocation, BrewStakingError::AllocationExceeded);
+    }
+    require!(
+        pool_config.max_total_staked == 0 || pool_state.total_staked <= pool_config.max_total_staked,
+        BrewStakingError::PoolStakeCapExceeded
+    );
+    Ok(())
+}
+
+// Recomputes the user's reward weight after their stake, NFT boost or lock
+// changed and moves the pool total by the difference. The weight is the stake
+// times both multipliers, where 0 counts as 1x.
+fn sync_boost(pool_state: &mut PoolState, user_info: &mut UserInfo) -> Result<()> {
+    let multiplier = |multiplier: u16| {
+        if multiplier == 0 { PERCENT_PRECISION } else { multiplier as u64 }
+    };
+    let boosted_amount = mul_div(
+        mul_div(user_info.staked_amount, multiplier(user_info.boost_multiplier), PERCENT_PRECISION)?,
+        multiplier(user_info.lock_multiplier),
+        PERCENT_PRECISION
+    )?;
+    pool_state.total_boosted = (pool_state.total_boosted - user_info.boosted_amount)
+        .checked_add(boosted_amount)
+        .ok_or(BrewStakingError::MathOverflow)?;
+    user_info.boosted_amount = boosted_amount;
+    Ok(())
+}
+
+// The debt rounds up while pending rewards round down, so stakers can never be
+// paid more in total than the pool accrued.
+fn sync_reward_debt(user_info: &mut UserInfo, pool_state: &PoolState, precision_factor: u64) -> Result<()> {
+    user_info.reward_debt = mul_div_ceil(
+        user_info.boosted_amount,
+        pool_state.acc_token_per_share,
+        precision_factor
+    )?;
+    Ok(())
+}
+
+// Books the user's pending reward as paid out of the pool's reward tokens and
+// returns it. The caller re-syncs the reward debt.
+fn pay_pending_reward(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    precision_factor: u64
+) -> Result<u64> {
+    let pending = pending_reward(user_info, pool_state, precision_factor)?;
+    require!(
+        available_reward_tokens(pool_config, pool_state) >= pending,
+        BrewStakingError::InsufficientReward
+    );
+
+    pool_state.reward_amount -= pending;
+    pool_state.total_earned = pool_state.total_earned.saturating_sub(pending);
+    pool_state.paid_rewards += pending;
+    user_info.reward_credit = 0;
+
+    Ok(pending)
+}
+
+// Moves the part of the user's vesting reward that has vested since the last
+// release to `vested_amount`.
+fn release_vested(user_info: &mut UserInfo, current_slot: u64) -> Result<()> {
+    if current_slot >= user_info.vesting_end_slot {
+        user_info.vested_amount = user_info.vested_amount
+            .checked_add(user_info.vesting_amount)
+            .ok_or(BrewStakingError::MathOverflow)?;
+        user_info.vesting_amount = 0;
+    } else if current_slot > user_info.vesting_start_slot {
+        let released = mul_div(
+            user_info.vesting_amount,
+            current_slot - user_info.vesting_start_slot,
+            user_info.vesting_end_slot - user_info.vesting_start_slot
+        )?;
+        user_info.vesting_amount -= released;
+        user_info.vested_amount = user_info.vested_amount
+            .checked_add(released)
+            .ok_or(BrewStakingError::MathOverflow)?;
+    }
+    user_info.vesting_start_slot = current_slot;
+    Ok(())
+}
+
+// Moves `reward` into the user's vesting schedule on pools that vest rewards
+// and returns the amount vested. The schedule then ends at the average of its
+// old end and a full vesting period from now, weighted by amount.
+fn vest(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    reward: u64,
+    current_slot: u64
+) -> Result<u64> {
+    if pool_config.vesting_slots == 0 || reward == 0 {
+        return Ok(0);
+    }
+
+    release_vested(user_info, current_slot)?;
+    let remaining_slots = user_info.vesting_end_slot.saturating_sub(current_slot);
+    let vesting_amount = user_info.vesting_amount
+        .checked_add(reward)
+        .ok_or(BrewStakingError::MathOverflow)?;
+    let vesting_slots = ((user_info.vesting_amount as u128) * (remaining_slots as u128) +
+        (reward as u128) * (pool_config.vesting_slots as u128)) / (vesting_amount as u128);
+
+    user_info.vesting_amount = vesting_amount;
+    user_info.vesting_end_slot = current_slot
+        .checked_add(vesting_slots as u64)
+        .ok_or(BrewStakingError::MathOverflow)?;
+    pool_state.total_vesting = pool_state.total_vesting
+        .checked_add(reward)
+        .ok_or(BrewStakingError::MathOverflow)?;
+
+    Ok(reward)
+}
+
+// Takes the share of the user's vesting reward that goes with `unstaked` of
+// their stake, and hands it back to the pool for everyone else still staking.
+// Called once the user's weight dropped; the caller re-syncs their debt.
+fn forfeit_unvested(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    unstaked: u64,
+    current_slot: u64
+) -> Result<u64> {
+    if !pool_config.forfeit_unvested {
+        return Ok(0);
+    }
+
+    release_vested(user_info, current_slot)?;
+    let forfeited = mul_div(user_info.vesting_amount, unstaked, user_info.staked_amount + unstaked)?;
+    if forfeited == 0 {
+        return Ok(0);
+    }
+    user_info.vesting_amount -= forfeited;
+    pool_state.total_vesting -= forfeited;
+
+    // Booked as earned but not paid again. Without other stakers it is
+    // refunded with the rest of the unearned reward
+    pool_state.reward_amount = pool_state.reward_amount
+        .checked_add(forfeited)
+        .ok_or(BrewStakingError::MathOverflow)?;
+    pool_state.paid_rewards -= forfeited;
+    let others = pool_state.total_boosted - user_info.boosted_amount;
+    if others > 0 {
+        pool_state.acc_token_per_share = pool_state.acc_token_per_share
+            .checked_add(mul_div(forfeited, get_precision_factor(pool_config), others)?)
+            .ok_or(BrewStakingError::MathOverflow)?;
+        pool_state.total_earned = pool_state.total_earned
+            .checked_add(forfeited)
+            .ok_or(BrewStakingError::MathOverflow)?;
+    } else {
+        pool_state.should_total_paid -= forfeited;
+    }
+
+    Ok(forfeited)
+}
+
+// Weight of a lock running `lock_slots` from now: linear from 1x up to the
+// pool's `lock_multiplier` for a `MAX_LOCK_WEEKS` lock.
+fn lock_multiplier(pool_config: &PoolConfig, lock_slots: u64) -> u16 {
+    let max_lock_slots = (MAX_LOCK_WEEKS as u64) * SLOTS_PER_WEEK;
+    let bonus = ((pool_config.lock_multiplier as u64) - PERCENT_PRECISION) * lock_slots.min(max_lock_slots) /
+        max_lock_slots;
+    (PERCENT_PRECISION + bonus) as u16
+}
+
+// Locks the user's whole stake for `lock_weeks` from now. A running lock can
+// only be extended, and its weight is taken from the new length.
+fn lock(pool_config: &PoolConfig, user_info: &mut UserInfo, lock_weeks: u16, current_slot: u64) -> Result<()> {
+    require!(pool_config.lock_multiplier > 0, BrewStakingError::LocksNotEnabled);
+    require!((1..=MAX_LOCK_WEEKS).contains(&lock_weeks), BrewStakingError::InvalidLockWeeks);
+
+    let lock_slots = (lock_weeks as u64) * SLOTS_PER_WEEK;
+    let lock_end_slot = current_slot.checked_add(lock_slots).ok_or(BrewStakingError::MathOverflow)?;
+    require!(lock_end_slot >= user_info.lock_end_slot, BrewStakingError::LockCannotShorten);
+
+    user_info.lock_end_slot = lock_end_slot;
+    user_info.lock_multiplier = lock_multiplier(pool_config, lock_slots);
+
+    Ok(())
+}
+
+// Drops the weight of a lock that has run out, as of its end slot: the reward
+// up to `lock_end_slot` is kept as a credit at the locked weight and the stake
+// counts 1x after it. Reward the pool already booked past the end, when someone
+// else updated it before the lock was released, stays at the locked weight.
+// Every action on a position calls it first, before the pool is updated to
+// `current_slot`.
+fn release_ended_lock(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    current_slot: u64
+) -> Result<()> {
+    if user_info.lock_multiplier == 0 || current_slot < user_info.lock_end_slot {
+        return Ok(());
+    }
+    reweight(pool_config, pool_state, user_info, user_info.lock_end_slot, |user_info| {
+        user_info.lock_multiplier = 0;
+        Ok(())
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn stake(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    stake_amount: u64,
+    lock_weeks: u16,
+    fee_discount: u16,
+    current_slot: u64
+) -> Result<StakeAmounts> {
+    require!(
+        pool_config.start_slot > 0 && pool_config.start_slot < current_slot,
+        BrewStakingError::PoolNotStarted
+    );
+    require!(stake_amount >= pool_config.min_stake, BrewStakingError::StakeBelowMinimum);
+
+    release_ended_lock(pool_config, pool_state, user_info, current_slot)?;
+    update_pool(pool_config, pool_state, current_slot)?;
+
+    let precision_factor = get_precision_factor(pool_config);
+
+    // If user already staked before, pay out his reward so far
+    let mut reward = 0;
+    if user_info.staked_amount > 0 {
+        reward = pay_pending_reward(pool_config, pool_state, user_info, precision_factor)?;
+    }
+    let vested = vest(pool_config, pool_state, user_info, reward, current_slot)?;
+
+    if lock_weeks > 0 {
+        lock(pool_config, user_info, lock_weeks, current_slot)?;
+    }
+
+    let fee = discounted(fee_amount(stake_amount, pool_config.stake_fee), fee_discount);
+    let staked = stake_amount - fee;
+    if staked > 0 {
+        user_info.deposit_slot = current_slot;
+    }
+
+    user_info.staked_amount = user_info.staked_amount
+        .checked_add(staked)
+        .ok_or(BrewStakingError::MathOverflow)?;
+    pool_state.total_staked = pool_state.total_staked
+        .checked_add(staked)
+        .ok_or(BrewStakingError::MathOverflow)?;
+    check_stake_caps(pool_config, pool_state, user_info, staked)?;
+    sync_boost(pool_state, user_info)?;
+    sync_reward_debt(user_info, pool_state, precision_factor)?;
+
+    Ok(StakeAmounts { reward: reward - vested, vested, fee, staked })
+}
+
+pub fn unstake(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    unstake_amount: u64,
+    fee_discount: u16,
+    current_slot: u64
+) -> Result<UnstakeAmounts> {
+    require!(unstake_amount > 0, BrewStakingError::UnstakeAmountTooSmall);
+    require!(user_info.staked_amount >= unstake_amount, BrewStakingError::UnstakeAmountTooHigh);
+    require!(current_slot >= user_info.lock_end_slot, BrewStakingError::StakeLocked);
+
+    release_ended_lock(pool_config, pool_state, user_info, current_slot)?;
+    update_pool(pool_config, pool_state, current_slot)?;
+
+    let precision_factor = get_precision_factor(pool_config);
+
+    let mut reward = 0;
+    if user_info.staked_amount > 0 {
+        reward = pay_pending_reward(pool_config, pool_state, user_info, precision_factor)?;
+    }
+    let vested = vest(pool_config, pool_state, user_info, reward, current_slot)?;
+
+    let fee = discounted(fee_amount(unstake_amount, pool_config.unstake_fee), fee_discount);
+
+    user_info.staked_amount -= unstake_amount;
+    pool_state.total_staked -= unstake_amount;
+    sync_boost(pool_state, user_info)?;
+    let forfeited = forfeit_unvested(pool_config, pool_state, user_info, unstake_amount, current_slot)?;
+    sync_reward_debt(user_info, pool_state, precision_factor)?;
+
+    Ok(UnstakeAmounts { reward: reward - vested, vested, forfeited, fee, withdrawn: unstake_amount - fee })
+}
+
+pub fn claim_reward(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    current_slot: u64
+) -> Result<ClaimAmounts> {
+    release_ended_lock(pool_config, pool_state, user_info, current_slot)?;
+    update_pool(pool_config, pool_state, current_slot)?;
+
+    if user_info.staked_amount == 0 {
+        return Ok(ClaimAmounts { reward: 0, vested: 0 });
+    }
+
+    let precision_factor = get_precision_factor(pool_config);
+
+    let reward = pay_pending_reward(pool_config, pool_state, user_info, precision_factor)?;
+    let vested = vest(pool_config, pool_state, user_info, reward, current_slot)?;
+
+    sync_reward_debt(user_info, pool_state, precision_factor)?;
+
+    Ok(ClaimAmounts { reward: reward - vested, vested })
+}
+
+// Returns the vested reward the user can take out now.
+pub fn withdraw_vested(pool_state: &mut PoolState, user_info: &mut UserInfo, current_slot: u64) -> Result<u64> {
+    release_vested(user_info, current_slot)?;
+
+    let amount = user_info.vested_amount;
+    require!(amount > 0, BrewStakingError::NothingVested);
+    user_info.vested_amount = 0;
+    pool_state.total_vesting -= amount;
+
+    Ok(amount)
+}
+
+// First half of `compound_reward`: books the pending reward as paid and
+// returns it. The caller converts it to stake tokens (swapping when the pool
+// mints differ) and finishes with `restake_reward`.
+pub fn harvest_for_compound(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    current_slot: u64
+) -> Result<u64> {
+    // The reward would skip vesting by becoming stake
+    require!(pool_config.vesting_slots == 0, BrewStakingError::VestingPoolCompound);
+
+    release_ended_lock(pool_config, pool_state, user_info, current_slot)?;
+    update_pool(pool_config, pool_state, current_slot)?;
+
+    if user_info.staked_amount == 0 {
+        return Ok(0);
+    }
+
+    pay_pending_reward(pool_config, pool_state, user_info, get_precision_factor(pool_config))
+}
+
+pub fn restake_reward(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    stake_amount: u64
+) -> Result<()> {
+    pool_state.total_staked = pool_state.total_staked
+        .checked_add(stake_amount)
+        .ok_or(BrewStakingError::MathOverflow)?;
+    user_info.staked_amount = user_info.staked_amount
+        .checked_add(stake_amount)
+        .ok_or(BrewStakingError::MathOverflow)?;
+    check_stake_caps(pool_config, pool_state, user_info, stake_amount)?;
+    sync_boost(pool_state, user_info)?;
+
+    sync_reward_debt(user_info, pool_state, get_precision_factor(pool_config))
+}
+
+// Applies `change` to the user's multipliers. The reward earned at the old
+// weight is kept as a credit for the next payout.
+fn reweight(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    current_slot: u64,
+    change: impl FnOnce(&mut UserInfo) -> Result<()>
+) -> Result<()> {
+    update_pool(pool_config, pool_state, current_slot)?;
+
+    let precision_factor = get_precision_factor(pool_config);
+    user_info.reward_credit = pending_reward(user_info, pool_state, precision_factor)?;
+    change(user_info)?;
+    sync_boost(pool_state, user_info)?;
+
+    sync_reward_debt(user_info, pool_state, precision_factor)
+}
+
+// Changes the user's boost, `multiplier` basis points or 0 to remove it.
+// Boosts are never 0 while an NFT is locked, so this also counts the pool's
+// locked NFTs.
+pub fn set_boost(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    multiplier: u16,
+    current_slot: u64
+) -> Result<()> {
+    match (user_info.boost_multiplier, multiplier) {
+        (0, 1..) => {
+            pool_state.open_boosts += 1;
+        }
+        (1.., 0) => {
+            pool_state.open_boosts -= 1;
+        }
+        _ => {}
+    }
+    release_ended_lock(pool_config, pool_state, user_info, current_slot)?;
+    reweight(pool_config, pool_state, user_info, current_slot, |user_info| {
+        user_info.boost_multiplier = multiplier;
+        Ok(())
+    })
+}
+
+// Relocks the user's stake for `lock_weeks` from now.
+pub fn extend_lock(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    lock_weeks: u16,
+    current_slot: u64
+) -> Result<()> {
+    release_ended_lock(pool_config, pool_state, user_info, current_slot)?;
+    reweight(pool_config, pool_state, user_info, current_slot, |user_info| {
+        lock(pool_config, user_info, lock_weeks, current_slot)
+    })
+}
+
+// Drops the weight of an ended lock without waiting for the user to act.
+pub fn expire_lock(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    current_slot: u64
+) -> Result<()> {
+    require!(current_slot >= user_info.lock_end_slot, BrewStakingError::StakeLocked);
+    release_ended_lock(pool_config, pool_state, user_info, current_slot)
+}
+
+pub fn set_referral(pool_config: &mut PoolConfig, referral_share: u16, referral_source: ReferralSource) -> Result<()> {
+    require!(referral_share <= MAX_REFERRAL_SHARE, BrewStakingError::InvalidReferralShare);
+
+    pool_config.referral_share = referral_share;
+    pool_config.referral_source = referral_source;
+
+    Ok(())
+}
+
+// Records the position's referrer unless it has one already. Returns whether
+// it was recorded.
+pub fn set_referrer(user_info: &mut UserInfo, staker: &Pubkey, referrer: Option<Pubkey>) -> Result<bool> {
+    let Some(referrer) = referrer else {
+        return Ok(false);
+    };
+    if user_info.referrer.is_some() {
+        return Ok(false);
+    }
+    require!(referrer != *staker, BrewStakingError::SelfReferral);
+
+    user_info.referrer = Some(referrer);
+
+    Ok(true)
+}
+
+// The referrer's cut of `amount` of `source`, 0 for positions without one or
+// when the pool shares the other source.
+pub fn referral_cut(pool_config: &PoolConfig, user_info: &UserInfo, source: ReferralSource, amount: u64) -> u64 {
+    if user_info.referrer.is_none() || pool_config.referral_source != source {
+        return 0;
+    }
+    fee_amount(amount, pool_config.referral_share)
+}
+
+pub fn credit_referrer(
+    pool_state: &mut PoolState,
+    referrer_info: &mut ReferrerInfo,
+    source: ReferralSource,
+    amount: u64
+) -> Result<()> {
+    let (earned, owed) = match source {
+        ReferralSource::StakeFee => (&mut referrer_info.stake_earned, &mut pool_state.referral_stake_owed),
+        ReferralSource::Rewards => (&mut referrer_info.reward_earned, &mut pool_state.referral_reward_owed),
+    };
+    *earned = earned.checked_add(amount).ok_or(BrewStakingError::MathOverflow)?;
+    *owed = owed.checked_add(amount).ok_or(BrewStakingError::MathOverflow)?;
+    Ok(())
+}
+
+// Pays the position's rewards to `recipient`, or to the staker again with
+// `None` or the staker's own wallet.
+pub fn set_reward_recipient(user_info: &mut UserInfo, staker: &Pubkey, recipient: Option<Pubkey>) {
+    user_info.reward_recipient = recipient.filter(|recipient| recipient != staker);
+}
+
+// Credits the referrer with their cut of a position's claimed reward and
+// returns it. Their account is required whenever there is a cut.
+pub fn referral_reward(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &UserInfo,
+    referrer_info: Option<&mut ReferrerInfo>,
+    reward: u64
+) -> Result<u64> {
+    let cut = referral_cut(pool_config, user_info, ReferralSource::Rewards, reward);
+    if cut == 0 {
+        return Ok(0);
+    }
+    let referrer_info = referrer_info.ok_or(BrewStakingError::ReferrerAccountRequired)?;
+    require!(user_info.referrer == Some(referrer_info.referrer), BrewStakingError::InvalidReferrerAccount);
+    credit_referrer(pool_state, referrer_info, ReferralSource::Rewards, cut)?;
+    Ok(cut)
+}
+
+// Checks a stake position is empty before its account is closed. Its
+// withdrawal tickets need it to be withdrawn or cancelled, and seed the next
+// ones from its `ticket_count`.
+pub fn close_user_info(
+    pool_config: &PoolConfig,
+    pool_state: &PoolState,
+    user_info: &UserInfo,
+    current_slot: u64
+) -> Result<()> {
+    require!(
+        user_info.staked_amount == 0 && user_info.boost_mint.is_none(),
+        BrewStakingError::PositionNotEmpty
+    );
+    require!(user_info.open_tickets == 0, BrewStakingError::OpenWithdrawalTickets);
+    require!(
+        user_info.vesting_amount == 0 &&
+            user_info.vested_amount == 0 &&
+            pending_reward_at(pool_config, pool_state, user_info, current_slot)? == 0,
+        BrewStakingError::UnclaimedReward
+    );
+    Ok(())
+}
+
+// Checks a finished pool owes nothing to anyone and returns the reward tokens
+// left in it for the owner.
+pub fn close_pool(pool_config: &PoolConfig, pool_state: &mut PoolState, current_slot: u64) -> Result<u64> {
+    require!(current_slot > pool_config.end_slot, BrewStakingError::PoolNotEnded);
+    require!(
+        pool_state.total_staked == 0 &&
+            pool_state.total_unbonding == 0 &&
+            pool_state.total_vesting == 0 &&
+            pool_state.referral_stake_owed == 0 &&
+            pool_state.referral_reward_owed == 0 &&
+            pool_state.open_boosts == 0,
+        BrewStakingError::PoolNotEmpty
+    );
+
+    let refund = available_reward_tokens(pool_config, pool_state);
+    pool_state.reward_amount -= refund;
+
+    Ok(refund)
+}
+
+// Returns the stake and reward tokens the referrer can take out.
+pub fn claim_referral(pool_state: &mut PoolState, referrer_info: &mut ReferrerInfo) -> Result<(u64, u64)> {
+    let earned = (referrer_info.stake_earned, referrer_info.reward_earned);
+    require!(earned != (0, 0), BrewStakingError::NoReferralEarnings);
+
+    referrer_info.stake_earned = 0;
+    referrer_info.reward_earned = 0;
+    pool_state.referral_stake_owed -= earned.0;
+    pool_state.referral_reward_owed -= earned.1;
+
+    Ok(earned)
+}
+
+// Vests rewards over `vesting_slots`, or pays them out right away with 0.
+// Running schedules keep their end slot.
+pub fn set_vesting(pool_config: &mut PoolConfig, vesting_slots: u64, forfeit_unvested: bool) -> Result<()> {
+    require!(vesting_slots <= MAX_VESTING_SLOTS, BrewStakingError::InvalidVestingPeriod);
+    require!(vesting_slots > 0 || !forfeit_unvested, BrewStakingError::InvalidVestingPeriod);
+
+    pool_config.vesting_slots = vesting_slots;
+    pool_config.forfeit_unvested = forfeit_unvested;
+
+    Ok(())
+}
+
+pub fn set_unbonding_period(pool_config: &mut PoolConfig, unbonding_slots: u64) -> Result<()> {
+    require!(unbonding_slots <= MAX_UNBONDING_SLOTS, BrewStakingError::InvalidUnbondingPeriod);
+
+    pool_config.unbonding_slots = unbonding_slots;
+
+    Ok(())
+}
+
+// Holds `amount` of just unstaked tokens in `ticket` for the pool's unbonding
+// period.
+pub fn open_ticket(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    ticket: &mut WithdrawalTicket,
+    amount: u64,
+    current_slot: u64
+) -> Result<()> {
+    ticket.amount = amount;
+    ticket.unlock_slot = current_slot
+        .checked_add(pool_config.unbonding_slots)
+        .ok_or(BrewStakingError::MathOverflow)?;
+    user_info.ticket_count += 1;
+    user_info.open_tickets += 1;
+    pool_state.total_unbonding = pool_state.total_unbonding
+        .checked_add(amount)
+        .ok_or(BrewStakingError::MathOverflow)?;
+
+    Ok(())
+}
+
+pub fn withdraw_ticket(
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    ticket: &WithdrawalTicket,
+    current_slot: u64
+) -> Result<()> {
+    require!(current_slot >= ticket.unlock_slot, BrewStakingError::TicketNotDue);
+
+    pool_state.total_unbonding -= ticket.amount;
+    user_info.open_tickets -= 1;
+
+    Ok(())
+}
+
+// Restakes a ticket's tokens into its position. They were staked before, so
+// the stake limits don't apply again.
+pub fn cancel_ticket(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    user_info: &mut UserInfo,
+    ticket: &WithdrawalTicket,
+    current_slot: u64
+) -> Result<()> {
+    pool_state.total_unbonding -= ticket.amount;
+    user_info.open_tickets -= 1;
+    release_ended_lock(pool_config, pool_state, user_info, current_slot)?;
+    reweight(pool_config, pool_state, user_info, current_slot, |user_info| {
+        user_info.staked_amount = user_info.staked_amount
+            .checked_add(ticket.amount)
+            .ok_or(BrewStakingError::MathOverflow)?;
+        Ok(())
+    })?;
+    pool_state.total_staked = pool_state.total_staked
+        .checked_add(ticket.amount)
+        .ok_or(BrewStakingError::MathOverflow)?;
+
+    Ok(())
+}
+
+pub fn set_lock_multiplier(pool_config: &mut PoolConfig, lock_multiplier: u16) -> Result<()> {
+    require!(
+        lock_multiplier == 0 || (PERCENT_PRECISION as u16..=MAX_LOCK_MULTIPLIER).contains(&lock_multiplier),
+        BrewStakingError::InvalidLockMultiplier
+    );
+
+    pool_config.lock_multiplier = lock_multiplier;
+
+    Ok(())
+}
+
+pub fn set_boost_collection(
+    pool_config: &mut PoolConfig,
+    boost_collection: Option<Pubkey>,
+    boost_multiplier: u16
+) -> Result<()> {
+    require!(
+        (PERCENT_PRECISION as u16..=MAX_BOOST_MULTIPLIER).contains(&boost_multiplier),
+        BrewStakingError::InvalidBoostMultiplier
+    );
+
+    pool_config.boost_collection = boost_collection;
+    pool_config.boost_multiplier = boost_multiplier;
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+// Allowlists are sha256 Merkle trees of (wallet, allocation) leaves. Leaves
+// and inner nodes are hashed under different prefixes so an inner node can
+// never pass as a leaf, and each pair is hashed in sorted order so proofs
+// need no left/right flags.
+
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+pub fn allowlist_leaf(wallet: &Pubkey, allocation: u64) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, wallet.as_ref(), &allocation.to_le_bytes()]).to_bytes()
+}
+
+fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, first, second]).to_bytes()
+}
+
+pub fn verify_proof(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| hash_pair(&node, sibling));
+    computed == *root
+}
+
+// Root of the tree over `leaves` and the proof for `leaves[index]`, for
+// off-chain tooling. An odd node out is carried up to the next level as is.
+pub fn root_and_proof(leaves: &[[u8; 32]], index: usize) -> ([u8; 32], Vec<[u8; 32]>) {
+    let mut level = leaves.to_vec();
+    let mut index = index;
+    let mut proof = Vec::new();
+
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        level = level
+            .chunks(2)
+            .map(|pair| if pair.len() == 2 { hash_pair(&pair[0], &pair[1]) } else { pair[0] })
+            .collect();
+        index /= 2;
+    }
+
+    (level.first().copied().unwrap_or_default(), proof)
+}
@@ -0,0 +1,60 @@
+use anchor_lang::prelude::*;
+
+use crate::state::*;
+use crate::error::*;
+
+// Reads the Metaplex Token Metadata account of an NFT. Only the fields up to
+// the collection are decoded; they are laid out in this order in every
+// metadata version that has a collection.
+
+#[derive(AnchorDeserialize)]
+#[allow(dead_code)]
+struct Creator {
+    address: Pubkey,
+    verified: bool,
+    share: u8,
+}
+
+#[derive(AnchorDeserialize)]
+struct Collection {
+    verified: bool,
+    key: Pubkey,
+}
+
+#[derive(AnchorDeserialize)]
+#[allow(dead_code)]
+struct Metadata {
+    key: u8,
+    update_authority: Pubkey,
+    mint: Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+    creators: Option<Vec<Creator>>,
+    primary_sale_happened: bool,
+    is_mutable: bool,
+    edition_nonce: Option<u8>,
+    token_standard: Option<u8>,
+    collection: Option<Collection>,
+}
+
+pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"metadata", token_metadata::ID.as_ref(), mint.as_ref()],
+        &token_metadata::ID
+    ).0
+}
+
+/// The collection `mint` is a verified member of, read from its metadata
+/// account. Fails unless `metadata` is that mint's metadata account.
+pub fn verified_collection(metadata: &AccountInfo, mint: &Pubkey) -> Result<Option<Pubkey>> {
+    require_keys_eq!(metadata.key(), metadata_address(mint), BrewStakingError::InvalidBoostNft);
+    require_keys_eq!(*metadata.owner, token_metadata::ID, BrewStakingError::InvalidBoostNft);
+
+    let data = metadata.try_borrow_data()?;
+    let metadata = Metadata::deserialize(&mut &data[..]).map_err(|_| BrewStakingError::InvalidBoostNft)?;
+    require_keys_eq!(metadata.mint, *mint, BrewStakingError::InvalidBoostNft);
+
+    Ok(metadata.collection.filter(|collection| collection.verified).map(|collection| collection.key))
+}
@@ -3,100 +3,107 @@ use anchor_lang::prelude::*;
 // use anchor_spl::token_interface::TokenAccount;
 
 use crate::state::*;
+use crate::error::*;
+
+pub mod accounting;
+pub mod merkle;
+pub mod metadata;
+pub mod payout;
+pub mod token;
+
+pub use accounting::*;
+
+// The helpers below only touch plain account structs so the on-chain handlers
+// and off-chain tooling (e.g. the reward simulator) run the exact same math.
 
 // Update reward variables of the given pool to be up-to-date.
-pub fn update_pool<'info>(
-    pool_config: &Account<'info, PoolConfig>,
-    pool_state: &mut Account<'info, PoolState>
+pub fn update_pool(
+    pool_config: &PoolConfig,
+    pool_state: &mut PoolState,
+    current_slot: u64
 ) -> Result<()> {
-    let clock = Clock::get()?;
-    // msg!("@@update pool start");
-    // msg!("@@current slot {}", clock.slot);
-    if clock.slot <= pool_state.last_reward_slot || pool_state.last_reward_slot == 0 {
+    if current_slot <= pool_state.last_reward_slot || pool_state.last_reward_slot == 0 {
         return Ok(());
     }
-    // msg!("@@pool_state.total_staked {}", pool_state.total_staked);
-    if pool_state.total_staked == 0 {
-        pool_state.last_reward_slot = clock.slot;
+    if pool_state.total_boosted == 0 {
+        pool_state.last_reward_slot = current_slot;
         return Ok(());
     }
 
-    let multiplier = get_multiplier(pool_state.last_reward_slot, clock.slot, pool_config.end_slot);
-    let reward = multiplier * pool_config.reward_per_slot;
+    let multiplier = get_multiplier(pool_state.last_reward_slot, current_slot, pool_config.end_slot);
+    let reward = multiplier
+        .checked_mul(pool_config.reward_per_slot)
+        .ok_or(BrewStakingError::MathOverflow)?;
     let precision_factor = get_precision_factor(pool_config);
 
-    // msg!("@@multiplier {}", multiplier);
-    // msg!("@@pool_config.reward_per_slot {}", pool_config.reward_per_slot);
-    // msg!("@@reward {}", reward);
-    // msg!("@@precision_factor {}", precision_factor);
+    pool_state.acc_token_per_share = pool_state.acc_token_per_share
+        .checked_add(mul_div(reward, precision_factor, pool_state.total_boosted)?)
+        .ok_or(BrewStakingError::MathOverflow)?;
 
-    pool_state.acc_token_per_share += (reward * precision_factor) / pool_state.total_staked;
+    pool_state.last_reward_slot = current_slot;
+    pool_state.should_total_paid = pool_state.should_total_paid
+        .checked_add(reward)
+        .ok_or(BrewStakingError::MathOverflow)?;
+    // Earned by stakers, not paid out yet
+    pool_state.total_earned = pool_state.total_earned
+        .checked_add(reward)
+        .ok_or(BrewStakingError::MathOverflow)?;
 
-    pool_state.last_reward_slot = clock.slot;
-    pool_state.should_total_paid += reward;
-
-    // msg!("@@pool_state.acc_token_per_share {}", pool_state.acc_token_per_share);
-    // msg!("@@pool_state.last_reward_slot {}", pool_state.last_reward_slot);
-    // msg!("@@pool_state.should_total_paide {}", pool_state.should_total_paid);
-    // msg!("@@update pool end");
     Ok(())
 }
 
+// Slots between `from_slot` and `to_slot` that fall inside the reward period.
 pub fn get_multiplier(from_slot: u64, to_slot: u64, pool_end_slot: u64) -> u64 {
-    if to_slot <= pool_end_slot {
-        return to_slot - from_slot;
-    } else if from_slot >= pool_end_slot {
-        return 0;
-    } else {
-        return pool_end_slot - from_slot;
-    }
+    to_slot.min(pool_end_slot).saturating_sub(from_slot)
 }
 
-pub fn get_precision_factor(pool_config: &Account<PoolConfig>) -> u64 {
+pub fn get_precision_factor(pool_config: &PoolConfig) -> u64 {
     let base: u64 = 10;
     let precision_decimals = 9 - pool_config.reward_mint_decimals;
-    let precision_factor = base.pow(precision_decimals as u32);
-    precision_factor
+    base.pow(precision_decimals as u32)
 }
 
-pub fn insufficient_rewards(
-    pool_config: &Account<PoolConfig>,
-    pool_state: &mut Account<PoolState>
-) -> u64 {
+// `value * numerator / denominator` without overflowing the intermediate product.
+pub fn mul_div(value: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    let result = ((value as u128) * (numerator as u128)) / (denominator as u128);
+    u64::try_from(result).map_err(|_| error!(BrewStakingError::MathOverflow))
+}
+
+// `mul_div` rounded up.
+pub fn mul_div_ceil(value: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    let result = ((value as u128) * (numerator as u128)).div_ceil(denominator as u128);
+    u64::try_from(result).map_err(|_| error!(BrewStakingError::MathOverflow))
+}
+
+pub fn insufficient_rewards(pool_config: &PoolConfig, pool_state: &PoolState) -> u64 {
     let mut adjusted_should_total_paid = pool_state.should_total_paid;
-    let remain_rewards = available_reward_tokens(pool_config, pool_state) + pool_state.paid_rewards;
+    let remain_rewards = available_reward_tokens(pool_config, pool_state).saturating_add(
+        pool_state.paid_rewards
+    );
 
     if pool_config.start_slot == 0 {
-        adjusted_should_total_paid +=
-            pool_config.reward_per_slot * (pool_config.duration as u64) * SLOTS_PER_DAY;
+        adjusted_should_total_paid = adjusted_should_total_paid.saturating_add(
+            pool_config.reward_per_slot.saturating_mul((pool_config.duration as u64) * SLOTS_PER_DAY)
+        );
     } else {
         let remain_blocks = get_multiplier(
             pool_state.last_reward_slot,
             pool_config.end_slot,
             pool_config.end_slot
         );
-        adjusted_should_total_paid += pool_config.reward_per_slot * remain_blocks;
-    }
-
-    if remain_rewards >= adjusted_should_total_paid {
-        return 0;
+        adjusted_should_total_paid = adjusted_should_total_paid.saturating_add(
+            pool_config.reward_per_slot.saturating_mul(remain_blocks)
+        );
     }
 
-    return adjusted_should_total_paid - remain_rewards;
+    adjusted_should_total_paid.saturating_sub(remain_rewards)
 }
 
-pub fn available_reward_tokens(
-    pool_config: &Account<PoolConfig>,
-    pool_state: &mut Account<PoolState>
-) -> u64 {
-    let amount = pool_state.reward_amount;
-    if pool_config.reward_mint == pool_config.stake_mint {
-        if amount < pool_state.total_staked {
-            return 0;
-        }
-        return amount - pool_state.total_staked;
-    }
-    return amount;
+// Reward tokens left in the pool. `reward_amount` only ever counts reward
+// tokens, so same-mint pools need no adjustment for the stake sharing their
+// vault.
+pub fn available_reward_tokens(_pool_config: &PoolConfig, pool_state: &PoolState) -> u64 {
+    pool_state.reward_amount
 }
 
 #[macro_export]
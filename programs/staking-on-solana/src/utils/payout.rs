@@ -0,0 +1,131 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{ Mint, TokenAccount, TokenInterface };
+
+use crate::state::*;
+use crate::error::*;
+use crate::events::*;
+use super::{ accounting, token, FeeDiscounts };
+
+// Reward payouts, the same for every instruction that pays out a reward: the
+// treasury's reward-share fee goes first, then the referrer's cut, which stays
+// in the vault until they claim it, and the rest goes to the position's reward
+// recipient.
+
+/// The accounts one reward payout moves tokens between.
+pub struct RewardPayout<'a, 'info> {
+    pub pool_config: &'a Account<'info, PoolConfig>,
+    pub platform: &'a PlatformInfo,
+    pub discounts: FeeDiscounts,
+    /// The staker, who pays the fees and is paid native SOL rewards
+    pub user: AccountInfo<'info>,
+    /// Owner of the pool vaults
+    pub admin: AccountInfo<'info>,
+    pub pool_reward_token_vault: AccountInfo<'info>,
+    /// Required when the pool charges a reward-share fee
+    pub treasury_reward_token_vault: Option<AccountInfo<'info>>,
+    /// Required when the position's referrer has a cut
+    pub referrer_info: Option<Pubkey>,
+    pub reward_mint: &'a InterfaceAccount<'info, Mint>,
+    pub token_program: &'a Interface<'info, TokenInterface>,
+    pub system_program: &'a Program<'info, System>,
+    pub remaining_accounts: &'a [AccountInfo<'info>],
+    pub slot: u64,
+}
+
+impl<'a, 'info> RewardPayout<'a, 'info> {
+    /// Sends the treasury its fee on `reward` and books the `referral` cut,
+    /// which the caller credited to the referrer. Returns what is left for the
+    /// user and the fee events to emit.
+    pub fn charge_fees(&self, reward: u64, referral: u64) -> Result<(u64, Vec<FeeCharged>)> {
+        let mut events = Vec::new();
+
+        // Reward-share pools pay the treasury out of the reward
+        let fee = accounting::reward_performance_fee(self.pool_config, self.platform, reward, self.discounts);
+        if fee > 0 {
+            let treasury_reward_token_vault = self.treasury_reward_token_vault
+                .as_ref()
+                .ok_or(BrewStakingError::TreasuryRewardAccountRequired)?;
+            token::transfer_checked(
+                self.token_program,
+                self.pool_reward_token_vault.clone(),
+                self.reward_mint,
+                treasury_reward_token_vault.clone(),
+                self.admin.clone(),
+                self.remaining_accounts,
+                fee
+            )?;
+            events.push(self.fee_event(treasury_reward_token_vault.key(), FeeKind::Reward, fee));
+        }
+
+        if referral > 0 {
+            let referrer_info = self.referrer_info.ok_or(BrewStakingError::ReferrerAccountRequired)?;
+            events.push(self.fee_event(referrer_info, FeeKind::Referral, referral));
+        }
+
+        let rest = reward
+            .checked_sub(fee)
+            .and_then(|rest| rest.checked_sub(referral))
+            .ok_or(BrewStakingError::MathOverflow)?;
+        Ok((rest, events))
+    }
+
+    /// Sends `amount` to the reward recipient's token account, or to the
+    /// user's. Without one native SOL goes through the wSOL account, which is
+    /// closed into the user's wallet again.
+    pub fn send(
+        &self,
+        amount: u64,
+        reward_recipient: Option<Pubkey>,
+        user_reward_token_vault: &Option<InterfaceAccount<'info, TokenAccount>>,
+        wsol_account: &Option<UncheckedAccount<'info>>,
+        wsol_seeds: &[&[u8]]
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let (reward_to, native) = token::reward_payout_account(
+            reward_recipient,
+            user_reward_token_vault,
+            self.reward_mint,
+            wsol_account,
+            self.token_program
+        )?;
+        if native {
+            token::open_wsol_account(
+                self.system_program,
+                self.token_program,
+                self.reward_mint,
+                reward_to.clone(),
+                self.user.clone(),
+                wsol_seeds
+            )?;
+        }
+        token::transfer_checked(
+            self.token_program,
+            self.pool_reward_token_vault.clone(),
+            self.reward_mint,
+            reward_to.clone(),
+            self.admin.clone(),
+            self.remaining_accounts,
+            amount
+        )?;
+        if native {
+            token::close_wsol_account(self.token_program, reward_to, self.user.clone(), wsol_seeds)?;
+        }
+
+        Ok(())
+    }
+
+    fn fee_event(&self, recipient: Pubkey, kind: FeeKind, amount: u64) -> FeeCharged {
+        FeeCharged {
+            pool_config: self.pool_config.key(),
+            payer: self.user.key(),
+            recipient,
+            kind,
+            mint: Some(self.pool_config.reward_mint),
+            amount,
+            slot: self.slot,
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::system_program;
+use anchor_spl::token_interface::{
+    self,
+    CloseAccount,
+    InitializeAccount3,
+    Mint,
+    SyncNative,
+    TokenAccount,
+    TokenInterface,
+};
+use spl_token_2022::extension::{
+    transfer_fee::TransferFeeConfig,
+    transfer_hook,
+    BaseStateWithExtensions,
+    StateWithExtensions,
+};
+use spl_token_2022::onchain::invoke_transfer_checked;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+
+use crate::state::*;
+use crate::error::*;
+
+// Token helpers shared by the handlers. They work for both the classic token
+// program and Token-2022, whose mints may carry transfer fee and transfer
+// hook extensions.
+
+/// `transfer_checked` through either token program. `remaining_accounts` is
+/// searched for the extra accounts a transfer hook mint needs.
+pub fn transfer_checked<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    amount: u64
+) -> Result<()> {
+    transfer_checked_signed(token_program, from, mint, to, authority, remaining_accounts, amount, &[])
+}
+
+/// `transfer_checked` with a program derived `authority`.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_checked_signed<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    amount: u64,
+    signer_seeds: &[&[&[u8]]]
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    invoke_transfer_checked(
+        token_program.key,
+        from,
+        mint.to_account_info(),
+        to,
+        authority,
+        remaining_accounts,
+        amount,
+        mint.decimals,
+        signer_seeds
+    ).map_err(Into::into)
+}
+
+/// The part of `amount` a transfer fee mint withholds from the recipient in
+/// the current epoch.
+pub fn transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let mint_info = mint.to_account_info();
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    let Ok(fee_config) = mint_state.get_extension::<TransferFeeConfig>() else {
+        return Ok(0);
+    };
+    let epoch = Clock::get()?.epoch;
+    fee_config.calculate_epoch_fee(epoch, amount).ok_or_else(|| error!(BrewStakingError::MathOverflow))
+}
+
+/// What the recipient of a `transfer_checked` of `amount` is credited.
+pub fn amount_received(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    Ok(amount.saturating_sub(transfer_fee(mint, amount)?))
+}
+
+/// Refuses transfer hook mints the platform hasn't allow-listed: a hook runs
+/// arbitrary code on every transfer in and out of the pool.
+pub fn check_transfer_hook(
+    mint: &InterfaceAccount<Mint>,
+    allowance: Option<&Account<HookMintAllowance>>
+) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    if transfer_hook::get_program_id(&mint_state).is_none() {
+        return Ok(());
+    }
+    require!(
+        allowance.is_some_and(|allowance| allowance.allowed && allowance.mint == mint.key()),
+        BrewStakingError::HookMintNotAllowed
+    );
+    Ok(())
+}
+
+pub fn is_native_mint(mint: &Pubkey) -> bool {
+    *mint == spl_token::native_mint::ID || *mint == spl_token_2022::native_mint::ID
+}
+
+/// The account a payout of `mint` goes to and whether it is native SOL: the
+/// user's token account when one was passed, otherwise the wSOL account that
+/// is unwrapped into the user's wallet.
+pub fn payout_account<'info>(
+    user_token_account: &Option<InterfaceAccount<'info, TokenAccount>>,
+    mint: &InterfaceAccount<'info, Mint>,
+    wsol_account: &Option<UncheckedAccount<'info>>
+) -> Result<(AccountInfo<'info>, bool)> {
+    if let Some(user_token_account) = user_token_account {
+        return Ok((user_token_account.to_account_info(), false));
+    }
+    require!(is_native_mint(&mint.key()), BrewStakingError::TokenAccountRequired);
+    let wsol_account = wsol_account.as_ref().ok_or(BrewStakingError::WsolAccountRequired)?;
+    Ok((wsol_account.to_account_info(), true))
+}
+
+/// The account a reward payout goes to. Positions with a reward recipient are
+/// paid into the recipient's associated token account, never in native SOL.
+pub fn reward_payout_account<'info>(
+    reward_recipient: Option<Pubkey>,
+    user_token_account: &Option<InterfaceAccount<'info, TokenAccount>>,
+    mint: &InterfaceAccount<'info, Mint>,
+    wsol_account: &Option<UncheckedAccount<'info>>,
+    token_program: &Interface<'info, TokenInterface>
+) -> Result<(AccountInfo<'info>, bool)> {
+    let Some(recipient) = reward_recipient else {
+        return payout_account(user_token_account, mint, wsol_account);
+    };
+    let recipient_account = user_token_account.as_ref().ok_or(BrewStakingError::InvalidRewardRecipientAccount)?;
+    require_keys_eq!(
+        recipient_account.key(),
+        get_associated_token_address_with_program_id(&recipient, &mint.key(), &token_program.key()),
+        BrewStakingError::InvalidRewardRecipientAccount
+    );
+    Ok((recipient_account.to_account_info(), false))
+}
+
+/// Brings a native mint token account's balance in line with its lamports.
+pub fn sync_native<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    account: AccountInfo<'info>
+) -> Result<()> {
+    token_interface::sync_native(CpiContext::new(token_program.to_account_info(), SyncNative { account }))
+}
+
+/// Deposits `amount` lamports of `from` into a native mint token account.
+pub fn wrap_sol<'info>(
+    system_program: &Program<'info, System>,
+    token_program: &Interface<'info, TokenInterface>,
+    from: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    amount: u64
+) -> Result<()> {
+    let cpi_accounts = system_program::Transfer { from, to: to.clone() };
+    system_program::transfer(CpiContext::new(system_program.to_account_info(), cpi_accounts), amount)?;
+    sync_native(token_program, to)
+}
+
+/// Creates the wSOL account native SOL payouts go through at its PDA. The
+/// account is its own authority so the program can close it afterwards.
+pub fn open_wsol_account<'info>(
+    system_program: &Program<'info, System>,
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    wsol_account: AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    wsol_seeds: &[&[u8]]
+) -> Result<()> {
+    let space = spl_token_2022::state::Account::LEN;
+    let cpi_accounts = system_program::CreateAccount { from: payer, to: wsol_account.clone() };
+    system_program::create_account(
+        CpiContext::new_with_signer(system_program.to_account_info(), cpi_accounts, &[wsol_seeds]),
+        Rent::get()?.minimum_balance(space),
+        space as u64,
+        token_program.key
+    )?;
+    let cpi_accounts = InitializeAccount3 {
+        account: wsol_account.clone(),
+        mint: mint.to_account_info(),
+        authority: wsol_account,
+    };
+    token_interface::initialize_account3(CpiContext::new(token_program.to_account_info(), cpi_accounts))
+}
+
+/// Closes an empty token account whose authority is a program derived
+/// `authority`, sending its rent to `recipient`.
+pub fn close_signed<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    account: AccountInfo<'info>,
+    recipient: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]]
+) -> Result<()> {
+    let cpi_accounts = CloseAccount { account, destination: recipient, authority };
+    token_interface::close_account(
+        CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer_seeds)
+    )
+}
+
+/// Closes the wSOL account, paying its tokens and rent to `recipient` as lamports.
+pub fn close_wsol_account<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    wsol_account: AccountInfo<'info>,
+    recipient: AccountInfo<'info>,
+    wsol_seeds: &[&[u8]]
+) -> Result<()> {
+    let cpi_accounts = CloseAccount {
+        account: wsol_account.clone(),
+        destination: recipient,
+        authority: wsol_account,
+    };
+    token_interface::close_account(
+        CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, &[wsol_seeds])
+    )
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use common::{ custom_error, PoolArgs, TestEnv };
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use staking_on_solana::error::BrewStakingError;
+use staking_on_solana::state::AllowlistProof;
+use staking_on_solana::utils::merkle;
+
+// Builds the allowlist over `entries` and returns its root and the proof of
+// each entry, in order.
+fn allowlist(entries: &[(Pubkey, u64)]) -> ([u8; 32], Vec<AllowlistProof>) {
+    let leaves: Vec<[u8; 32]> = entries
+        .iter()
+        .map(|(wallet, allocation)| merkle::allowlist_leaf(wallet, *allocation))
+        .collect();
+    let proofs = entries
+        .iter()
+        .enumerate()
+        .map(|(index, (_, allocation))| AllowlistProof {
+            allocation: *allocation,
+            proof: merkle::root_and_proof(&leaves, index).1,
+        })
+        .collect();
+    (merkle::root_and_proof(&leaves, 0).0, proofs)
+}
+
+#[tokio::test]
+async fn allowlisted_pools_take_listed_wallets_up_to_their_allocation() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(1_000_000).await;
+    let carol = env.new_user(1_000_000).await;
+
+    let (root, proofs) = allowlist(&[
+        (alice.pubkey(), 600_000),
+        (bob.pubkey(), 1_000_000),
+        (Pubkey::new_unique(), 5),
+    ]);
+    env.set_allowlist(Some(root)).await.unwrap();
+    assert_eq!(env.pool_config().await.allowlist_root, Some(root));
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+
+    assert_eq!(
+        custom_error(env.stake(&alice, 100_000).await),
+        u32::from(BrewStakingError::AllowlistProofRequired)
+    );
+    // Carol is not on the list, and alice can't claim bob's allocation
+    assert_eq!(
+        custom_error(env.stake_with_proof(&carol, 100_000, proofs[1].clone()).await),
+        u32::from(BrewStakingError::InvalidAllowlistProof)
+    );
+    assert_eq!(
+        custom_error(env.stake_with_proof(&alice, 100_000, proofs[1].clone()).await),
+        u32::from(BrewStakingError::InvalidAllowlistProof)
+    );
+
+    env.stake_with_proof(&alice, 400_000, proofs[0].clone()).await.unwrap();
+    assert_eq!(
+        custom_error(env.stake_with_proof(&alice, 200_001, proofs[0].clone()).await),
+        u32::from(BrewStakingError::AllocationExceeded)
+    );
+    env.stake_with_proof(&alice, 200_000, proofs[0].clone()).await.unwrap();
+    env.stake_with_proof(&bob, 1_000_000, proofs[1].clone()).await.unwrap();
+    assert_eq!(env.pool_state().await.total_staked, 1_600_000);
+}
+
+#[tokio::test]
+async fn compounding_stays_within_the_allocation() {
+    let mut env = TestEnv::new(PoolArgs { same_mint: true, ..Default::default() }).await;
+    let alice = env.new_user(1_000_000).await;
+
+    let (root, proofs) = allowlist(&[(alice.pubkey(), 1_050_000)]);
+    env.set_allowlist(Some(root)).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake_with_proof(&alice, 1_000_000, proofs[0].clone()).await.unwrap();
+    assert_eq!(env.user_info(&alice.pubkey()).await.allocation, Some(1_050_000));
+
+    // 120..170 fills the allocation, 170..220 would go over it
+    env.warp_to(170);
+    env.compound_reward(&alice).await.unwrap();
+    assert_eq!(env.user_info(&alice.pubkey()).await.staked_amount, 1_050_000);
+    env.warp_to(220);
+    assert_eq!(
+        custom_error(env.compound_reward(&alice).await),
+        u32::from(BrewStakingError::AllocationExceeded)
+    );
+
+    // Open pools don't hold stakes to their old allocations. The 50_000
+    // reward is rounded down to 0.047 per staked token
+    env.set_allowlist(None).await.unwrap();
+    env.compound_reward(&alice).await.unwrap();
+    assert_eq!(env.user_info(&alice.pubkey()).await.staked_amount, 1_050_000 + 49_350);
+}
+
+#[tokio::test]
+async fn the_owner_rotates_and_clears_the_allowlist() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(1_000_000).await;
+
+    let (first_root, first_proofs) = allowlist(&[(alice.pubkey(), 1_000_000)]);
+    let (second_root, _) = allowlist(&[(bob.pubkey(), 1_000_000)]);
+    env.set_allowlist(Some(first_root)).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake_with_proof(&alice, 500_000, first_proofs[0].clone()).await.unwrap();
+
+    // Proofs of the old root stop working
+    env.set_allowlist(Some(second_root)).await.unwrap();
+    assert_eq!(
+        custom_error(env.stake_with_proof(&alice, 500_000, first_proofs[0].clone()).await),
+        u32::from(BrewStakingError::InvalidAllowlistProof)
+    );
+
+    env.set_allowlist(None).await.unwrap();
+    env.stake(&alice, 500_000).await.unwrap();
+    env.stake(&bob, 1_000_000).await.unwrap();
+
+    let creator = std::mem::replace(&mut env.creator, alice.insecure_clone());
+    assert!(env.set_allowlist(Some(first_root)).await.is_err());
+    env.creator = creator;
+}
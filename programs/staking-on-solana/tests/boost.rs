@@ -0,0 +1,97 @@
+mod common;
+
+use common::{ custom_error, PoolArgs, TestEnv };
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use staking_on_solana::error::BrewStakingError;
+
+// Pools reward 1_000 tokens per slot. A 2x boost makes alice's 1_000_000
+// stake weigh 2_000_000 while her NFT is locked, against bob's 3_000_000.
+
+#[tokio::test]
+async fn locked_nfts_boost_the_reward_weight() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(3_000_000).await;
+    let user = alice.pubkey();
+    let collection = Pubkey::new_unique();
+    let nft = env.create_boost_nft(&user, &collection, true).await;
+    env.set_boost_collection(Some(collection), 20_000).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+    env.stake(&bob, 3_000_000).await.unwrap();
+
+    // Unboosted for 120..220: alice earns a quarter
+    env.warp_to(220);
+    env.lock_boost_nft(&alice, &nft).await.unwrap();
+    let escrow = env.boost_escrow_address(&user);
+    assert_eq!(env.token_balance(&escrow).await, 1);
+    let info = env.user_info(&user).await;
+    assert_eq!((info.boosted_amount, info.boost_multiplier, info.boost_mint), (2_000_000, 20_000, Some(nft)));
+    assert_eq!(info.reward_credit, 25_000);
+    assert_eq!(env.pool_state().await.total_boosted, 5_000_000);
+
+    // Boosted for 220..320: alice earns 40%. The credit is paid with the claim
+    env.warp_to(320);
+    env.claim_reward(&alice).await.unwrap();
+    env.claim_reward(&bob).await.unwrap();
+    assert_eq!(env.reward_balance(&user).await, 25_000 + 40_000);
+    assert_eq!(env.reward_balance(&bob.pubkey()).await, 75_000 + 60_000);
+    assert_eq!(env.user_info(&user).await.reward_credit, 0);
+
+    // The boost follows the stake
+    env.unstake(&alice, 500_000).await.unwrap();
+    assert_eq!(env.user_info(&user).await.boosted_amount, 1_000_000);
+    assert_eq!(env.pool_state().await.total_boosted, 4_000_000);
+
+    env.unlock_boost_nft(&alice, &nft).await.unwrap();
+    assert_eq!(env.token_balance(&env.ata(&user, &nft)).await, 1);
+    assert!(env.account(&escrow).await.is_none());
+    let info = env.user_info(&user).await;
+    assert_eq!((info.boosted_amount, info.boost_multiplier, info.boost_mint), (500_000, 0, None));
+    assert_eq!(env.pool_state().await.total_boosted, 3_500_000);
+}
+
+#[tokio::test]
+async fn only_verified_collection_nfts_boost() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let user = alice.pubkey();
+    let collection = Pubkey::new_unique();
+    let unverified = env.create_boost_nft(&user, &collection, false).await;
+    let other = env.create_boost_nft(&user, &Pubkey::new_unique(), true).await;
+    let nft = env.create_boost_nft(&user, &collection, true).await;
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+
+    assert_eq!(
+        custom_error(env.lock_boost_nft(&alice, &nft).await),
+        u32::from(BrewStakingError::BoostNotEnabled)
+    );
+    assert_eq!(
+        custom_error(env.set_boost_collection(Some(collection), 30_001).await),
+        u32::from(BrewStakingError::InvalidBoostMultiplier)
+    );
+    env.set_boost_collection(Some(collection), 20_000).await.unwrap();
+
+    for mint in [unverified, other] {
+        assert_eq!(
+            custom_error(env.lock_boost_nft(&alice, &mint).await),
+            u32::from(BrewStakingError::InvalidBoostNft)
+        );
+    }
+    env.lock_boost_nft(&alice, &nft).await.unwrap();
+    assert_eq!(env.user_info(&user).await.boosted_amount, 2_000_000);
+
+    // Only the locked NFT comes back out
+    assert_eq!(
+        custom_error(env.unlock_boost_nft(&alice, &other).await),
+        u32::from(BrewStakingError::InvalidBoostNft)
+    );
+}
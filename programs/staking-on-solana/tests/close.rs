@@ -0,0 +1,130 @@
+mod common;
+
+use common::{ custom_error, PoolArgs, TestEnv };
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use staking_on_solana::error::BrewStakingError;
+use staking_on_solana::state::SLOTS_PER_DAY;
+
+#[tokio::test]
+async fn empty_positions_can_be_closed() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(1_000_000).await;
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 0).await.unwrap();
+    env.stake(&bob, 1_000_000).await.unwrap();
+
+    env.warp_to(220);
+    assert_eq!(
+        custom_error(env.close_user_info(&bob, 0).await),
+        u32::from(BrewStakingError::PositionNotEmpty)
+    );
+
+    // The rent goes back to alice
+    let address = env.user_info_address(&alice.pubkey());
+    let rent = env.lamports(&address).await;
+    let balance = env.lamports(&alice.pubkey()).await;
+    env.close_user_info(&alice, 0).await.unwrap();
+    assert!(env.account(&address).await.is_none());
+    assert_eq!(env.lamports(&alice.pubkey()).await, balance + rent);
+
+    // A staked pool can't be closed, even after its reward period
+    env.warp_to(110 + SLOTS_PER_DAY + 1);
+    assert_eq!(custom_error(env.close_pool().await), u32::from(BrewStakingError::PoolNotEmpty));
+}
+
+#[tokio::test]
+async fn positions_with_open_tickets_stay_open() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(1_000_000).await;
+    let user = alice.pubkey();
+    env.set_unbonding_period(1_000).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+    env.stake(&bob, 1_000_000).await.unwrap();
+
+    // The ticket opened by the exit keeps the position open
+    env.warp_to(220);
+    let address = env.user_info_address(&user);
+    env.exit(&alice, 0, 0, true).await.unwrap();
+    assert_eq!(env.position(&user, 0).await.open_tickets, 1);
+    assert_eq!(
+        custom_error(env.close_user_info(&alice, 0).await),
+        u32::from(BrewStakingError::OpenWithdrawalTickets)
+    );
+
+    // With nothing staked the exit only closes the position
+    env.warp_to(1_220);
+    let ticket = env.ticket_address(&user, 0, 0);
+    env.withdraw_ticket(&alice, &ticket, 0).await.unwrap();
+    env.exit(&alice, 0, 0, true).await.unwrap();
+    assert!(env.account(&address).await.is_none());
+    assert_eq!(env.stake_balance(&user).await, 1_000_000);
+
+    // A new position numbers its tickets from 0 again
+    env.stake(&alice, 1_000_000).await.unwrap();
+    env.unstake(&alice, 1_000_000).await.unwrap();
+    assert_eq!(env.position(&user, 0).await.open_tickets, 1);
+    assert!(env.account(&ticket).await.is_some());
+}
+
+#[tokio::test]
+async fn pools_with_locked_boost_nfts_stay_open() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let collection = Pubkey::new_unique();
+    let nft = env.create_boost_nft(&alice.pubkey(), &collection, true).await;
+    env.set_boost_collection(Some(collection), 20_000).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 0).await.unwrap();
+    env.lock_boost_nft(&alice, &nft).await.unwrap();
+    assert_eq!(env.pool_state().await.open_boosts, 1);
+
+    // The escrow could never be unlocked once the pool is gone
+    env.warp_to(111 + SLOTS_PER_DAY);
+    assert_eq!(custom_error(env.close_pool().await), u32::from(BrewStakingError::PoolNotEmpty));
+
+    env.unlock_boost_nft(&alice, &nft).await.unwrap();
+    assert_eq!(env.pool_state().await.open_boosts, 0);
+    env.close_pool().await.unwrap();
+}
+
+#[tokio::test]
+async fn finished_pools_return_their_reward_and_rent() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let pool = env.pool;
+    let config = env.pool_config().await;
+    let creator = env.creator.pubkey();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(110 + SLOTS_PER_DAY);
+    assert_eq!(custom_error(env.close_pool().await), u32::from(BrewStakingError::PoolNotEnded));
+
+    // Nobody staked, so the whole funding is left
+    env.warp_to(111 + SLOTS_PER_DAY);
+    let rent = env.lamports(&pool).await + env.lamports(&config.state_addr).await;
+    let balance = env.lamports(&creator).await;
+    env.close_pool().await.unwrap();
+    assert_eq!(env.reward_balance(&creator).await, 300_000_000);
+    for address in [pool, config.state_addr] {
+        assert!(env.account(&address).await.is_none());
+    }
+    assert_eq!(env.lamports(&creator).await, balance + rent);
+
+    // Other pools of the same mints share the vaults, so they stay open
+    for address in [config.pool_stake_token_vault, config.pool_reward_token_vault] {
+        assert!(env.account(&address).await.is_some());
+    }
+}
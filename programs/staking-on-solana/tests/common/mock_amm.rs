@@ -0,0 +1,133 @@
+//! A stand-in for the Raydium AMM that `compound_reward` swaps through.
+//!
+//! It understands only `swap_base_in` (tag 9) and fills it at a fixed rate
+//! stored in the `amm` account as two little-endian `u64`s (numerator,
+//! denominator): `amount_in` moves from the user's source account into the
+//! coin vault and `amount_in * numerator / denominator` moves from the pc
+//! vault to the user's destination account.
+
+use solana_program_test::{ processor, ProgramTest };
+use solana_sdk::{
+    account::Account,
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program::{ invoke, invoke_signed },
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+pub const ID: Pubkey = Pubkey::new_from_array([0x4d; 32]);
+
+/// Stake tokens the pc vault starts with
+pub const LIQUIDITY: u64 = 1_000_000_000_000;
+
+const SWAP_BASE_IN: u8 = 9;
+const AUTHORITY_SEED: &[u8] = b"amm authority";
+
+// Account positions in Raydium's `swap_base_in` instruction
+const TOKEN_PROGRAM: usize = 0;
+const AMM: usize = 1;
+const AMM_AUTHORITY: usize = 2;
+const POOL_COIN: usize = 5;
+const POOL_PC: usize = 6;
+const USER_SOURCE: usize = 15;
+const USER_DESTINATION: usize = 16;
+const USER_SOURCE_OWNER: usize = 17;
+
+pub struct MockAmm {
+    pub amm: Pubkey,
+    pub authority: Pubkey,
+    pub coin_vault: Pubkey,
+    pub pc_vault: Pubkey,
+    /// Open orders, target orders and serum accounts the mock ignores
+    pub unused: [Pubkey; 10],
+}
+
+impl MockAmm {
+    /// Registers the program and an `amm` account quoting `numerator / denominator`.
+    pub fn new(program_test: &mut ProgramTest, numerator: u64, denominator: u64) -> Self {
+        program_test.add_program("mock_amm", ID, processor!(process_instruction));
+
+        let amm = Pubkey::new_unique();
+        let mut data = numerator.to_le_bytes().to_vec();
+        data.extend_from_slice(&denominator.to_le_bytes());
+        program_test.add_account(amm, Account {
+            lamports: 1_000_000_000,
+            data,
+            owner: ID,
+            executable: false,
+            rent_epoch: 0,
+        });
+
+        Self {
+            amm,
+            authority: Pubkey::find_program_address(&[AUTHORITY_SEED], &ID).0,
+            coin_vault: Pubkey::default(),
+            pc_vault: Pubkey::default(),
+            unused: std::array::from_fn(|_| Pubkey::new_unique()),
+        }
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    data.get(offset..offset + 8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(ProgramError::InvalidInstructionData)
+}
+
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if data.first() != Some(&SWAP_BASE_IN) || accounts.len() <= USER_SOURCE_OWNER {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let amount_in = read_u64(data, 1)?;
+    let minimum_amount_out = read_u64(data, 9)?;
+
+    let (numerator, denominator) = {
+        let rate = accounts[AMM].try_borrow_data()?;
+        (read_u64(&rate, 0)?, read_u64(&rate, 8)?)
+    };
+    let amount_out = (amount_in * numerator) / denominator;
+    if amount_out < minimum_amount_out {
+        return Err(ProgramError::Custom(30));
+    }
+
+    let token_program = &accounts[TOKEN_PROGRAM];
+    invoke(
+        &spl_token::instruction::transfer(
+            token_program.key,
+            accounts[USER_SOURCE].key,
+            accounts[POOL_COIN].key,
+            accounts[USER_SOURCE_OWNER].key,
+            &[],
+            amount_in
+        )?,
+        &[
+            accounts[USER_SOURCE].clone(),
+            accounts[POOL_COIN].clone(),
+            accounts[USER_SOURCE_OWNER].clone(),
+            token_program.clone(),
+        ]
+    )?;
+
+    let (authority, bump) = Pubkey::find_program_address(&[AUTHORITY_SEED], program_id);
+    if *accounts[AMM_AUTHORITY].key != authority {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    invoke_signed(
+        &spl_token::instruction::transfer(
+            token_program.key,
+            accounts[POOL_PC].key,
+            accounts[USER_DESTINATION].key,
+            &authority,
+            &[],
+            amount_out
+        )?,
+        &[
+            accounts[POOL_PC].clone(),
+            accounts[USER_DESTINATION].clone(),
+            accounts[AMM_AUTHORITY].clone(),
+            token_program.clone(),
+        ],
+        &[&[AUTHORITY_SEED, &[bump]]]
+    )
+}
@@ -0,0 +1,1518 @@
+//! Shared harness for the `solana-program-test` suites.
+//!
+//! The staking program and a Raydium stand-in (see `mock_amm`) run natively
+//! inside a `ProgramTestContext`; SPL Token, Token-2022 and the associated
+//! token account program are the BPF builds bundled with `solana-program-test`.
+
+#![allow(dead_code)]
+
+pub mod mock_amm;
+
+use std::sync::Mutex;
+
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::{ AccountDeserialize, AnchorDeserialize, Discriminator, InstructionData, ToAccountMetas };
+use solana_program_test::{ processor, BanksClientError, ProgramTest, ProgramTestContext };
+use solana_sdk::{
+    account::Account,
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{ AccountMeta, Instruction, InstructionError },
+    program::MAX_RETURN_DATA,
+    pubkey::Pubkey,
+    signature::{ Keypair, Signer },
+    system_instruction,
+    system_program,
+    transaction::{ Transaction, TransactionError },
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use spl_token_2022::extension::{ transfer_fee, transfer_hook, ExtensionType, StateWithExtensions };
+use staking_on_solana::state::{
+    position_seed,
+    token_metadata,
+    AllowlistProof,
+    PerformanceFeeModel,
+    PoolConfig,
+    PoolState,
+    ReferralSource,
+    UserInfo,
+    FEE_EXEMPTION_SEED,
+    HOOK_MINT_ALLOWANCE_SEED,
+    BOOST_ESCROW_SEED,
+    REFERRER_SEED,
+    WITHDRAWAL_TICKET_SEED,
+    WSOL_SEED,
+};
+use staking_on_solana::utils::metadata::metadata_address;
+use staking_on_solana::utils::token::is_native_mint;
+use staking_on_solana::utils::{ PoolInfo, UserPosition };
+use staking_on_solana::{ accounts, instruction, ID as PROGRAM_ID };
+
+pub const DEPLOY_FEE: u64 = 100_000_000;
+pub const PERFORMANCE_FEE: u64 = 5_000;
+/// Treasury cut of rewards on `PerformanceFeeModel::RewardShare` pools
+pub const REWARD_PERFORMANCE_FEE: u16 = 1_000;
+
+// The instruction data of every `emit_cpi!` self-invocation, failed
+// transactions included. The tests of a suite share it, so `TestEnv::events`
+// picks out its own pool's by the `pool_config` every event starts with.
+static EVENTS: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+// Anchor's generated entrypoint ties the account slice to the account
+// lifetime, which `processor!` can't express; leaking a copy of the slice is
+// fine for the lifetime of a test process.
+fn process_staking(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if data.starts_with(&EVENT_IX_TAG_LE) {
+        EVENTS.lock().unwrap().push(data.to_vec());
+    }
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    staking_on_solana::entry(program_id, accounts, data)
+}
+
+pub struct PoolArgs {
+    pub pool_id: &'static str,
+    pub stake_fee: u16,
+    pub unstake_fee: u16,
+    pub initial_funding: u64,
+    pub reward_per_slot: u64,
+    pub duration: u16,
+    pub same_mint: bool,
+    /// `spl_token::ID` or `spl_token_2022::ID`, for both mints
+    pub token_program: Pubkey,
+    /// Token-2022 transfer fee of both mints, in basis points
+    pub transfer_fee_bps: u16,
+    /// Stake native SOL; with `same_mint` rewards are paid in SOL too. Users
+    /// then have no token accounts for the native mint
+    pub native_sol: bool,
+    /// Platform share of the stake and unstake fees, in basis points
+    pub treasury_fee_share: u16,
+    pub performance_fee_model: PerformanceFeeModel,
+}
+
+impl Default for PoolArgs {
+    fn default() -> Self {
+        Self {
+            pool_id: "0",
+            stake_fee: 0,
+            unstake_fee: 0,
+            initial_funding: 300_000_000,
+            reward_per_slot: 1_000,
+            duration: 1,
+            same_mint: false,
+            token_program: spl_token::ID,
+            transfer_fee_bps: 0,
+            native_sol: false,
+            treasury_fee_share: 0,
+            performance_fee_model: PerformanceFeeModel::Flat,
+        }
+    }
+}
+
+/// A platform with one pool. As in the TypeScript suite the fee payer is also
+/// `admin`, the owner of the pool vaults that co-signs every user instruction.
+pub struct TestEnv {
+    pub ctx: ProgramTestContext,
+    pub admin: Keypair,
+    pub treasury: Keypair,
+    pub creator: Keypair,
+    pub stake_mint: Pubkey,
+    pub reward_mint: Pubkey,
+    pub token_program: Pubkey,
+    pub native_sol: bool,
+    pub pool: Pubkey,
+    pub amm: mock_amm::MockAmm,
+}
+
+impl TestEnv {
+    /// Initializes the platform and creates a pool with 6-decimal mints.
+    pub async fn new(args: PoolArgs) -> Self {
+        let mut program_test = ProgramTest::new(
+            "staking_on_solana",
+            PROGRAM_ID,
+            processor!(process_staking)
+        );
+        let amm = mock_amm::MockAmm::new(&mut program_test, 2, 1);
+        let ctx = program_test.start_with_context().await;
+
+        let mut env = Self {
+            admin: ctx.payer.insecure_clone(),
+            ctx,
+            treasury: Keypair::new(),
+            creator: Keypair::new(),
+            stake_mint: Pubkey::default(),
+            reward_mint: Pubkey::default(),
+            token_program: args.token_program,
+            native_sol: args.native_sol,
+            pool: Pubkey::default(),
+            amm,
+        };
+
+        for wallet in [env.treasury.pubkey(), env.creator.pubkey()] {
+            env.airdrop(&wallet, 10_000_000_000).await;
+        }
+
+        let extensions = if args.transfer_fee_bps > 0 {
+            vec![MintExtension::TransferFee(args.transfer_fee_bps)]
+        } else {
+            vec![]
+        };
+        env.stake_mint = if args.native_sol {
+            spl_token::native_mint::ID
+        } else {
+            env.create_mint_with(6, &extensions).await
+        };
+        env.reward_mint = if args.same_mint {
+            env.stake_mint
+        } else {
+            env.create_mint_with(6, &extensions).await
+        };
+
+        let admin = env.admin.pubkey();
+        let creator = env.creator.pubkey();
+        let (stake_mint, reward_mint) = (env.stake_mint, env.reward_mint);
+        env.create_ata(&admin, &stake_mint).await;
+        env.create_ata(&admin, &reward_mint).await;
+        env.create_ata(&creator, &stake_mint).await;
+        env.create_ata(&env.treasury.pubkey(), &stake_mint).await;
+        env.create_ata(&env.treasury.pubkey(), &reward_mint).await;
+        let creator_reward = env.create_ata(&creator, &reward_mint).await;
+        env.mint_to(&reward_mint, &creator_reward, args.initial_funding).await;
+
+        // The mock AMM takes reward tokens in and pays stake tokens out
+        let amm_authority = env.amm.authority;
+        env.amm.coin_vault = env.create_ata(&amm_authority, &reward_mint).await;
+        env.amm.pc_vault = env.create_ata(&amm_authority, &stake_mint).await;
+        let pc_vault = env.amm.pc_vault;
+        env.mint_to(&stake_mint, &pc_vault, mock_amm::LIQUIDITY).await;
+
+        let initialize = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::Initialize {
+                platform: env.platform(),
+                admin,
+                treasury: env.treasury.pubkey(),
+                system_program: system_program::ID,
+                token_program: env.token_program,
+            }).to_account_metas(None),
+            data: (instruction::Initialize {
+                deploy_fee: DEPLOY_FEE,
+                performance_fee: PERFORMANCE_FEE,
+                treasury_fee_share: args.treasury_fee_share,
+                reward_performance_fee: REWARD_PERFORMANCE_FEE,
+            }).data(),
+        };
+        let admin_kp = env.admin.insecure_clone();
+        let treasury_kp = env.treasury.insecure_clone();
+        env.send(&[initialize], &[&admin_kp, &treasury_kp]).await.unwrap();
+
+        env.pool = env.create_pool(&args, &stake_mint, &reward_mint).await.unwrap();
+
+        env
+    }
+
+    /// Creates a pool of `stake_mint` and `reward_mint` owned by `creator`,
+    /// passing the hook allowances of mints that have one.
+    pub async fn create_pool(
+        &mut self,
+        args: &PoolArgs,
+        stake_mint: &Pubkey,
+        reward_mint: &Pubkey
+    ) -> Result<Pubkey, BanksClientError> {
+        let admin = self.admin.pubkey();
+        let creator = self.creator.pubkey();
+        let pool = Pubkey::find_program_address(
+            &[args.pool_id.as_bytes(), creator.as_ref()],
+            &PROGRAM_ID
+        ).0;
+        let stake_hook_allowance = self.hook_allowance_if_any(stake_mint).await;
+        let reward_hook_allowance = self.hook_allowance_if_any(reward_mint).await;
+        let pool_state = Keypair::new();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::CreatePool {
+                pool_config_account: pool,
+                pool_state_account: pool_state.pubkey(),
+                platform: self.platform(),
+                creator,
+                treasury: self.treasury.pubkey(),
+                stake_mint: *stake_mint,
+                reward_mint: *reward_mint,
+                pool_stake_token_vault: self.ata(&admin, stake_mint),
+                pool_reward_token_vault: self.ata(&admin, reward_mint),
+                creator_reward_token_vault: self.ata(&creator, reward_mint),
+                stake_hook_allowance,
+                reward_hook_allowance,
+                system_program: system_program::ID,
+                token_program: self.token_program,
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::CreatePool {
+                pool_id: args.pool_id.to_string(),
+                stake_fee: args.stake_fee,
+                unstake_fee: args.unstake_fee,
+                initial_funding: args.initial_funding,
+                reward_per_slot: args.reward_per_slot,
+                duration: args.duration,
+                performance_fee_model: args.performance_fee_model,
+            }).data(),
+        };
+        let creator_kp = self.creator.insecure_clone();
+        let treasury_kp = self.treasury.insecure_clone();
+        self.send(&[ix], &[&creator_kp, &treasury_kp, &pool_state]).await?;
+        Ok(pool)
+    }
+
+    pub fn hook_allowance_address(&self, mint: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[HOOK_MINT_ALLOWANCE_SEED, self.platform().as_ref(), mint.as_ref()],
+            &PROGRAM_ID
+        ).0
+    }
+
+    async fn hook_allowance_if_any(&mut self, mint: &Pubkey) -> Option<Pubkey> {
+        let address = self.hook_allowance_address(mint);
+        self.account(&address).await.map(|_| address)
+    }
+
+    pub async fn allow_hook_mint(&mut self, mint: &Pubkey, allowed: bool) -> Result<(), BanksClientError> {
+        let treasury = self.treasury.insecure_clone();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::AllowHookMint {
+                allowance: self.hook_allowance_address(mint),
+                platform: self.platform(),
+                treasury: treasury.pubkey(),
+                mint: *mint,
+                system_program: system_program::ID,
+            }).to_account_metas(None),
+            data: (instruction::AllowHookMint { allowed }).data(),
+        };
+        self.send(&[ix], &[&treasury]).await
+    }
+
+    pub fn fee_exemption_address(&self, scope: &Pubkey, wallet: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[FEE_EXEMPTION_SEED, scope.as_ref(), wallet.as_ref()],
+            &PROGRAM_ID
+        ).0
+    }
+
+    /// The pool and platform fee exemptions of `wallet` that exist.
+    async fn fee_exemptions(&mut self, wallet: &Pubkey) -> (Option<Pubkey>, Option<Pubkey>) {
+        let pool = self.fee_exemption_address(&self.pool, wallet);
+        let platform = self.fee_exemption_address(&self.platform(), wallet);
+        (
+            self.account(&pool).await.map(|_| pool),
+            self.account(&platform).await.map(|_| platform),
+        )
+    }
+
+    /// Sets the pool's minimum stake and caps, signed by the creator.
+    pub async fn set_stake_limits(
+        &mut self,
+        min_stake: u64,
+        max_stake_per_wallet: u64,
+        max_total_staked: u64
+    ) -> Result<(), BanksClientError> {
+        let creator = self.creator.insecure_clone();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::SetStakeLimits {
+                pool_config_account: self.pool,
+                owner: creator.pubkey(),
+            }).to_account_metas(None),
+            data: (instruction::SetStakeLimits { min_stake, max_stake_per_wallet, max_total_staked }).data(),
+        };
+        self.send(&[ix], &[&creator]).await
+    }
+
+    /// Sets or clears the pool's allowlist root, signed by the creator.
+    pub async fn set_allowlist(&mut self, allowlist_root: Option<[u8; 32]>) -> Result<(), BanksClientError> {
+        let creator = self.creator.insecure_clone();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::SetAllowlist {
+                pool_config_account: self.pool,
+                owner: creator.pubkey(),
+            }).to_account_metas(None),
+            data: (instruction::SetAllowlist { allowlist_root }).data(),
+        };
+        self.send(&[ix], &[&creator]).await
+    }
+
+    /// Sets the collection whose NFTs boost stakes, signed by the creator.
+    pub async fn set_boost_collection(
+        &mut self,
+        boost_collection: Option<Pubkey>,
+        boost_multiplier: u16
+    ) -> Result<(), BanksClientError> {
+        let creator = self.creator.insecure_clone();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::SetBoostCollection {
+                pool_config_account: self.pool,
+                owner: creator.pubkey(),
+            }).to_account_metas(None),
+            data: (instruction::SetBoostCollection { boost_collection, boost_multiplier }).data(),
+        };
+        self.send(&[ix], &[&creator]).await
+    }
+
+    /// Mints an NFT to `owner` with a Metaplex metadata account naming
+    /// `collection`, verified or not.
+    pub async fn create_boost_nft(&mut self, owner: &Pubkey, collection: &Pubkey, verified: bool) -> Pubkey {
+        let mint = self.create_mint(0).await;
+        let account = self.create_ata(owner, &mint).await;
+        self.mint_to(&mint, &account, 1).await;
+
+        // Borsh layout of the metadata fields the program reads
+        let mut data = vec![4];
+        data.extend_from_slice(self.ctx.payer.pubkey().as_ref());
+        data.extend_from_slice(mint.as_ref());
+        for field in ["Boost", "BOOST", "https://example.com/boost.json"] {
+            data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            data.extend_from_slice(field.as_bytes());
+        }
+        data.extend_from_slice(&500u16.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 1, 0, 1, 0, 1, verified as u8]);
+        data.extend_from_slice(collection.as_ref());
+        data.resize(679, 0);
+
+        // The metadata program isn't loaded, so the account is written
+        // directly. Its rent comes out of the payer to keep the bank's
+        // capitalization in balance
+        let rent = self.ctx.banks_client.get_rent().await.unwrap();
+        let lamports = rent.minimum_balance(data.len());
+        let payer = self.ctx.payer.pubkey();
+        let mut payer_account = self.account(&payer).await.unwrap();
+        payer_account.lamports -= lamports;
+        self.ctx.set_account(&payer, &payer_account.into());
+        let metadata = Account { lamports, data, owner: token_metadata::ID, executable: false, rent_epoch: 0 };
+        self.ctx.set_account(&metadata_address(&mint), &metadata.into());
+        mint
+    }
+
+    pub fn boost_escrow_address(&self, user: &Pubkey) -> Pubkey {
+        let user_info = self.user_info_address(user);
+        Pubkey::find_program_address(&[BOOST_ESCROW_SEED, user_info.as_ref()], &PROGRAM_ID).0
+    }
+
+    pub async fn lock_boost_nft(&mut self, user: &Keypair, nft_mint: &Pubkey) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let owner = user.pubkey();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::LockBoostNft {
+                user: owner,
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                user_info: self.user_info_address(&owner),
+                nft_mint: *nft_mint,
+                nft_metadata: metadata_address(nft_mint),
+                user_nft_account: self.ata(&owner, nft_mint),
+                boost_escrow: self.boost_escrow_address(&owner),
+                nft_token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::LockBoostNft { position: 0 }).data(),
+        };
+        self.send(&[ix], &[user]).await
+    }
+
+    pub async fn unlock_boost_nft(&mut self, user: &Keypair, nft_mint: &Pubkey) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let owner = user.pubkey();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::UnlockBoostNft {
+                user: owner,
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                user_info: self.user_info_address(&owner),
+                nft_mint: *nft_mint,
+                user_nft_account: self.ata(&owner, nft_mint),
+                boost_escrow: self.boost_escrow_address(&owner),
+                nft_token_program: self.token_program,
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::UnlockBoostNft { position: 0 }).data(),
+        };
+        self.send(&[ix], &[user]).await
+    }
+
+    pub async fn set_lock_multiplier(&mut self, lock_multiplier: u16) -> Result<(), BanksClientError> {
+        let creator = self.creator.insecure_clone();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::SetLockMultiplier {
+                pool_config_account: self.pool,
+                owner: creator.pubkey(),
+            }).to_account_metas(None),
+            data: (instruction::SetLockMultiplier { lock_multiplier }).data(),
+        };
+        self.send(&[ix], &[&creator]).await
+    }
+
+    pub async fn extend_lock(&mut self, user: &Keypair, lock_weeks: u16) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let owner = user.pubkey();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::ExtendLock {
+                user: owner,
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                user_info: self.user_info_address(&owner),
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::ExtendLock { lock_weeks, position: 0 }).data(),
+        };
+        self.send(&[ix], &[user]).await
+    }
+
+    /// Expires `user`'s ended lock, paid for by the test payer.
+    pub async fn expire_lock(&mut self, user: &Pubkey) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::ExpireLock {
+                user: *user,
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                user_info: self.user_info_address(user),
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::ExpireLock { position: 0 }).data(),
+        };
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn update_pool(&mut self) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::UpdatePool {
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::UpdatePool {}).data(),
+        };
+        self.send(&[ix], &[]).await
+    }
+
+    /// Updates the given (config, state) pairs in one instruction.
+    pub async fn update_pools(&mut self, pools: &[(Pubkey, Pubkey)]) -> Result<(), BanksClientError> {
+        let mut accounts = (accounts::UpdatePools {
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None);
+        for (pool, state) in pools {
+            accounts.push(AccountMeta::new_readonly(*pool, false));
+            accounts.push(AccountMeta::new(*state, false));
+        }
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts,
+            data: (instruction::UpdatePools {}).data(),
+        };
+        self.send(&[ix], &[]).await
+    }
+
+    pub async fn set_unbonding_period(&mut self, unbonding_slots: u64) -> Result<(), BanksClientError> {
+        let creator = self.creator.insecure_clone();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::SetUnbondingPeriod {
+                pool_config_account: self.pool,
+                owner: creator.pubkey(),
+            }).to_account_metas(None),
+            data: (instruction::SetUnbondingPeriod { unbonding_slots }).data(),
+        };
+        self.send(&[ix], &[&creator]).await
+    }
+
+    pub async fn set_vesting(&mut self, vesting_slots: u64, forfeit_unvested: bool) -> Result<(), BanksClientError> {
+        let creator = self.creator.insecure_clone();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::SetVesting {
+                pool_config_account: self.pool,
+                owner: creator.pubkey(),
+            }).to_account_metas(None),
+            data: (instruction::SetVesting { vesting_slots, forfeit_unvested }).data(),
+        };
+        self.send(&[ix], &[&creator]).await
+    }
+
+    pub async fn set_referral(
+        &mut self,
+        referral_share: u16,
+        referral_source: ReferralSource
+    ) -> Result<(), BanksClientError> {
+        let creator = self.creator.insecure_clone();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::SetReferral {
+                pool_config_account: self.pool,
+                owner: creator.pubkey(),
+            }).to_account_metas(None),
+            data: (instruction::SetReferral { referral_share, referral_source }).data(),
+        };
+        self.send(&[ix], &[&creator]).await
+    }
+
+    pub fn referrer_address(&self, referrer: &Pubkey) -> Pubkey {
+        let seeds = [REFERRER_SEED, self.pool.as_ref(), referrer.as_ref()];
+        Pubkey::find_program_address(&seeds, &PROGRAM_ID).0
+    }
+
+    /// The position's `UserInfo`, if it exists yet.
+    async fn existing_position(&mut self, user: &Pubkey, position: u32) -> Option<UserInfo> {
+        let account = self.account(&self.position_address(user, position)).await?;
+        UserInfo::try_deserialize(&mut account.data.as_slice()).ok()
+    }
+
+    async fn position_referrer(&mut self, user: &Pubkey, position: u32) -> Option<Pubkey> {
+        self.existing_position(user, position).await?.referrer
+    }
+
+    /// The account the position's rewards are paid to: its reward recipient's
+    /// when it has one.
+    async fn reward_account(&mut self, user: &Pubkey, position: u32) -> Option<Pubkey> {
+        let reward_mint = self.reward_mint;
+        match self.existing_position(user, position).await.and_then(|info| info.reward_recipient) {
+            Some(recipient) => Some(self.ata(&recipient, &reward_mint)),
+            None => self.user_token_account(user, &reward_mint),
+        }
+    }
+
+    pub async fn set_reward_recipient(
+        &mut self,
+        user: &Keypair,
+        position: u32,
+        recipient: Option<Pubkey>
+    ) -> Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::SetRewardRecipient {
+                user: user.pubkey(),
+                pool_config_account: self.pool,
+                user_info: self.position_address(&user.pubkey(), position),
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::SetRewardRecipient { position, recipient }).data(),
+        };
+        self.send(&[ix], &[user]).await
+    }
+
+    /// The earnings account of the position's referrer, if it has one.
+    async fn referrer_info(&mut self, user: &Pubkey, position: u32) -> Option<Pubkey> {
+        let referrer = self.position_referrer(user, position).await?;
+        Some(self.referrer_address(&referrer))
+    }
+
+    pub async fn claim_referral(&mut self, referrer: &Keypair) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let owner = referrer.pubkey();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::ClaimReferral {
+                referrer: owner,
+                admin: self.admin.pubkey(),
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                referrer_info: self.referrer_address(&owner),
+                referrer_stake_token_vault: self.ata(&owner, &config.stake_mint),
+                referrer_reward_token_vault: self.ata(&owner, &config.reward_mint),
+                pool_stake_token_vault: config.pool_stake_token_vault,
+                pool_reward_token_vault: config.pool_reward_token_vault,
+                stake_mint: config.stake_mint,
+                reward_mint: config.reward_mint,
+                token_program: self.token_program,
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::ClaimReferral {}).data(),
+        };
+        let admin = self.admin.insecure_clone();
+        self.send(&[ix], &[referrer, &admin]).await
+    }
+
+    pub fn ticket_address(&self, user: &Pubkey, position: u32, index: u64) -> Pubkey {
+        let user_info = self.position_address(user, position);
+        let seeds = [WITHDRAWAL_TICKET_SEED, user_info.as_ref(), &index.to_le_bytes()];
+        Pubkey::find_program_address(&seeds, &PROGRAM_ID).0
+    }
+
+    pub async fn close_user_info(&mut self, user: &Keypair, position: u32) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::CloseUserInfo {
+                user: user.pubkey(),
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                user_info: self.position_address(&user.pubkey(), position),
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::CloseUserInfo { position }).data(),
+        };
+        self.send(&[ix], &[user]).await
+    }
+
+    /// Closes the pool, signed by the creator and the vault owner.
+    pub async fn close_pool(&mut self) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let creator = self.creator.insecure_clone();
+        let admin = self.admin.insecure_clone();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::ClosePool {
+                owner: creator.pubkey(),
+                admin: admin.pubkey(),
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                pool_reward_token_vault: config.pool_reward_token_vault,
+                owner_reward_token_vault: self.ata(&creator.pubkey(), &config.reward_mint),
+                reward_mint: config.reward_mint,
+                token_program: self.token_program,
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::ClosePool {}).data(),
+        };
+        self.send(&[ix], &[&creator, &admin]).await
+    }
+
+    pub async fn withdraw_ticket(
+        &mut self,
+        user: &Keypair,
+        ticket: &Pubkey,
+        position: u32
+    ) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let owner = user.pubkey();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::WithdrawTicket {
+                user: owner,
+                admin: self.admin.pubkey(),
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                withdrawal_ticket: *ticket,
+                user_info: self.position_address(&owner, position),
+                user_stake_token_vault: self.user_token_account(&owner, &config.stake_mint),
+                pool_stake_token_vault: config.pool_stake_token_vault,
+                stake_mint: config.stake_mint,
+                wsol_account: self.wsol_account(&owner),
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::WithdrawTicket {}).data(),
+        };
+        let admin = self.admin.insecure_clone();
+        self.send(&[ix], &[user, &admin]).await
+    }
+
+    pub async fn cancel_ticket(
+        &mut self,
+        user: &Keypair,
+        ticket: &Pubkey,
+        position: u32
+    ) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let owner = user.pubkey();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::CancelTicket {
+                user: owner,
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                withdrawal_ticket: *ticket,
+                user_info: self.position_address(&owner, position),
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::CancelTicket {}).data(),
+        };
+        self.send(&[ix], &[user]).await
+    }
+
+    /// Grants `wallet` a discount on the pool's fees, signed by the creator.
+    pub async fn set_pool_fee_exemption(
+        &mut self,
+        wallet: &Pubkey,
+        discount: u16
+    ) -> Result<(), BanksClientError> {
+        let creator = self.creator.insecure_clone();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::SetPoolFeeExemption {
+                fee_exemption: self.fee_exemption_address(&self.pool, wallet),
+                pool_config_account: self.pool,
+                owner: creator.pubkey(),
+                system_program: system_program::ID,
+            }).to_account_metas(None),
+            data: (instruction::SetPoolFeeExemption { wallet: *wallet, discount }).data(),
+        };
+        self.send(&[ix], &[&creator]).await
+    }
+
+    /// Grants `wallet` a discount on every fee, signed by the treasury.
+    pub async fn set_platform_fee_exemption(
+        &mut self,
+        wallet: &Pubkey,
+        discount: u16
+    ) -> Result<(), BanksClientError> {
+        let treasury = self.treasury.insecure_clone();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::SetPlatformFeeExemption {
+                fee_exemption: self.fee_exemption_address(&self.platform(), wallet),
+                platform: self.platform(),
+                treasury: treasury.pubkey(),
+                system_program: system_program::ID,
+            }).to_account_metas(None),
+            data: (instruction::SetPlatformFeeExemption { wallet: *wallet, discount }).data(),
+        };
+        self.send(&[ix], &[&treasury]).await
+    }
+
+    /// The token account a user instruction is given for `mint`: none for
+    /// native SOL in a `native_sol` pool.
+    pub fn user_token_account(&self, owner: &Pubkey, mint: &Pubkey) -> Option<Pubkey> {
+        (!(self.native_sol && is_native_mint(mint))).then(|| self.ata(owner, mint))
+    }
+
+    /// The wSOL account native SOL payouts go through, in a `native_sol` pool.
+    pub fn wsol_account(&self, user: &Pubkey) -> Option<Pubkey> {
+        let address = Pubkey::find_program_address(
+            &[WSOL_SEED, self.pool.as_ref(), user.as_ref()],
+            &PROGRAM_ID
+        ).0;
+        self.native_sol.then_some(address)
+    }
+
+    /// The treasury's reward token account, passed to reward-share pools only.
+    pub fn treasury_reward_account(&self, config: &PoolConfig) -> Option<Pubkey> {
+        let address = self.ata(&self.treasury.pubkey(), &config.reward_mint);
+        (config.performance_fee_model == PerformanceFeeModel::RewardShare).then_some(address)
+    }
+
+    /// The associated token account of `owner` under the pool's token program.
+    pub fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+
+    pub fn platform(&self) -> Pubkey {
+        Pubkey::find_program_address(&[self.treasury.pubkey().as_ref()], &PROGRAM_ID).0
+    }
+
+    pub fn user_info_address(&self, user: &Pubkey) -> Pubkey {
+        self.position_address(user, 0)
+    }
+
+    pub fn position_address(&self, user: &Pubkey, position: u32) -> Pubkey {
+        let seeds = [self.pool.as_ref(), user.as_ref(), &position_seed(position)];
+        Pubkey::find_program_address(&seeds, &PROGRAM_ID).0
+    }
+
+    pub async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair]
+    ) -> Result<(), BanksClientError> {
+        let blockhash = self.ctx.banks_client.get_latest_blockhash().await?;
+        let mut all_signers = vec![&self.ctx.payer];
+        for signer in signers {
+            if !all_signers.iter().any(|existing| existing.pubkey() == signer.pubkey()) {
+                all_signers.push(signer);
+            }
+        }
+        let tx = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.ctx.payer.pubkey()),
+            &all_signers,
+            blockhash
+        );
+        self.ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// Sends `instructions` signed by `payer` alone, without the admin.
+    pub async fn send_paid_by(
+        &mut self,
+        instructions: &[Instruction],
+        payer: &Keypair
+    ) -> Result<(), BanksClientError> {
+        let blockhash = self.ctx.banks_client.get_latest_blockhash().await?;
+        let tx = Transaction::new_signed_with_payer(instructions, Some(&payer.pubkey()), &[payer], blockhash);
+        self.ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// The events of type `T` the pool has emitted so far, oldest first.
+    pub fn events<T: Discriminator + AnchorDeserialize>(&self) -> Vec<T> {
+        EVENTS.lock()
+            .unwrap()
+            .iter()
+            .filter_map(|data| data.strip_prefix(&EVENT_IX_TAG_LE)?.strip_prefix(&T::DISCRIMINATOR))
+            .filter(|event| event.starts_with(self.pool.as_ref()))
+            .map(|mut event| T::deserialize(&mut event).unwrap())
+            .collect()
+    }
+
+    /// Simulates a view instruction and decodes what it returns. Return data
+    /// comes back without its trailing zero bytes, so it's padded again first.
+    pub async fn view<T: AnchorDeserialize>(&mut self, ix: Instruction) -> Result<T, BanksClientError> {
+        let blockhash = self.ctx.banks_client.get_latest_blockhash().await?;
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&self.ctx.payer.pubkey()),
+            &[&self.ctx.payer],
+            blockhash
+        );
+        let simulation = self.ctx.banks_client.simulate_transaction(tx).await?;
+        if let Some(Err(err)) = simulation.result {
+            return Err(BanksClientError::TransactionError(err));
+        }
+        let mut data = simulation.simulation_details
+            .and_then(|details| details.return_data)
+            .map(|return_data| return_data.data)
+            .unwrap_or_default();
+        data.resize(MAX_RETURN_DATA, 0);
+        Ok(T::deserialize(&mut data.as_slice()).unwrap())
+    }
+
+    pub async fn pool_info(&mut self) -> Result<PoolInfo, BanksClientError> {
+        let config = self.pool_config().await;
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::GetPoolInfo {
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+            }).to_account_metas(None),
+            data: (instruction::GetPoolInfo {}).data(),
+        };
+        self.view(ix).await
+    }
+
+    pub async fn user_position(&mut self, user: &Pubkey, position: u32) -> Result<UserPosition, BanksClientError> {
+        let config = self.pool_config().await;
+        let (pool_fee_exemption, platform_fee_exemption) = self.fee_exemptions(user).await;
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::GetUserPosition {
+                user: *user,
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                platform: self.platform(),
+                user_info: self.position_address(user, position),
+                pool_fee_exemption,
+                platform_fee_exemption,
+            }).to_account_metas(None),
+            data: (instruction::GetUserPosition { position }).data(),
+        };
+        self.view(ix).await
+    }
+
+    pub fn warp_to(&mut self, slot: u64) {
+        self.ctx.warp_to_slot(slot).unwrap();
+    }
+
+    pub async fn airdrop(&mut self, to: &Pubkey, lamports: u64) {
+        let ix = system_instruction::transfer(&self.ctx.payer.pubkey(), to, lamports);
+        self.send(&[ix], &[]).await.unwrap();
+    }
+
+    pub async fn create_mint(&mut self, decimals: u8) -> Pubkey {
+        self.create_mint_with(decimals, &[]).await
+    }
+
+    /// A mint of the pool's token program; extensions need Token-2022.
+    pub async fn create_mint_with(&mut self, decimals: u8, extensions: &[MintExtension]) -> Pubkey {
+        let mint = Keypair::new();
+        let payer = self.ctx.payer.pubkey();
+        let token_program = self.token_program;
+        let rent = self.ctx.banks_client.get_rent().await.unwrap();
+        let extension_types: Vec<_> = extensions.iter().map(MintExtension::extension_type).collect();
+        let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(
+            &extension_types
+        ).unwrap();
+        let mut instructions = vec![
+            system_instruction::create_account(
+                &payer,
+                &mint.pubkey(),
+                rent.minimum_balance(space),
+                space as u64,
+                &token_program
+            )
+        ];
+        for extension in extensions {
+            instructions.push(match *extension {
+                MintExtension::TransferFee(bps) =>
+                    transfer_fee::instruction
+                        ::initialize_transfer_fee_config(
+                            &token_program,
+                            &mint.pubkey(),
+                            None,
+                            None,
+                            bps,
+                            u64::MAX
+                        )
+                        .unwrap(),
+                MintExtension::TransferHook(program_id) =>
+                    transfer_hook::instruction
+                        ::initialize(&token_program, &mint.pubkey(), None, Some(program_id))
+                        .unwrap(),
+            });
+        }
+        instructions.push(
+            spl_token_2022::instruction
+                ::initialize_mint2(&token_program, &mint.pubkey(), &payer, None, decimals)
+                .unwrap()
+        );
+        self.send(&instructions, &[&mint]).await.unwrap();
+        mint.pubkey()
+    }
+
+    pub async fn create_ata(&mut self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        let address = self.ata(owner, mint);
+        // Resending an identical transaction in the same slot would stall
+        if self.account(&address).await.is_some() {
+            return address;
+        }
+        let ix = create_associated_token_account_idempotent(
+            &self.ctx.payer.pubkey(),
+            owner,
+            mint,
+            &self.token_program
+        );
+        self.send(&[ix], &[]).await.unwrap();
+        address
+    }
+
+    /// Mints `amount` tokens into `to`; for the native mint, wraps SOL.
+    pub async fn mint_to(&mut self, mint: &Pubkey, to: &Pubkey, amount: u64) {
+        if is_native_mint(mint) {
+            let instructions = [
+                system_instruction::transfer(&self.ctx.payer.pubkey(), to, amount),
+                spl_token_2022::instruction::sync_native(&self.token_program, to).unwrap(),
+            ];
+            self.send(&instructions, &[]).await.unwrap();
+            return;
+        }
+        let ix = spl_token_2022::instruction
+            ::mint_to(&self.token_program, mint, to, &self.ctx.payer.pubkey(), &[], amount)
+            .unwrap();
+        self.send(&[ix], &[]).await.unwrap();
+    }
+
+    /// A funded wallet holding `amount` stake tokens, with both token accounts.
+    pub async fn new_user(&mut self, amount: u64) -> Keypair {
+        let user = Keypair::new();
+        let (stake_mint, reward_mint) = (self.stake_mint, self.reward_mint);
+        if self.native_sol {
+            self.airdrop(&user.pubkey(), 1_000_000_000 + amount).await;
+            if !is_native_mint(&reward_mint) {
+                self.create_ata(&user.pubkey(), &reward_mint).await;
+            }
+            return user;
+        }
+        self.airdrop(&user.pubkey(), 1_000_000_000).await;
+        let stake_account = self.create_ata(&user.pubkey(), &stake_mint).await;
+        self.create_ata(&user.pubkey(), &reward_mint).await;
+        self.mint_to(&stake_mint, &stake_account, amount).await;
+        user
+    }
+
+    pub async fn account(&mut self, address: &Pubkey) -> Option<Account> {
+        self.ctx.banks_client.get_account(*address).await.unwrap()
+    }
+
+    pub async fn fetch<T: AccountDeserialize>(&mut self, address: &Pubkey) -> T {
+        let account = self.account(address).await.unwrap();
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    pub async fn lamports(&mut self, address: &Pubkey) -> u64 {
+        self.account(address).await.map_or(0, |account| account.lamports)
+    }
+
+    pub async fn token_balance(&mut self, address: &Pubkey) -> u64 {
+        let account = self.account(address).await.unwrap();
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data).unwrap().base.amount
+    }
+
+    pub async fn stake_balance(&mut self, owner: &Pubkey) -> u64 {
+        let address = self.ata(owner, &self.stake_mint);
+        self.token_balance(&address).await
+    }
+
+    pub async fn reward_balance(&mut self, owner: &Pubkey) -> u64 {
+        let address = self.ata(owner, &self.reward_mint);
+        self.token_balance(&address).await
+    }
+
+    pub async fn pool_config(&mut self) -> PoolConfig {
+        let pool = self.pool;
+        self.fetch(&pool).await
+    }
+
+    pub async fn pool_state(&mut self) -> PoolState {
+        let state = self.pool_config().await.state_addr;
+        self.fetch(&state).await
+    }
+
+    pub async fn user_info(&mut self, user: &Pubkey) -> UserInfo {
+        self.position(user, 0).await
+    }
+
+    pub async fn position(&mut self, user: &Pubkey, position: u32) -> UserInfo {
+        let address = self.position_address(user, position);
+        self.fetch(&address).await
+    }
+
+    pub async fn start_reward(&mut self) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::StartReward {
+                deployer: self.creator.pubkey(),
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                token_program: self.token_program,
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::StartReward {}).data(),
+        };
+        let creator = self.creator.insecure_clone();
+        self.send(&[ix], &[&creator]).await
+    }
+
+    /// Stops the pool; the vault owner signs and the unearned rewards go back
+    /// to the creator.
+    pub async fn stop_reward(&mut self) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let creator = self.creator.pubkey();
+        let admin = self.admin.insecure_clone();
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::StopReward {
+                deployer: admin.pubkey(),
+                pool_config_account: self.pool,
+                pool_reward_token_vault: config.pool_reward_token_vault,
+                deployer_reward_token_vault: self.ata(&creator, &config.reward_mint),
+                pool_state_account: config.state_addr,
+                reward_mint: config.reward_mint,
+                token_program: self.token_program,
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::StopReward {}).data(),
+        };
+        self.send(&[ix], &[&admin]).await
+    }
+
+    pub async fn stake(&mut self, user: &Keypair, stake_amount: u64) -> Result<(), BanksClientError> {
+        self.stake_with_minimum(user, stake_amount, 0).await
+    }
+
+    pub async fn stake_with_minimum(
+        &mut self,
+        user: &Keypair,
+        stake_amount: u64,
+        min_received: u64
+    ) -> Result<(), BanksClientError> {
+        self.stake_with_options(user, 0, stake_amount, min_received, None, 0, None).await
+    }
+
+    pub async fn stake_with_proof(
+        &mut self,
+        user: &Keypair,
+        stake_amount: u64,
+        allowlist_proof: AllowlistProof
+    ) -> Result<(), BanksClientError> {
+        self.stake_with_options(user, 0, stake_amount, 0, Some(allowlist_proof), 0, None).await
+    }
+
+    pub async fn stake_locked(
+        &mut self,
+        user: &Keypair,
+        stake_amount: u64,
+        lock_weeks: u16
+    ) -> Result<(), BanksClientError> {
+        self.stake_with_options(user, 0, stake_amount, 0, None, lock_weeks, None).await
+    }
+
+    pub async fn stake_position(
+        &mut self,
+        user: &Keypair,
+        position: u32,
+        stake_amount: u64,
+        lock_weeks: u16
+    ) -> Result<(), BanksClientError> {
+        self.stake_with_options(user, position, stake_amount, 0, None, lock_weeks, None).await
+    }
+
+    pub async fn stake_referred(
+        &mut self,
+        user: &Keypair,
+        stake_amount: u64,
+        referrer: &Pubkey
+    ) -> Result<(), BanksClientError> {
+        self.stake_with_options(user, 0, stake_amount, 0, None, 0, Some(*referrer)).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn stake_with_options(
+        &mut self,
+        user: &Keypair,
+        position: u32,
+        stake_amount: u64,
+        min_received: u64,
+        allowlist_proof: Option<AllowlistProof>,
+        lock_weeks: u16,
+        referrer: Option<Pubkey>
+    ) -> Result<(), BanksClientError> {
+        let ix = self.stake_instruction(
+            user.pubkey(),
+            position,
+            stake_amount,
+            min_received,
+            allowlist_proof,
+            lock_weeks,
+            referrer
+        ).await;
+        let admin = self.admin.insecure_clone();
+        self.send(&[ix], &[user, &admin]).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn stake_instruction(
+        &mut self,
+        staker: Pubkey,
+        position: u32,
+        stake_amount: u64,
+        min_received: u64,
+        allowlist_proof: Option<AllowlistProof>,
+        lock_weeks: u16,
+        referrer: Option<Pubkey>
+    ) -> Instruction {
+        let config = self.pool_config().await;
+        // Positions keep their first referrer, whose account goes along
+        let referrer_info = self
+            .position_referrer(&staker, position).await
+            .or(referrer)
+            .map(|referrer| self.referrer_address(&referrer));
+        let (pool_fee_exemption, platform_fee_exemption) = self.fee_exemptions(&staker).await;
+        let treasury = self.treasury.pubkey();
+        Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::Stake {
+                user_info: self.position_address(&staker, position),
+                referrer_info,
+                staker,
+                admin: self.admin.pubkey(),
+                treasury,
+                platform: self.platform(),
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                user_stake_token_vault: self.user_token_account(&staker, &config.stake_mint),
+                user_reward_token_vault: self.reward_account(&staker, position).await,
+                pool_stake_token_vault: config.pool_stake_token_vault,
+                pool_reward_token_vault: config.pool_reward_token_vault,
+                creator_stake_token_vault: self.ata(
+                    &config.owner,
+                    &config.stake_mint
+                ),
+                treasury_stake_token_vault: self.ata(&treasury, &config.stake_mint),
+                treasury_reward_token_vault: self.treasury_reward_account(&config),
+                stake_mint: config.stake_mint,
+                reward_mint: config.reward_mint,
+                wsol_account: self.wsol_account(&staker),
+                pool_fee_exemption,
+                platform_fee_exemption,
+                system_program: system_program::ID,
+                token_program: self.token_program,
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::Stake {
+                stake_amount,
+                min_received,
+                allowlist_proof,
+                lock_weeks,
+                position,
+                referrer,
+            }).data(),
+        }
+    }
+
+    pub async fn unstake(
+        &mut self,
+        user: &Keypair,
+        unstake_amount: u64
+    ) -> Result<(), BanksClientError> {
+        self.unstake_position(user, 0, unstake_amount).await
+    }
+
+    pub async fn unstake_position(
+        &mut self,
+        user: &Keypair,
+        position: u32,
+        unstake_amount: u64
+    ) -> Result<(), BanksClientError> {
+        let data = (instruction::Unstake { unstake_amount, position }).data();
+        self.send_unstake(user, position, data).await
+    }
+
+    pub async fn exit(
+        &mut self,
+        user: &Keypair,
+        position: u32,
+        min_withdrawn: u64,
+        close: bool
+    ) -> Result<(), BanksClientError> {
+        let data = (instruction::Exit { min_withdrawn, position, close }).data();
+        self.send_unstake(user, position, data).await
+    }
+
+    /// Sends `data` with the accounts of `unstake`, which `exit` shares.
+    async fn send_unstake(&mut self, user: &Keypair, position: u32, data: Vec<u8>) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let staker = user.pubkey();
+        let (pool_fee_exemption, platform_fee_exemption) = self.fee_exemptions(&staker).await;
+        let treasury = self.treasury.pubkey();
+        // Pools with an unbonding period open the position's next ticket,
+        // unless there's nothing staked to hold in it
+        let withdrawal_ticket = if config.unbonding_slots > 0 {
+            let user_info = self.position(&staker, position).await;
+            (user_info.staked_amount > 0).then(|| self.ticket_address(&staker, position, user_info.ticket_count))
+        } else {
+            None
+        };
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::Unstake {
+                user: staker,
+                admin: self.admin.pubkey(),
+                treasury,
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                platform: self.platform(),
+                user_info: self.position_address(&staker, position),
+                withdrawal_ticket,
+                referrer_info: self.referrer_info(&staker, position).await,
+                user_stake_token_vault: self.user_token_account(&staker, &config.stake_mint),
+                user_reward_token_vault: self.reward_account(&staker, position).await,
+                pool_stake_token_vault: config.pool_stake_token_vault,
+                pool_reward_token_vault: config.pool_reward_token_vault,
+                creator_stake_token_vault: self.ata(
+                    &config.owner,
+                    &config.stake_mint
+                ),
+                treasury_stake_token_vault: self.ata(&treasury, &config.stake_mint),
+                treasury_reward_token_vault: self.treasury_reward_account(&config),
+                stake_mint: config.stake_mint,
+                reward_mint: config.reward_mint,
+                wsol_account: self.wsol_account(&staker),
+                token_program: self.token_program,
+                pool_fee_exemption,
+                platform_fee_exemption,
+                system_program: system_program::ID,
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data,
+        };
+        let admin = self.admin.insecure_clone();
+        self.send(&[ix], &[user, &admin]).await
+    }
+
+    pub async fn claim_reward(&mut self, user: &Keypair) -> Result<(), BanksClientError> {
+        self.claim_position(user, 0).await
+    }
+
+    pub async fn claim_position(&mut self, user: &Keypair, position: u32) -> Result<(), BanksClientError> {
+        let data = (instruction::ClaimReward { position }).data();
+        self.send_claim(user, position, data).await
+    }
+
+    pub async fn withdraw_vested(&mut self, user: &Keypair, position: u32) -> Result<(), BanksClientError> {
+        let data = (instruction::WithdrawVested { position }).data();
+        self.send_claim(user, position, data).await
+    }
+
+    /// Sends `data` with the accounts of `claim_reward`, which
+    /// `withdraw_vested` shares.
+    async fn send_claim(&mut self, user: &Keypair, position: u32, data: Vec<u8>) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let claimer = user.pubkey();
+        let reward_account = self.reward_account(&claimer, position).await;
+        let (pool_fee_exemption, platform_fee_exemption) = self.fee_exemptions(&claimer).await;
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::ClaimReward {
+                claimer,
+                admin: self.admin.pubkey(),
+                treasury: self.treasury.pubkey(),
+                user_info: self.position_address(&claimer, position),
+                referrer_info: self.referrer_info(&claimer, position).await,
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                platform: self.platform(),
+                user_reward_token_vault: reward_account,
+                pool_reward_token_vault: config.pool_reward_token_vault,
+                treasury_reward_token_vault: self.treasury_reward_account(&config),
+                reward_mint: config.reward_mint,
+                wsol_account: self.wsol_account(&claimer),
+                token_program: self.token_program,
+                pool_fee_exemption,
+                platform_fee_exemption,
+                system_program: system_program::ID,
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data,
+        };
+        let admin = self.admin.insecure_clone();
+        self.send(&[ix], &[user, &admin]).await
+    }
+
+    /// Claims `positions` in one payout, passing their accounts in order.
+    pub async fn claim_all(&mut self, user: &Keypair, positions: &[u32]) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let claimer = user.pubkey();
+        let (pool_fee_exemption, platform_fee_exemption) = self.fee_exemptions(&claimer).await;
+        let (referrer_info, reward_account) = match positions.first() {
+            Some(&position) => (
+                self.referrer_info(&claimer, position).await,
+                self.reward_account(&claimer, position).await,
+            ),
+            None => (None, self.user_token_account(&claimer, &config.reward_mint)),
+        };
+        let mut accounts = (accounts::ClaimAll {
+            claimer,
+            admin: self.admin.pubkey(),
+            treasury: self.treasury.pubkey(),
+            pool_config_account: self.pool,
+            pool_state_account: config.state_addr,
+            platform: self.platform(),
+            referrer_info,
+            user_reward_token_vault: reward_account,
+            pool_reward_token_vault: config.pool_reward_token_vault,
+            treasury_reward_token_vault: self.treasury_reward_account(&config),
+            reward_mint: config.reward_mint,
+            wsol_account: self.wsol_account(&claimer),
+            token_program: self.token_program,
+            pool_fee_exemption,
+            platform_fee_exemption,
+            system_program: system_program::ID,
+            event_authority: event_authority(),
+            program: PROGRAM_ID,
+        }).to_account_metas(None);
+        for &position in positions {
+            accounts.push(AccountMeta::new(self.position_address(&claimer, position), false));
+        }
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts,
+            data: (instruction::ClaimAll { positions: positions.to_vec() }).data(),
+        };
+        let admin = self.admin.insecure_clone();
+        self.send(&[ix], &[user, &admin]).await
+    }
+
+    pub async fn compound_reward(&mut self, user: &Keypair) -> Result<(), BanksClientError> {
+        let config = self.pool_config().await;
+        let (pool_fee_exemption, platform_fee_exemption) = self.fee_exemptions(&user.pubkey()).await;
+        let referrer_info = self.referrer_info(&user.pubkey(), 0).await;
+        let amm = &self.amm;
+        let ix = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: (accounts::CompoundReward {
+                user: user.pubkey(),
+                admin: self.admin.pubkey(),
+                treasury: self.treasury.pubkey(),
+                pool_config_account: self.pool,
+                pool_state_account: config.state_addr,
+                user_info: self.user_info_address(&user.pubkey()),
+                referrer_info,
+                platform: self.platform(),
+                pool_stake_token_vault: config.pool_stake_token_vault,
+                pool_reward_token_vault: config.pool_reward_token_vault,
+                treasury_reward_token_vault: self.treasury_reward_account(&config),
+                reward_mint: config.reward_mint,
+                token_program: self.token_program,
+                amm_program: mock_amm::ID,
+                amm: amm.amm,
+                amm_authority: amm.authority,
+                amm_open_orders: amm.unused[0],
+                amm_target_orders: amm.unused[1],
+                pool_coin_token_account: amm.coin_vault,
+                pool_pc_token_account: amm.pc_vault,
+                serum_program: amm.unused[2],
+                serum_market: amm.unused[3],
+                serum_bids: amm.unused[4],
+                serum_asks: amm.unused[5],
+                serum_event_queue: amm.unused[6],
+                serum_coin_vault_account: amm.unused[7],
+                serum_pc_vault_account: amm.unused[8],
+                serum_vault_signer: amm.unused[9],
+                spl_token_program: spl_token::ID,
+                pool_fee_exemption,
+                platform_fee_exemption,
+                system_program: system_program::ID,
+                event_authority: event_authority(),
+                program: PROGRAM_ID,
+            }).to_account_metas(None),
+            data: (instruction::CompoundReward { position: 0 }).data(),
+        };
+        let admin = self.admin.insecure_clone();
+        self.send(&[ix], &[user, &admin]).await
+    }
+}
+
+pub enum MintExtension {
+    /// Transfer fee in basis points, without a cap
+    TransferFee(u16),
+    /// Transfer hook program
+    TransferHook(Pubkey),
+}
+
+impl MintExtension {
+    fn extension_type(&self) -> ExtensionType {
+        match self {
+            Self::TransferFee(_) => ExtensionType::TransferFeeConfig,
+            Self::TransferHook(_) => ExtensionType::TransferHook,
+        }
+    }
+}
+
+/// The PDA `emit_cpi!` signs its self-invocation with.
+pub fn event_authority() -> Pubkey {
+    Pubkey::find_program_address(&[b"__event_authority"], &PROGRAM_ID).0
+}
+
+/// The custom error code a failed single-instruction transaction returned.
+pub fn custom_error(result: Result<(), BanksClientError>) -> u32 {
+    match result.unwrap_err().unwrap() {
+        TransactionError::InstructionError(0, InstructionError::Custom(code)) => code,
+        err => panic!("unexpected error {:?}", err),
+    }
+}
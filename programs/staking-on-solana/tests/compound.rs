@@ -0,0 +1,69 @@
+mod common;
+
+use common::{ PoolArgs, TestEnv, PERFORMANCE_FEE };
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn compound_swaps_rewards_into_stake() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let admin = env.admin.pubkey();
+    let treasury = env.treasury.pubkey();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+
+    let treasury_before = env.lamports(&treasury).await;
+    env.warp_to(220);
+    env.compound_reward(&alice).await.unwrap();
+
+    // The mock AMM pays 2 stake tokens per reward token
+    let (coin_vault, pc_vault) = (env.amm.coin_vault, env.amm.pc_vault);
+    assert_eq!(env.token_balance(&coin_vault).await, 100_000);
+    assert_eq!(env.token_balance(&pc_vault).await, common::mock_amm::LIQUIDITY - 200_000);
+    assert_eq!(env.reward_balance(&admin).await, 300_000_000 - 100_000);
+    assert_eq!(env.stake_balance(&admin).await, 1_200_000);
+    assert_eq!(env.reward_balance(&alice.pubkey()).await, 0);
+    assert_eq!(env.lamports(&treasury).await, treasury_before + PERFORMANCE_FEE);
+
+    let user = env.user_info(&alice.pubkey()).await;
+    assert_eq!(user.staked_amount, 1_200_000);
+    assert_eq!(user.reward_debt, 120_000);
+
+    let state = env.pool_state().await;
+    assert_eq!(state.total_staked, 1_200_000);
+    assert_eq!(state.reward_amount, 300_000_000 - 100_000);
+    assert_eq!(state.paid_rewards, 100_000);
+
+    // The compounded stake earns from then on: 120 slots of 1_000
+    env.warp_to(340);
+    env.claim_reward(&alice).await.unwrap();
+    assert_eq!(env.reward_balance(&alice.pubkey()).await, 120_000);
+}
+
+#[tokio::test]
+async fn compound_restakes_in_place_for_single_token_pools() {
+    let mut env = TestEnv::new(PoolArgs { same_mint: true, ..Default::default() }).await;
+    let alice = env.new_user(1_000_000).await;
+    let admin = env.admin.pubkey();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+    env.warp_to(220);
+    env.compound_reward(&alice).await.unwrap();
+
+    // Nothing is swapped; the reward stays in the shared vault as stake
+    let pc_vault = env.amm.pc_vault;
+    assert_eq!(env.token_balance(&pc_vault).await, common::mock_amm::LIQUIDITY);
+    assert_eq!(env.stake_balance(&admin).await, 300_000_000 + 1_000_000);
+    assert_eq!(env.user_info(&alice.pubkey()).await.staked_amount, 1_100_000);
+
+    let state = env.pool_state().await;
+    assert_eq!(state.total_staked, 1_100_000);
+    assert_eq!(state.reward_amount, 300_000_000 - 100_000);
+    assert_eq!(state.paid_rewards, 100_000);
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use common::{ custom_error, PoolArgs, TestEnv, PERFORMANCE_FEE };
+use solana_sdk::signature::Signer;
+use staking_on_solana::error::BrewStakingError;
+
+#[tokio::test]
+async fn exit_withdraws_the_whole_stake_and_reward() {
+    let mut env = TestEnv::new(PoolArgs { unstake_fee: 200, ..Default::default() }).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(1_000_000).await;
+    let creator = env.creator.pubkey();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+    env.stake(&bob, 1_000_000).await.unwrap();
+
+    // 2% of the stake goes to the pool creator, which leaves 980_000
+    env.warp_to(220);
+    assert_eq!(
+        custom_error(env.exit(&alice, 0, 980_001, false).await),
+        u32::from(BrewStakingError::ReceivedBelowMinimum)
+    );
+    env.exit(&alice, 0, 980_000, false).await.unwrap();
+
+    assert_eq!(env.stake_balance(&alice.pubkey()).await, 980_000);
+    assert_eq!(env.reward_balance(&alice.pubkey()).await, 50_000);
+    assert_eq!(env.stake_balance(&creator).await, 20_000);
+    assert_eq!(env.user_info(&alice.pubkey()).await.staked_amount, 0);
+    assert_eq!(env.pool_state().await.total_staked, 1_000_000);
+
+    // The empty position stays open until it's closed
+    let address = env.user_info_address(&alice.pubkey());
+    env.close_user_info(&alice, 0).await.unwrap();
+    assert!(env.account(&address).await.is_none());
+}
+
+#[tokio::test]
+async fn exit_can_close_the_position() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(1_000_000).await;
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+    env.stake(&bob, 1_000_000).await.unwrap();
+
+    // The rent comes back to alice in the same transaction
+    env.warp_to(220);
+    let address = env.user_info_address(&alice.pubkey());
+    let rent = env.lamports(&address).await;
+    let balance = env.lamports(&alice.pubkey()).await;
+    env.exit(&alice, 0, 0, true).await.unwrap();
+
+    assert!(env.account(&address).await.is_none());
+    assert_eq!(env.lamports(&alice.pubkey()).await, balance + rent - PERFORMANCE_FEE);
+    assert_eq!(env.stake_balance(&alice.pubkey()).await, 1_000_000);
+    assert_eq!(env.reward_balance(&alice.pubkey()).await, 50_000);
+
+    // A reward still vesting keeps the position open
+    env.set_vesting(1_000, false).await.unwrap();
+    env.warp_to(320);
+    let address = env.user_info_address(&bob.pubkey());
+    env.exit(&bob, 0, 0, true).await.unwrap();
+    assert_eq!(env.stake_balance(&bob.pubkey()).await, 1_000_000);
+    assert_eq!(env.user_info(&bob.pubkey()).await.vesting_amount, 150_000);
+    env.exit(&bob, 0, 0, true).await.unwrap();
+    assert!(env.account(&address).await.is_some());
+
+    // Once it's withdrawn, an exit with nothing staked only closes the position
+    env.warp_to(1_320);
+    env.withdraw_vested(&bob, 0).await.unwrap();
+    env.exit(&bob, 0, 0, true).await.unwrap();
+    assert!(env.account(&address).await.is_none());
+    assert_eq!(env.reward_balance(&bob.pubkey()).await, 150_000);
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::{ PoolArgs, TestEnv, PERFORMANCE_FEE };
+use solana_sdk::signature::Signer;
+use staking_on_solana::state::FeeExemption;
+
+// 1% stake fee, 2% unstake fee, and the flat performance fee on every action.
+
+#[tokio::test]
+async fn pool_exemptions_waive_only_the_pool_fees() {
+    let mut env = TestEnv::new(PoolArgs { stake_fee: 100, unstake_fee: 200, ..Default::default() }).await;
+    let alice = env.new_user(1_000_000).await;
+    let user = alice.pubkey();
+    let creator = env.creator.pubkey();
+    let treasury = env.treasury.pubkey();
+
+    env.set_pool_fee_exemption(&user, 10_000).await.unwrap();
+    let pool = env.pool;
+    let exemption: FeeExemption = env.fetch(&env.fee_exemption_address(&pool, &user)).await;
+    assert_eq!((exemption.scope, exemption.wallet, exemption.discount), (pool, user, 10_000));
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+
+    let treasury_before = env.lamports(&treasury).await;
+    env.stake(&alice, 1_000_000).await.unwrap();
+    env.unstake(&alice, 500_000).await.unwrap();
+    assert_eq!(env.stake_balance(&creator).await, 0);
+    assert_eq!(env.stake_balance(&user).await, 500_000);
+    assert_eq!(env.lamports(&treasury).await, treasury_before + 2 * PERFORMANCE_FEE);
+}
+
+#[tokio::test]
+async fn platform_exemptions_discount_every_fee() {
+    let mut env = TestEnv::new(PoolArgs { stake_fee: 100, unstake_fee: 200, ..Default::default() }).await;
+    let alice = env.new_user(1_000_000).await;
+    let user = alice.pubkey();
+    let creator = env.creator.pubkey();
+    let treasury = env.treasury.pubkey();
+
+    // The larger of the two discounts applies to the pool fees
+    env.set_pool_fee_exemption(&user, 2_500).await.unwrap();
+    env.set_platform_fee_exemption(&user, 5_000).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+
+    let treasury_before = env.lamports(&treasury).await;
+    env.stake(&alice, 1_000_000).await.unwrap();
+    assert_eq!(env.stake_balance(&creator).await, 5_000);
+    assert_eq!(env.user_info(&user).await.staked_amount, 995_000);
+    assert_eq!(env.lamports(&treasury).await, treasury_before + PERFORMANCE_FEE / 2);
+}
+
+#[tokio::test]
+async fn only_the_pool_owner_sets_pool_exemptions() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(0).await;
+
+    // The creator signs in the harness; swap in someone else
+    let creator = std::mem::replace(&mut env.creator, alice.insecure_clone());
+    assert!(env.set_pool_fee_exemption(&alice.pubkey(), 10_000).await.is_err());
+
+    env.creator = creator;
+    env.set_pool_fee_exemption(&alice.pubkey(), 10_000).await.unwrap();
+}
@@ -0,0 +1,320 @@
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use common::{ custom_error, PoolArgs, TestEnv, DEPLOY_FEE, PERFORMANCE_FEE };
+use solana_sdk::signature::Signer;
+use staking_on_solana::error::BrewStakingError;
+use staking_on_solana::state::{ PerformanceFeeModel, PlatformInfo, SLOTS_PER_DAY };
+
+// Pools reward 1_000 tokens per slot; with 6-decimal reward tokens the
+// precision factor is 1_000, so `acc_token_per_share` gains
+// `slots * 1_000 * 1_000 / total_staked` on every update.
+
+#[tokio::test]
+async fn create_pool_takes_funding_and_deploy_fee() {
+    let mut env = TestEnv::new(PoolArgs { stake_fee: 100, unstake_fee: 200, ..Default::default() }).await;
+
+    let platform: PlatformInfo = env.fetch(&env.platform()).await;
+    assert_eq!(platform.treasury, env.treasury.pubkey());
+    assert_eq!(platform.deploy_fee, DEPLOY_FEE);
+    assert_eq!(platform.performance_fee, PERFORMANCE_FEE);
+
+    let config = env.pool_config().await;
+    assert_eq!(config.owner, env.creator.pubkey());
+    assert_eq!(config.stake_mint, env.stake_mint);
+    assert_eq!(config.reward_mint, env.reward_mint);
+    assert_eq!((config.stake_fee, config.unstake_fee), (100, 200));
+    assert_eq!(config.start_slot, 0);
+
+    let state = env.pool_state().await;
+    assert_eq!(state.reward_amount, 300_000_000);
+    assert_eq!(state.total_staked, 0);
+
+    let admin = env.admin.pubkey();
+    let creator = env.creator.pubkey();
+    let treasury = env.treasury.pubkey();
+    assert_eq!(env.reward_balance(&admin).await, 300_000_000);
+    assert_eq!(env.reward_balance(&creator).await, 0);
+    assert_eq!(env.lamports(&treasury).await, 10_000_000_000 + DEPLOY_FEE);
+}
+
+#[tokio::test]
+async fn start_reward_opens_the_pool_ten_slots_later() {
+    let mut env = TestEnv::new(PoolArgs { duration: 2, ..Default::default() }).await;
+    let alice = env.new_user(1_000_000).await;
+
+    env.warp_to(100);
+    assert_eq!(
+        custom_error(env.stake(&alice, 1_000_000).await),
+        u32::from(BrewStakingError::PoolNotStarted)
+    );
+
+    env.start_reward().await.unwrap();
+
+    let config = env.pool_config().await;
+    assert_eq!(config.start_slot, 110);
+    assert_eq!(config.end_slot, 110 + 2 * SLOTS_PER_DAY);
+    assert_eq!(env.pool_state().await.last_reward_slot, 110);
+
+    // Still closed until the start slot has passed
+    env.warp_to(110);
+    assert_eq!(
+        custom_error(env.stake(&alice, 1_000_000).await),
+        u32::from(BrewStakingError::PoolNotStarted)
+    );
+
+    env.warp_to(120);
+    assert_eq!(
+        custom_error(env.start_reward().await),
+        u32::from(BrewStakingError::PoolAlreadyStarted)
+    );
+    env.stake(&alice, 1_000_000).await.unwrap();
+    assert_eq!(env.user_info(&alice.pubkey()).await.staked_amount, 1_000_000);
+}
+
+#[tokio::test]
+async fn stakers_share_rewards_by_stake() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(3_000_000).await;
+    let admin = env.admin.pubkey();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+
+    // Alice is alone for slots 120..220: acc 100
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+    env.warp_to(220);
+    env.stake(&bob, 3_000_000).await.unwrap();
+    assert_eq!(env.stake_balance(&admin).await, 4_000_000);
+    assert_eq!(env.stake_balance(&bob.pubkey()).await, 0);
+
+    // 220..320 split 1:3: acc 125
+    env.warp_to(320);
+    env.claim_reward(&alice).await.unwrap();
+    env.unstake(&bob, 2_000_000).await.unwrap();
+
+    assert_eq!(env.reward_balance(&alice.pubkey()).await, 100_000 + 25_000);
+    assert_eq!(env.reward_balance(&bob.pubkey()).await, 75_000);
+    assert_eq!(env.stake_balance(&bob.pubkey()).await, 2_000_000);
+    assert_eq!(env.stake_balance(&admin).await, 2_000_000);
+
+    // 320..420 split 1:1: acc 175
+    env.warp_to(420);
+    env.claim_reward(&alice).await.unwrap();
+    env.claim_reward(&bob).await.unwrap();
+
+    assert_eq!(env.reward_balance(&alice.pubkey()).await, 125_000 + 50_000);
+    assert_eq!(env.reward_balance(&bob.pubkey()).await, 75_000 + 50_000);
+    assert_eq!(env.reward_balance(&admin).await, 300_000_000 - 300_000);
+
+    let state = env.pool_state().await;
+    assert_eq!(state.acc_token_per_share, 175);
+    assert_eq!(state.total_staked, 2_000_000);
+    assert_eq!(state.should_total_paid, 300_000);
+    assert_eq!(state.paid_rewards, 300_000);
+    assert_eq!(state.reward_amount, 300_000_000 - 300_000);
+
+    let bob_info = env.user_info(&bob.pubkey()).await;
+    assert_eq!(bob_info.staked_amount, 1_000_000);
+    assert_eq!(bob_info.reward_debt, 175_000);
+
+    // More than the position can't be unstaked, the whole of it can
+    assert_eq!(
+        custom_error(env.unstake(&alice, 1_000_001).await),
+        u32::from(BrewStakingError::UnstakeAmountTooHigh)
+    );
+    env.unstake(&alice, 1_000_000).await.unwrap();
+    assert_eq!(env.user_info(&alice.pubkey()).await.staked_amount, 0);
+    assert_eq!(env.stake_balance(&alice.pubkey()).await, 1_000_000);
+}
+
+#[tokio::test]
+async fn fees_go_to_the_pool_owner_and_treasury() {
+    let mut env = TestEnv::new(PoolArgs { stake_fee: 100, unstake_fee: 200, ..Default::default() }).await;
+    let alice = env.new_user(1_000_000).await;
+    let creator = env.creator.pubkey();
+    let treasury = env.treasury.pubkey();
+    let treasury_before = env.lamports(&treasury).await;
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+
+    let alice_lamports = env.lamports(&alice.pubkey()).await;
+    env.stake(&alice, 1_000_000).await.unwrap();
+    env.unstake(&alice, 490_000).await.unwrap();
+
+    // 1% of the deposit and 2% of the withdrawal go to the pool creator
+    assert_eq!(env.stake_balance(&creator).await, 10_000 + 9_800);
+    assert_eq!(env.stake_balance(&alice.pubkey()).await, 480_200);
+    assert_eq!(env.stake_balance(&env.admin.pubkey()).await, 500_000);
+    assert_eq!(env.user_info(&alice.pubkey()).await.staked_amount, 500_000);
+    assert_eq!(env.pool_state().await.total_staked, 500_000);
+
+    // Every user action pays the platform's flat fee in lamports; the
+    // transaction fee is paid by the harness' fee payer, not by alice.
+    assert_eq!(env.lamports(&treasury).await, treasury_before + 2 * PERFORMANCE_FEE);
+    let rent = env.ctx.banks_client.get_rent().await.unwrap();
+    let user_info_rent = rent.minimum_balance(staking_on_solana::state::USER_INFO_SIZE);
+    assert_eq!(
+        env.lamports(&alice.pubkey()).await,
+        alice_lamports - user_info_rent - 2 * PERFORMANCE_FEE
+    );
+}
+
+#[tokio::test]
+async fn treasury_takes_its_share_of_pool_fees() {
+    let mut env = TestEnv::new(PoolArgs {
+        stake_fee: 100,
+        unstake_fee: 200,
+        treasury_fee_share: 2_500,
+        ..Default::default()
+    }).await;
+    let platform = env.platform();
+    assert_eq!(env.fetch::<PlatformInfo>(&platform).await.treasury_fee_share, 2_500);
+    let alice = env.new_user(1_000_000).await;
+    let creator = env.creator.pubkey();
+    let treasury = env.treasury.pubkey();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+
+    // A quarter of the 10_000 stake fee and the 9_800 unstake fee
+    env.stake(&alice, 1_000_000).await.unwrap();
+    env.unstake(&alice, 490_000).await.unwrap();
+    assert_eq!(env.stake_balance(&creator).await, 7_500 + 7_350);
+    assert_eq!(env.stake_balance(&treasury).await, 2_500 + 2_450);
+    assert_eq!(env.stake_balance(&alice.pubkey()).await, 480_200);
+
+    let config = env.pool_config().await;
+    assert_eq!((config.creator_fees, config.treasury_fees), (14_850, 4_950));
+}
+
+#[tokio::test]
+async fn flat_performance_fee_skips_actions_that_move_nothing() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let treasury = env.treasury.pubkey();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+
+    // Nothing has accrued in the staking slot
+    let treasury_before = env.lamports(&treasury).await;
+    env.claim_reward(&alice).await.unwrap();
+    env.compound_reward(&alice).await.unwrap();
+    assert_eq!(env.lamports(&treasury).await, treasury_before);
+
+    env.warp_to(130);
+    env.claim_reward(&alice).await.unwrap();
+    assert_eq!(env.lamports(&treasury).await, treasury_before + PERFORMANCE_FEE);
+}
+
+#[tokio::test]
+async fn reward_share_pools_pay_the_treasury_in_reward_tokens() {
+    let mut env = TestEnv::new(PoolArgs {
+        performance_fee_model: PerformanceFeeModel::RewardShare,
+        ..Default::default()
+    }).await;
+    let alice = env.new_user(1_000_000).await;
+    let user = alice.pubkey();
+    let treasury = env.treasury.pubkey();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+
+    // No lamports change hands for the platform
+    let treasury_before = env.lamports(&treasury).await;
+    env.stake(&alice, 1_000_000).await.unwrap();
+    assert_eq!(env.lamports(&treasury).await, treasury_before);
+
+    // 10% of the 10_000 reward goes to the treasury
+    env.warp_to(130);
+    let alice_lamports = env.lamports(&user).await;
+    env.claim_reward(&alice).await.unwrap();
+    assert_eq!(env.lamports(&user).await, alice_lamports);
+    assert_eq!(env.reward_balance(&user).await, 9_000);
+    assert_eq!(env.reward_balance(&treasury).await, 1_000);
+    assert_eq!(env.pool_state().await.paid_rewards, 10_000);
+
+    env.warp_to(140);
+    env.unstake(&alice, 500_000).await.unwrap();
+    assert_eq!(env.reward_balance(&user).await, 18_000);
+    assert_eq!(env.reward_balance(&treasury).await, 2_000);
+    assert_eq!(env.lamports(&treasury).await, treasury_before);
+}
+
+#[tokio::test]
+async fn stop_reward_refunds_unearned_rewards() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let creator = env.creator.pubkey();
+    let admin = env.admin.pubkey();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+
+    env.warp_to(320);
+    env.stop_reward().await.unwrap();
+
+    // 200 slots were earned; everything else goes back to the creator
+    assert_eq!(env.pool_config().await.end_slot, 320);
+    assert_eq!(env.reward_balance(&creator).await, 300_000_000 - 200_000);
+    assert_eq!(env.reward_balance(&admin).await, 200_000);
+
+    // Rewards stop accruing at the end slot
+    env.warp_to(500);
+    env.claim_reward(&alice).await.unwrap();
+    assert_eq!(env.reward_balance(&alice.pubkey()).await, 200_000);
+    assert_eq!(env.reward_balance(&admin).await, 0);
+
+    let state = env.pool_state().await;
+    assert_eq!(state.should_total_paid, 200_000);
+    assert_eq!(state.paid_rewards, 200_000);
+}
+
+#[tokio::test]
+async fn stakes_only_credit_their_own_pool() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let (stake_mint, reward_mint) = (env.stake_mint, env.reward_mint);
+    let first = env.pool;
+    let args = PoolArgs { pool_id: "2", initial_funding: 0, ..PoolArgs::default() };
+    env.pool = env.create_pool(&args, &stake_mint, &reward_mint).await.unwrap();
+    let other_state = env.pool_config().await.state_addr;
+    env.pool = first;
+    let alice = env.new_user(2_000_000).await;
+    let admin = env.admin.pubkey();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+
+    // A fee-free stake moves no admin-owned tokens, so alice can send it alone,
+    // but only with her pool's own state
+    let mut ix = env.stake_instruction(alice.pubkey(), 0, 1_000_000, 0, None, 0, None).await;
+    let state = env.pool_config().await.state_addr;
+    for meta in &mut ix.accounts {
+        meta.is_signer &= meta.pubkey != admin;
+    }
+    let mut forged = ix.clone();
+    for meta in &mut forged.accounts {
+        if meta.pubkey == state {
+            meta.pubkey = other_state;
+        }
+    }
+    assert_eq!(
+        custom_error(env.send_paid_by(&[forged], &alice).await),
+        u32::from(ErrorCode::ConstraintAddress)
+    );
+
+    env.send_paid_by(&[ix], &alice).await.unwrap();
+    assert_eq!(env.pool_state().await.total_staked, 1_000_000);
+}
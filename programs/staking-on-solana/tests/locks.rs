@@ -0,0 +1,165 @@
+mod common;
+
+use common::{ custom_error, PoolArgs, TestEnv };
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{ Keypair, Signer };
+use staking_on_solana::error::BrewStakingError;
+use staking_on_solana::state::{ SLOTS_PER_DAY, SLOTS_PER_WEEK };
+
+// Pools reward 1_000 tokens per slot. A 2x lock multiplier makes alice's
+// 1_000_000 stake weigh 1_500_000 locked for 26 weeks and 2_000_000 for 52,
+// against bob's unlocked 500_000.
+
+#[tokio::test]
+async fn locks_weigh_by_length_until_they_end() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(500_000).await;
+    let user = alice.pubkey();
+    env.set_lock_multiplier(20_000).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake_locked(&alice, 1_000_000, 26).await.unwrap();
+    env.stake(&bob, 500_000).await.unwrap();
+    let info = env.user_info(&user).await;
+    assert_eq!((info.lock_end_slot, info.lock_multiplier), (120 + 26 * SLOTS_PER_WEEK, 15_000));
+    assert_eq!((info.boosted_amount, info.deposit_slot), (1_500_000, 120));
+
+    assert_eq!(
+        custom_error(env.unstake(&alice, 500_000).await),
+        u32::from(BrewStakingError::StakeLocked)
+    );
+    assert_eq!(
+        custom_error(env.extend_lock(&alice, 1).await),
+        u32::from(BrewStakingError::LockCannotShorten)
+    );
+
+    // 120..220 at 1.5x: alice earns 75%, kept as a credit when she relocks
+    env.warp_to(220);
+    env.extend_lock(&alice, 52).await.unwrap();
+    let info = env.user_info(&user).await;
+    assert_eq!((info.lock_end_slot, info.lock_multiplier), (220 + 52 * SLOTS_PER_WEEK, 20_000));
+    assert_eq!((info.boosted_amount, info.reward_credit), (2_000_000, 75_000));
+    assert_eq!(env.pool_state().await.total_boosted, 2_500_000);
+
+    // 220..320 at 2x: alice earns 80%
+    env.warp_to(320);
+    env.claim_reward(&alice).await.unwrap();
+    env.claim_reward(&bob).await.unwrap();
+    assert_eq!(env.reward_balance(&user).await, 75_000 + 80_000);
+    assert_eq!(env.reward_balance(&bob.pubkey()).await, 25_000 + 20_000);
+
+    // Anyone can drop the weight once the lock has ended
+    assert_eq!(
+        custom_error(env.expire_lock(&user).await),
+        u32::from(BrewStakingError::StakeLocked)
+    );
+    env.warp_to(220 + 52 * SLOTS_PER_WEEK);
+    env.expire_lock(&user).await.unwrap();
+    let info = env.user_info(&user).await;
+    assert_eq!((info.lock_multiplier, info.boosted_amount), (0, 1_000_000));
+    assert_eq!(env.pool_state().await.total_boosted, 1_500_000);
+
+    env.unstake(&alice, 500_000).await.unwrap();
+    assert_eq!(env.user_info(&user).await.staked_amount, 500_000);
+}
+
+#[tokio::test]
+async fn locks_follow_the_pool_terms() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let user = alice.pubkey();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    assert_eq!(
+        custom_error(env.stake_locked(&alice, 1_000_000, 13).await),
+        u32::from(BrewStakingError::LocksNotEnabled)
+    );
+    for multiplier in [9_999, 40_001] {
+        assert_eq!(
+            custom_error(env.set_lock_multiplier(multiplier).await),
+            u32::from(BrewStakingError::InvalidLockMultiplier)
+        );
+    }
+    env.set_lock_multiplier(20_000).await.unwrap();
+    assert_eq!(
+        custom_error(env.stake_locked(&alice, 1_000_000, 53).await),
+        u32::from(BrewStakingError::InvalidLockWeeks)
+    );
+
+    env.stake_locked(&alice, 1_000_000, 13).await.unwrap();
+    let info = env.user_info(&user).await;
+    assert_eq!((info.lock_multiplier, info.boosted_amount), (12_500, 1_250_000));
+
+    // An ended lock falls back to 1x on the user's next action
+    env.warp_to(120 + 13 * SLOTS_PER_WEEK);
+    env.claim_reward(&alice).await.unwrap();
+    let info = env.user_info(&user).await;
+    assert_eq!((info.lock_multiplier, info.boosted_amount), (0, 1_000_000));
+    assert_eq!(env.pool_state().await.total_boosted, 1_000_000);
+}
+
+const LOCK_END_SLOT: u64 = 120 + 13 * SLOTS_PER_WEEK;
+
+// A pool paying 1 token per slot for 100 days, where alice locks 1_000_000
+// for 13 weeks at 1.25x next to bob's 250_000, and nobody acts until
+// 1_000_000 slots after the lock ended. Until then alice earns 5/6, after the
+// end 4/5.
+async fn expired_lock() -> (TestEnv, Keypair, Keypair) {
+    let mut env = TestEnv::new(PoolArgs {
+        initial_funding: 100 * SLOTS_PER_DAY,
+        reward_per_slot: 1,
+        duration: 100,
+        ..PoolArgs::default()
+    }).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(250_000).await;
+    env.set_lock_multiplier(20_000).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake_locked(&alice, 1_000_000, 13).await.unwrap();
+    env.stake(&bob, 250_000).await.unwrap();
+
+    env.warp_to(LOCK_END_SLOT + 1_000_000);
+    (env, alice, bob)
+}
+
+#[tokio::test]
+async fn ended_locks_earn_at_1x_from_their_end_slot() {
+    // Nobody released the lock at its end, so the claim releases it as of then
+    let (mut env, alice, bob) = expired_lock().await;
+    env.claim_reward(&alice).await.unwrap();
+    env.claim_reward(&bob).await.unwrap();
+    assert_eq!(env.reward_balance(&alice.pubkey()).await, 16_380_000 + 800_000);
+    assert_eq!(env.reward_balance(&bob.pubkey()).await, 3_276_000 + 200_000);
+    assert_eq!(env.user_info(&alice.pubkey()).await.boosted_amount, 1_000_000);
+}
+
+#[tokio::test]
+async fn relocking_an_ended_lock_credits_1x_after_its_end() {
+    let (mut env, alice, _) = expired_lock().await;
+
+    env.extend_lock(&alice, 13).await.unwrap();
+    let info = env.user_info(&alice.pubkey()).await;
+    assert_eq!(info.reward_credit, 16_380_000 + 800_000);
+    assert_eq!((info.lock_multiplier, info.boosted_amount), (12_500, 1_250_000));
+}
+
+#[tokio::test]
+async fn boosting_an_ended_lock_credits_1x_after_its_end() {
+    let (mut env, alice, _) = expired_lock().await;
+    let collection = Pubkey::new_unique();
+    let nft = env.create_boost_nft(&alice.pubkey(), &collection, true).await;
+    env.set_boost_collection(Some(collection), 20_000).await.unwrap();
+
+    env.lock_boost_nft(&alice, &nft).await.unwrap();
+    let info = env.user_info(&alice.pubkey()).await;
+    assert_eq!(info.reward_credit, 16_380_000 + 800_000);
+    assert_eq!((info.lock_multiplier, info.boosted_amount), (0, 2_000_000));
+}
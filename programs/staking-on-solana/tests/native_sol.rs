@@ -0,0 +1,56 @@
+mod common;
+
+use common::{ PoolArgs, TestEnv, PERFORMANCE_FEE };
+use solana_sdk::{ rent::Rent, signature::Signer };
+use staking_on_solana::state::USER_INFO_SIZE;
+
+// A SOL/SOL pool: stakers send and receive lamports, the pool vault is the
+// admin's wSOL account. SOL has 9 decimals, so the precision factor is 1 and
+// a single staker of 9_900_000 lamports earns all 9_900_000 lamports per slot.
+
+#[tokio::test]
+async fn stakers_deposit_and_withdraw_native_sol() {
+    let mut env = TestEnv::new(PoolArgs {
+        stake_fee: 100,
+        reward_per_slot: 9_900_000,
+        same_mint: true,
+        native_sol: true,
+        ..Default::default()
+    }).await;
+    let alice = env.new_user(10_000_000).await;
+    let user = alice.pubkey();
+    let admin = env.admin.pubkey();
+    let creator = env.creator.pubkey();
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+
+    // 0.01 SOL in: 1% to the creator, the rest staked in the wSOL vault
+    let vault_before = env.stake_balance(&admin).await;
+    let lamports_before = env.lamports(&user).await;
+    env.stake(&alice, 10_000_000).await.unwrap();
+    let user_info_rent = Rent::default().minimum_balance(USER_INFO_SIZE);
+    assert_eq!(
+        lamports_before - env.lamports(&user).await,
+        10_000_000 + PERFORMANCE_FEE + user_info_rent
+    );
+    assert_eq!(env.user_info(&user).await.staked_amount, 9_900_000);
+    assert_eq!(env.stake_balance(&admin).await - vault_before, 9_900_000);
+    assert_eq!(env.stake_balance(&creator).await, 100_000);
+
+    // Rewards arrive as lamports; the wSOL account is gone again
+    env.warp_to(130);
+    let lamports_before = env.lamports(&user).await;
+    env.claim_reward(&alice).await.unwrap();
+    assert_eq!(env.lamports(&user).await + PERFORMANCE_FEE - lamports_before, 99_000_000);
+    let wsol_account = env.wsol_account(&user).unwrap();
+    assert!(env.account(&wsol_account).await.is_none());
+
+    // Withdrawals too, together with the reward since the claim
+    env.warp_to(140);
+    let lamports_before = env.lamports(&user).await;
+    env.unstake(&alice, 4_950_000).await.unwrap();
+    assert_eq!(env.lamports(&user).await + PERFORMANCE_FEE - lamports_before, 4_950_000 + 99_000_000);
+    assert_eq!(env.user_info(&user).await.staked_amount, 4_950_000);
+    assert!(env.account(&wsol_account).await.is_none());
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::{ custom_error, PoolArgs, TestEnv };
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use staking_on_solana::error::BrewStakingError;
+use staking_on_solana::ID as PROGRAM_ID;
+
+// Pools reward 1_000 tokens per slot. Alice holds 1_000_000 unlocked in
+// position 0 and 1_000_000 locked for 26 weeks at 1.5x in position 1.
+
+#[tokio::test]
+async fn positions_stake_and_earn_independently() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(2_000_000).await;
+    let user = alice.pubkey();
+    env.set_lock_multiplier(20_000).await.unwrap();
+
+    // Position 0 has no index in its seeds
+    let legacy = Pubkey::find_program_address(&[env.pool.as_ref(), user.as_ref()], &PROGRAM_ID).0;
+    assert_eq!(env.position_address(&user, 0), legacy);
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake_position(&alice, 0, 1_000_000, 0).await.unwrap();
+    env.stake_position(&alice, 1, 1_000_000, 26).await.unwrap();
+    assert_eq!(env.position(&user, 0).await.boosted_amount, 1_000_000);
+    assert_eq!(env.position(&user, 1).await.boosted_amount, 1_500_000);
+    assert_eq!(env.pool_state().await.total_staked, 2_000_000);
+
+    // 120..220: position 0 earns 40%, position 1 60%
+    env.warp_to(220);
+    env.claim_position(&alice, 1).await.unwrap();
+    assert_eq!(env.reward_balance(&user).await, 60_000);
+
+    // 220..320, both paid at once
+    env.warp_to(320);
+    env.claim_all(&alice, &[0, 1]).await.unwrap();
+    assert_eq!(env.reward_balance(&user).await, 60_000 + 40_000 + 100_000);
+    assert_eq!(
+        custom_error(env.claim_all(&alice, &[]).await),
+        u32::from(BrewStakingError::InvalidPositions)
+    );
+
+    // Only position 1 is locked
+    assert_eq!(
+        custom_error(env.unstake_position(&alice, 1, 500_000).await),
+        u32::from(BrewStakingError::StakeLocked)
+    );
+    env.unstake_position(&alice, 0, 500_000).await.unwrap();
+    assert_eq!(env.position(&user, 0).await.staked_amount, 500_000);
+    assert_eq!(env.position(&user, 1).await.staked_amount, 1_000_000);
+}
+
+#[tokio::test]
+async fn per_wallet_limits_keep_wallets_to_one_position() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(2_000_000).await;
+    env.set_stake_limits(0, 1_500_000, 0).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake_position(&alice, 0, 1_000_000, 0).await.unwrap();
+    assert_eq!(
+        custom_error(env.stake_position(&alice, 1, 1_000_000, 0).await),
+        u32::from(BrewStakingError::PositionsNotAllowed)
+    );
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use common::{ custom_error, PoolArgs, TestEnv };
+use solana_sdk::signature::Signer;
+use staking_on_solana::error::BrewStakingError;
+use staking_on_solana::state::{ ReferralSource, ReferrerInfo, MAX_REFERRAL_SHARE };
+
+#[tokio::test]
+async fn referrers_earn_a_share_of_the_stake_fee() {
+    let mut env = TestEnv::new(PoolArgs { stake_fee: 100, ..PoolArgs::default() }).await;
+    let alice = env.new_user(2_000_000).await;
+    let carol = env.new_user(0).await;
+    let dave = env.new_user(0).await;
+    let referrer = carol.pubkey();
+    assert_eq!(
+        custom_error(env.set_referral(MAX_REFERRAL_SHARE + 1, ReferralSource::StakeFee).await),
+        u32::from(BrewStakingError::InvalidReferralShare)
+    );
+    env.set_referral(2_000, ReferralSource::StakeFee).await.unwrap();
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+
+    assert_eq!(
+        custom_error(env.stake_referred(&alice, 1_000_000, &alice.pubkey()).await),
+        u32::from(BrewStakingError::SelfReferral)
+    );
+
+    // 20% of the 10_000 stake fee stays in the pool for carol
+    env.stake_referred(&alice, 1_000_000, &referrer).await.unwrap();
+    assert_eq!(env.user_info(&alice.pubkey()).await.referrer, Some(referrer));
+    let info: ReferrerInfo = env.fetch(&env.referrer_address(&referrer)).await;
+    assert_eq!((info.referrer, info.referred_count, info.stake_earned), (referrer, 1, 2_000));
+    let config = env.pool_config().await;
+    assert_eq!(config.creator_fees + config.treasury_fees, 8_000);
+
+    // The position keeps its first referrer
+    env.stake_referred(&alice, 1_000_000, &dave.pubkey()).await.unwrap();
+    assert!(env.account(&env.referrer_address(&dave.pubkey())).await.is_none());
+    let info: ReferrerInfo = env.fetch(&env.referrer_address(&referrer)).await;
+    assert_eq!((info.referred_count, info.stake_earned), (1, 4_000));
+    assert_eq!(env.pool_state().await.referral_stake_owed, 4_000);
+
+    env.claim_referral(&carol).await.unwrap();
+    assert_eq!(env.stake_balance(&referrer).await, 4_000);
+    assert_eq!(env.pool_state().await.referral_stake_owed, 0);
+    assert_eq!(
+        custom_error(env.claim_referral(&carol).await),
+        u32::from(BrewStakingError::NoReferralEarnings)
+    );
+}
+
+#[tokio::test]
+async fn referrers_earn_a_share_of_claimed_rewards() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(1_000_000).await;
+    let carol = env.new_user(0).await;
+    let referrer = carol.pubkey();
+    env.set_referral(1_000, ReferralSource::Rewards).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake_referred(&alice, 1_000_000, &referrer).await.unwrap();
+    env.stake(&bob, 1_000_000).await.unwrap();
+
+    // 120..220: each earns 50_000, of which carol gets 10% of alice's
+    env.warp_to(220);
+    env.claim_reward(&alice).await.unwrap();
+    env.claim_reward(&bob).await.unwrap();
+    assert_eq!(env.reward_balance(&alice.pubkey()).await, 45_000);
+    assert_eq!(env.reward_balance(&bob.pubkey()).await, 50_000);
+
+    // Unstaking pays out the reward with the same cut
+    env.warp_to(320);
+    env.unstake(&alice, 500_000).await.unwrap();
+    assert_eq!(env.reward_balance(&alice.pubkey()).await, 45_000 + 45_000);
+    let info: ReferrerInfo = env.fetch(&env.referrer_address(&referrer)).await;
+    assert_eq!((info.stake_earned, info.reward_earned), (0, 10_000));
+
+    env.claim_referral(&carol).await.unwrap();
+    assert_eq!(env.reward_balance(&referrer).await, 10_000);
+}
+
+#[tokio::test]
+async fn referrers_earn_a_share_of_compounded_rewards() {
+    let mut env = TestEnv::new(PoolArgs { same_mint: true, ..Default::default() }).await;
+    let alice = env.new_user(1_000_000).await;
+    let carol = env.new_user(0).await;
+    let referrer = carol.pubkey();
+    env.set_referral(1_000, ReferralSource::Rewards).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake_referred(&alice, 1_000_000, &referrer).await.unwrap();
+
+    // 120..220: alice earns 100_000 and restakes all but carol's 10%
+    env.warp_to(220);
+    env.compound_reward(&alice).await.unwrap();
+    assert_eq!(env.user_info(&alice.pubkey()).await.staked_amount, 1_090_000);
+    let info: ReferrerInfo = env.fetch(&env.referrer_address(&referrer)).await;
+    assert_eq!((info.stake_earned, info.reward_earned), (0, 10_000));
+    assert_eq!(env.pool_state().await.referral_reward_owed, 10_000);
+
+    env.claim_referral(&carol).await.unwrap();
+    assert_eq!(env.reward_balance(&referrer).await, 10_000);
+}
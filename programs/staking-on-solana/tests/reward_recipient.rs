@@ -0,0 +1,65 @@
+mod common;
+
+use common::{ custom_error, PoolArgs, TestEnv };
+use solana_sdk::signature::Signer;
+use staking_on_solana::error::BrewStakingError;
+
+// Pools reward 1_000 tokens per slot, all of it to alice while she stakes alone.
+
+#[tokio::test]
+async fn rewards_are_paid_to_the_recipient() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(2_000_000).await;
+    let cold = env.new_user(0).await.pubkey();
+    let user = alice.pubkey();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+    env.set_reward_recipient(&alice, 0, Some(cold)).await.unwrap();
+    assert_eq!(env.user_info(&user).await.reward_recipient, Some(cold));
+
+    env.warp_to(220);
+    env.claim_reward(&alice).await.unwrap();
+    assert_eq!(env.reward_balance(&cold).await, 100_000);
+
+    env.warp_to(320);
+    env.stake(&alice, 1_000_000).await.unwrap();
+    assert_eq!(env.reward_balance(&cold).await, 200_000);
+
+    // The unstaked tokens still go back to alice
+    env.warp_to(420);
+    env.unstake(&alice, 1_000_000).await.unwrap();
+    assert_eq!(env.reward_balance(&cold).await, 300_000);
+    assert_eq!(env.reward_balance(&user).await, 0);
+    assert_eq!(env.stake_balance(&user).await, 1_000_000);
+}
+
+#[tokio::test]
+async fn one_payout_goes_to_one_recipient() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let cold = env.new_user(0).await.pubkey();
+    let user = alice.pubkey();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake_position(&alice, 0, 500_000, 0).await.unwrap();
+    env.stake_position(&alice, 1, 500_000, 0).await.unwrap();
+    env.set_reward_recipient(&alice, 1, Some(cold)).await.unwrap();
+
+    env.warp_to(220);
+    assert_eq!(
+        custom_error(env.claim_all(&alice, &[0, 1]).await),
+        u32::from(BrewStakingError::RewardRecipientMismatch)
+    );
+
+    // Naming herself pays alice again
+    env.set_reward_recipient(&alice, 1, Some(user)).await.unwrap();
+    assert_eq!(env.position(&user, 1).await.reward_recipient, None);
+    env.claim_all(&alice, &[0, 1]).await.unwrap();
+    assert_eq!(env.reward_balance(&user).await, 100_000);
+    assert_eq!(env.reward_balance(&cold).await, 0);
+}
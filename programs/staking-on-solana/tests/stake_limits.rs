@@ -0,0 +1,83 @@
+mod common;
+
+use common::{ custom_error, PoolArgs, TestEnv };
+use solana_sdk::signature::Signer;
+use staking_on_solana::error::BrewStakingError;
+
+#[tokio::test]
+async fn stakes_must_fit_the_pool_limits() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(1_000_000).await;
+
+    env.set_stake_limits(1_000, 600_000, 1_000_000).await.unwrap();
+    let config = env.pool_config().await;
+    assert_eq!(
+        (config.min_stake, config.max_stake_per_wallet, config.max_total_staked),
+        (1_000, 600_000, 1_000_000)
+    );
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+
+    assert_eq!(custom_error(env.stake(&alice, 999).await), u32::from(BrewStakingError::StakeBelowMinimum));
+    assert_eq!(
+        custom_error(env.stake(&alice, 600_001).await),
+        u32::from(BrewStakingError::WalletStakeCapExceeded)
+    );
+    env.stake(&alice, 600_000).await.unwrap();
+
+    // Bob fits his own cap but not what is left of the pool's
+    assert_eq!(
+        custom_error(env.stake(&bob, 500_000).await),
+        u32::from(BrewStakingError::PoolStakeCapExceeded)
+    );
+    env.stake(&bob, 400_000).await.unwrap();
+    assert_eq!(env.pool_state().await.total_staked, 1_000_000);
+}
+
+#[tokio::test]
+async fn compounding_respects_the_caps() {
+    let mut env = TestEnv::new(PoolArgs { same_mint: true, ..Default::default() }).await;
+    let alice = env.new_user(1_000_000).await;
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+
+    // The 100_000 reward does not fit under the cap
+    env.set_stake_limits(0, 1_050_000, 0).await.unwrap();
+    env.warp_to(220);
+    assert_eq!(
+        custom_error(env.compound_reward(&alice).await),
+        u32::from(BrewStakingError::WalletStakeCapExceeded)
+    );
+
+    env.set_stake_limits(0, 1_100_000, 0).await.unwrap();
+    env.compound_reward(&alice).await.unwrap();
+    assert_eq!(env.user_info(&alice.pubkey()).await.staked_amount, 1_100_000);
+
+    // A cap below the current stake only blocks new stake
+    env.set_stake_limits(0, 1_000_000, 0).await.unwrap();
+    env.warp_to(230);
+    env.claim_reward(&alice).await.unwrap();
+    env.stake(&alice, 0).await.unwrap();
+}
+
+#[tokio::test]
+async fn only_the_pool_owner_sets_limits() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(0).await;
+
+    assert_eq!(
+        custom_error(env.set_stake_limits(1_000, 500, 0).await),
+        u32::from(BrewStakingError::InvalidStakeLimits)
+    );
+
+    let creator = std::mem::replace(&mut env.creator, alice.insecure_clone());
+    assert!(env.set_stake_limits(1_000, 0, 0).await.is_err());
+    env.creator = creator;
+    env.set_stake_limits(1_000, 0, 0).await.unwrap();
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use common::{ custom_error, MintExtension, PoolArgs, TestEnv };
+use solana_sdk::{ pubkey::Pubkey, signature::Signer };
+use staking_on_solana::error::BrewStakingError;
+
+// Both mints charge a 1% Token-2022 transfer fee, withheld in the receiving
+// account on every transfer into, out of and within the pool.
+
+#[tokio::test]
+async fn transfer_fee_mints_credit_what_the_pool_receives() {
+    let mut env = TestEnv::new(PoolArgs {
+        stake_fee: 100,
+        token_program: spl_token_2022::ID,
+        transfer_fee_bps: 100,
+        ..Default::default()
+    }).await;
+    assert_eq!(env.pool_config().await.token_program, spl_token_2022::ID);
+    assert_eq!(env.pool_state().await.reward_amount, 297_000_000);
+
+    let alice = env.new_user(1_000_000).await;
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+
+    // 990_000 arrives; the 1% stake fee is taken from that
+    env.stake(&alice, 1_000_000).await.unwrap();
+    let admin = env.admin.pubkey();
+    let creator = env.creator.pubkey();
+    assert_eq!(env.user_info(&alice.pubkey()).await.staked_amount, 980_100);
+    assert_eq!(env.pool_state().await.total_staked, 980_100);
+    assert_eq!(env.stake_balance(&admin).await, 980_100);
+    assert_eq!(env.stake_balance(&creator).await, 9_801);
+
+    // The vault pays out exactly what was unstaked, the user is credited net
+    // of the transfer fee
+    env.unstake(&alice, 490_050).await.unwrap();
+    assert_eq!(env.pool_state().await.total_staked, 490_050);
+    assert_eq!(env.stake_balance(&admin).await, 490_050);
+    assert_eq!(env.stake_balance(&alice.pubkey()).await, 485_149);
+}
+
+#[tokio::test]
+async fn transfer_hook_mints_need_a_platform_allowance() {
+    let mut env = TestEnv::new(PoolArgs { token_program: spl_token_2022::ID, ..Default::default() }).await;
+    let hook_mint = env.create_mint_with(6, &[MintExtension::TransferHook(Pubkey::new_unique())]).await;
+    let admin = env.admin.pubkey();
+    env.create_ata(&admin, &hook_mint).await;
+    let reward_mint = env.reward_mint;
+    let args = |pool_id| PoolArgs { pool_id, initial_funding: 0, ..Default::default() };
+
+    assert_eq!(
+        custom_error(env.create_pool(&args("1"), &hook_mint, &reward_mint).await.map(|_| ())),
+        u32::from(BrewStakingError::HookMintNotAllowed)
+    );
+
+    env.allow_hook_mint(&hook_mint, true).await.unwrap();
+    let pool = env.create_pool(&args("1"), &hook_mint, &reward_mint).await.unwrap();
+    assert_eq!(env.fetch::<staking_on_solana::state::PoolConfig>(&pool).await.stake_mint, hook_mint);
+
+    env.allow_hook_mint(&hook_mint, false).await.unwrap();
+    assert_eq!(
+        custom_error(env.create_pool(&args("2"), &hook_mint, &reward_mint).await.map(|_| ())),
+        u32::from(BrewStakingError::HookMintNotAllowed)
+    );
+}
+
+#[tokio::test]
+async fn min_received_bounds_the_transfer_fee_loss() {
+    let mut env = TestEnv::new(PoolArgs {
+        token_program: spl_token_2022::ID,
+        transfer_fee_bps: 100,
+        ..Default::default()
+    }).await;
+    let alice = env.new_user(1_000_000).await;
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+
+    assert_eq!(
+        custom_error(env.stake_with_minimum(&alice, 1_000_000, 990_001).await),
+        u32::from(BrewStakingError::ReceivedBelowMinimum)
+    );
+
+    env.stake_with_minimum(&alice, 1_000_000, 990_000).await.unwrap();
+    assert_eq!(env.user_info(&alice.pubkey()).await.staked_amount, 990_000);
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use common::{ custom_error, PoolArgs, TestEnv };
+use solana_sdk::signature::Signer;
+use staking_on_solana::error::BrewStakingError;
+use staking_on_solana::events::{ Deposit, TicketCancelled };
+use staking_on_solana::state::{ WithdrawalTicket, MAX_UNBONDING_SLOTS };
+
+// Pools reward 1_000 tokens per slot and hold unstaked tokens for 1_000 slots.
+// Alice stakes 1_000_000 next to bob's 1_500_000.
+
+#[tokio::test]
+async fn tickets_stop_earning_and_pay_out_when_due() {
+    let mut env = TestEnv::new(PoolArgs { unstake_fee: 100, ..PoolArgs::default() }).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(1_500_000).await;
+    let user = alice.pubkey();
+    env.set_unbonding_period(1_000).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+    env.stake(&bob, 1_500_000).await.unwrap();
+
+    // 120..220: alice earns 40%, paid out on unstake. The 1% fee is taken
+    // right away and the rest waits in a ticket
+    env.warp_to(220);
+    env.unstake(&alice, 500_000).await.unwrap();
+    let ticket_address = env.ticket_address(&user, 0, 0);
+    let ticket: WithdrawalTicket = env.fetch(&ticket_address).await;
+    assert_eq!((ticket.beneficiary, ticket.position), (user, 0));
+    assert_eq!((ticket.amount, ticket.unlock_slot), (495_000, 1_220));
+    assert_eq!(env.reward_balance(&user).await, 40_000);
+    assert_eq!(env.stake_balance(&user).await, 0);
+    let info = env.user_info(&user).await;
+    assert_eq!((info.ticket_count, info.open_tickets), (1, 1));
+    let state = env.pool_state().await;
+    assert_eq!((state.total_staked, state.total_unbonding), (2_000_000, 495_000));
+
+    // 220..320: the ticket earns nothing, alice's remaining stake 25%
+    env.warp_to(320);
+    env.claim_reward(&alice).await.unwrap();
+    assert_eq!(env.reward_balance(&user).await, 40_000 + 25_000);
+
+    assert_eq!(
+        custom_error(env.withdraw_ticket(&alice, &ticket_address, 0).await),
+        u32::from(BrewStakingError::TicketNotDue)
+    );
+    env.warp_to(1_220);
+    assert_eq!(
+        custom_error(env.withdraw_ticket(&bob, &ticket_address, 0).await),
+        u32::from(BrewStakingError::WrongBeneficiary)
+    );
+    env.withdraw_ticket(&alice, &ticket_address, 0).await.unwrap();
+    assert_eq!(env.stake_balance(&user).await, 495_000);
+    assert!(env.account(&ticket_address).await.is_none());
+    assert_eq!(env.pool_state().await.total_unbonding, 0);
+    assert_eq!(env.user_info(&user).await.open_tickets, 0);
+}
+
+#[tokio::test]
+async fn cancelled_tickets_go_back_into_the_stake() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(1_500_000).await;
+    let user = alice.pubkey();
+    assert_eq!(
+        custom_error(env.set_unbonding_period(MAX_UNBONDING_SLOTS + 1).await),
+        u32::from(BrewStakingError::InvalidUnbondingPeriod)
+    );
+    env.set_unbonding_period(1_000).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+    env.stake(&bob, 1_500_000).await.unwrap();
+
+    // Every unstake opens the position's next ticket
+    env.warp_to(220);
+    env.unstake(&alice, 250_000).await.unwrap();
+    env.unstake(&alice, 250_000).await.unwrap();
+    let first = env.ticket_address(&user, 0, 0);
+    let second = env.ticket_address(&user, 0, 1);
+    assert_eq!(env.fetch::<WithdrawalTicket>(&second).await.amount, 250_000);
+    assert_eq!(env.pool_state().await.total_unbonding, 500_000);
+
+    // 220..320 at 500_000 against 1_500_000: alice earns 25%, kept when
+    // the first ticket is restaked
+    env.warp_to(320);
+    assert_eq!(env.events::<Deposit>().len(), 2);
+    env.cancel_ticket(&alice, &first, 0).await.unwrap();
+    assert!(env.account(&first).await.is_none());
+
+    // The restake is reported as a cancellation, not as a new deposit
+    let cancelled = env.events::<TicketCancelled>();
+    assert_eq!(cancelled.len(), 1);
+    assert_eq!((cancelled[0].ticket, cancelled[0].amount, cancelled[0].staked_amount), (first, 250_000, 750_000));
+    assert_eq!(env.events::<Deposit>().len(), 2);
+    let info = env.user_info(&user).await;
+    assert_eq!((info.staked_amount, info.reward_credit), (750_000, 25_000));
+    let state = env.pool_state().await;
+    assert_eq!((state.total_staked, state.total_unbonding), (2_250_000, 250_000));
+
+    // 320..410 at 750_000 against 1_500_000: a third
+    env.warp_to(410);
+    env.claim_reward(&alice).await.unwrap();
+    assert_eq!(env.reward_balance(&user).await, 40_000 + 25_000 + 30_000);
+}
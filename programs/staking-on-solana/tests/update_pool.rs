@@ -0,0 +1,59 @@
+mod common;
+
+use common::{ custom_error, PoolArgs, TestEnv };
+use solana_sdk::signature::Signer;
+use staking_on_solana::error::BrewStakingError;
+
+// Pools reward 1_000 tokens per slot, all of it to alice while she stakes alone.
+
+#[tokio::test]
+async fn anyone_can_update_an_idle_pool() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+
+    env.warp_to(220);
+    assert_eq!(env.pool_state().await.should_total_paid, 0);
+    env.update_pool().await.unwrap();
+    let state = env.pool_state().await;
+    assert_eq!((state.last_reward_slot, state.should_total_paid, state.total_earned), (220, 100_000, 100_000));
+    assert_eq!(state.acc_token_per_share, 100);
+
+    // Updating changes nothing the stakers are owed
+    env.claim_reward(&alice).await.unwrap();
+    assert_eq!(env.reward_balance(&alice.pubkey()).await, 100_000);
+}
+
+#[tokio::test]
+async fn update_pools_updates_every_pool() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let args = PoolArgs { pool_id: "2", initial_funding: 0, ..PoolArgs::default() };
+    let (stake_mint, reward_mint) = (env.stake_mint, env.reward_mint);
+    let first = (env.pool, env.pool_config().await.state_addr);
+    env.pool = env.create_pool(&args, &stake_mint, &reward_mint).await.unwrap();
+    let second = (env.pool, env.pool_config().await.state_addr);
+    let alice = env.new_user(1_000_000).await;
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.pool = first.0;
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+
+    env.warp_to(220);
+    assert_eq!(
+        custom_error(env.update_pools(&[(first.0, second.1)]).await),
+        u32::from(BrewStakingError::InvalidPoolAccounts)
+    );
+    env.update_pools(&[first, second]).await.unwrap();
+    assert_eq!(env.pool_state().await.should_total_paid, 100_000);
+    // Nobody staked in the second pool, so it only moves its last reward slot
+    env.pool = second.0;
+    let state = env.pool_state().await;
+    assert_eq!((state.last_reward_slot, state.should_total_paid), (220, 0));
+}
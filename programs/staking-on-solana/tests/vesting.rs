@@ -0,0 +1,84 @@
+mod common;
+
+use common::{ custom_error, PoolArgs, TestEnv };
+use solana_sdk::signature::Signer;
+use staking_on_solana::error::BrewStakingError;
+
+// Pools reward 1_000 tokens per slot, vested over 1_000 slots. Alice and bob
+// stake 1_000_000 each, so each earns 500 per slot.
+
+#[tokio::test]
+async fn claims_vest_linearly() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(1_000_000).await;
+    let user = alice.pubkey();
+    assert_eq!(
+        custom_error(env.set_vesting(0, true).await),
+        u32::from(BrewStakingError::InvalidVestingPeriod)
+    );
+    env.set_vesting(1_000, false).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+    env.stake(&bob, 1_000_000).await.unwrap();
+
+    // The claim starts vesting instead of paying out
+    env.warp_to(220);
+    env.claim_reward(&alice).await.unwrap();
+    let info = env.user_info(&user).await;
+    assert_eq!((info.vesting_amount, info.vesting_end_slot), (50_000, 1_220));
+    assert_eq!(env.reward_balance(&user).await, 0);
+    assert_eq!(
+        custom_error(env.compound_reward(&bob).await),
+        u32::from(BrewStakingError::VestingPoolCompound)
+    );
+
+    // Half way through half of it can be withdrawn
+    env.warp_to(720);
+    env.withdraw_vested(&alice, 0).await.unwrap();
+    assert_eq!(env.reward_balance(&user).await, 25_000);
+    assert_eq!(env.user_info(&user).await.vesting_amount, 25_000);
+    assert_eq!(env.pool_state().await.total_vesting, 25_000);
+    assert_eq!(
+        custom_error(env.withdraw_vested(&alice, 0).await),
+        u32::from(BrewStakingError::NothingVested)
+    );
+
+    env.warp_to(1_220);
+    env.withdraw_vested(&alice, 0).await.unwrap();
+    assert_eq!(env.reward_balance(&user).await, 50_000);
+    let info = env.user_info(&user).await;
+    assert_eq!((info.vesting_amount, info.vested_amount), (0, 0));
+    assert_eq!(env.pool_state().await.total_vesting, 0);
+}
+
+#[tokio::test]
+async fn unstaking_forfeits_unvested_rewards_to_other_stakers() {
+    let mut env = TestEnv::new(PoolArgs::default()).await;
+    let alice = env.new_user(1_000_000).await;
+    let bob = env.new_user(1_000_000).await;
+    let user = alice.pubkey();
+    env.set_vesting(1_000, true).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+    env.stake(&bob, 1_000_000).await.unwrap();
+
+    // Alice's 250_000 starts vesting as she unstakes half her stake, which
+    // gives up half of it to bob
+    env.warp_to(620);
+    env.unstake(&alice, 500_000).await.unwrap();
+    let info = env.user_info(&user).await;
+    assert_eq!((info.vesting_amount, info.vesting_end_slot), (125_000, 1_620));
+    assert_eq!(env.stake_balance(&user).await, 500_000);
+
+    env.claim_reward(&bob).await.unwrap();
+    assert_eq!(env.user_info(&bob.pubkey()).await.vesting_amount, 250_000 + 125_000);
+    let state = env.pool_state().await;
+    assert_eq!((state.paid_rewards, state.total_earned), (500_000, 0));
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::{ PoolArgs, TestEnv, PERFORMANCE_FEE };
+use solana_sdk::signature::Signer;
+use staking_on_solana::state::{ SLOTS_PER_DAY, SLOTS_PER_WEEK };
+
+// Pools reward 1_000 tokens per slot for one day, and start 10 slots after
+// `start_reward`.
+
+#[tokio::test]
+async fn pool_info_reports_runway_and_solvency() {
+    let mut env = TestEnv::new(PoolArgs { initial_funding: 100_000_000, ..PoolArgs::default() }).await;
+    let alice = env.new_user(1_000_000).await;
+
+    // The funding covers 100_000 of the 216_000 slots
+    let info = env.pool_info().await.unwrap();
+    assert_eq!((info.reward_rate, info.remaining_slots, info.runway_slots), (0, SLOTS_PER_DAY, 100_000));
+    assert_eq!(info.insufficient_rewards, 116_000_000);
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake(&alice, 1_000_000).await.unwrap();
+
+    // Nothing was earned in the 10 slots before alice staked
+    env.warp_to(220);
+    let info = env.pool_info().await.unwrap();
+    assert_eq!((info.tvl, info.reward_rate, info.acc_token_per_share), (1_000_000, 1_000, 100));
+    assert_eq!((info.remaining_slots, info.runway_slots), (110 + SLOTS_PER_DAY - 220, 99_900));
+    assert_eq!(info.insufficient_rewards, 115_990_000);
+    assert_eq!(info.slot, 220);
+    // The view doesn't write the update back
+    assert_eq!(env.pool_state().await.last_reward_slot, 120);
+}
+
+#[tokio::test]
+async fn user_position_reports_what_unstaking_would_charge() {
+    let mut env = TestEnv::new(PoolArgs { unstake_fee: 200, ..PoolArgs::default() }).await;
+    let alice = env.new_user(1_000_000).await;
+    let user = alice.pubkey();
+    env.set_lock_multiplier(20_000).await.unwrap();
+
+    env.warp_to(100);
+    env.start_reward().await.unwrap();
+    env.warp_to(120);
+    env.stake_locked(&alice, 1_000_000, 13).await.unwrap();
+
+    env.warp_to(220);
+    let position = env.user_position(&user, 0).await.unwrap();
+    assert_eq!((position.staked_amount, position.boosted_amount), (1_000_000, 1_250_000));
+    assert_eq!(position.pending_reward, 100_000);
+    assert!(position.locked);
+    assert_eq!(position.lock_end_slot, 120 + 13 * SLOTS_PER_WEEK);
+    assert_eq!((position.unstake_fee, position.performance_fee), (20_000, PERFORMANCE_FEE));
+    assert_eq!((position.reward_fee, position.referral_fee), (0, 0));
+
+    // Fee discounts count
+    env.set_pool_fee_exemption(&user, 2_500).await.unwrap();
+    assert_eq!(env.user_position(&user, 0).await.unwrap().unstake_fee, 15_000);
+
+    // Positions that don't exist can't be viewed
+    assert!(env.user_position(&user, 1).await.is_err());
+}
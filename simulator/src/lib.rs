@@ -0,0 +1,494 @@
+//! Deterministic off-chain replay of staking-on-solana pool accounting.
+//!
+//! The simulator drives the program's own `utils` bookkeeping (the same
+//! functions the on-chain handlers call) over plain `PoolConfig`, `PoolState`
+//! and `UserInfo` structs, and mirrors the token transfers each handler makes
+//! so pool solvency can be followed slot by slot.
+
+use std::collections::BTreeMap;
+
+use anchor_lang::prelude::{ ProgramError, Pubkey };
+use anchor_lang::Result;
+use serde::{ Deserialize, Serialize };
+use staking_on_solana::state::{
+    PerformanceFeeModel,
+    PoolConfig,
+    PoolState,
+    ReferralSource,
+    UserInfo,
+    SLOTS_PER_DAY,
+};
+use staking_on_solana::utils::{ self, accounting };
+
+const STAKE_MINT: Pubkey = Pubkey::new_from_array([1; 32]);
+const REWARD_MINT: Pubkey = Pubkey::new_from_array([2; 32]);
+
+/// Parameters a pool would be created with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolParams {
+    pub reward_per_slot: u64,
+    /// Reward period in days
+    pub duration: u16,
+    /// Stake fee in basis points
+    #[serde(default)]
+    pub stake_fee: u16,
+    /// Unstake fee in basis points
+    #[serde(default)]
+    pub unstake_fee: u16,
+    pub stake_mint_decimals: u8,
+    pub reward_mint_decimals: u8,
+    /// Stake and reward token are the same mint (and share one vault)
+    #[serde(default)]
+    pub same_mint: bool,
+    pub initial_funding: u64,
+}
+
+/// Stake tokens received per reward token when compounding swaps mints.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SwapRate {
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+impl Default for SwapRate {
+    fn default() -> Self {
+        SwapRate { numerator: 1, denominator: 1 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    Start,
+    Stop,
+    Fund {
+        amount: u64,
+    },
+    Stake {
+        user: String,
+        amount: u64,
+    },
+    Unstake {
+        user: String,
+        amount: u64,
+    },
+    Claim {
+        user: String,
+    },
+    Compound {
+        user: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+    pub slot: u64,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    pub pool: PoolParams,
+    #[serde(default)]
+    pub swap_rate: SwapRate,
+    /// Steps must be ordered by slot
+    pub steps: Vec<Step>,
+    /// Also snapshot the pool every this many slots between steps
+    #[serde(default)]
+    pub sample_interval: Option<u64>,
+}
+
+/// Running totals for one staker.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserReport {
+    pub staked_amount: u64,
+    pub deposited: u64,
+    pub withdrawn: u64,
+    pub rewards_claimed: u64,
+    pub rewards_compounded: u64,
+    pub fees_paid: u64,
+    /// Claimable reward at the last replayed slot
+    pub pending_reward: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub slot: u64,
+    pub total_staked: u64,
+    pub acc_token_per_share: u64,
+    pub reward_amount: u64,
+    pub should_total_paid: u64,
+    pub paid_rewards: u64,
+    /// Actual reward vault balance
+    pub reward_vault: u64,
+    /// Actual stake vault balance (equal to `reward_vault` for same-mint pools)
+    pub stake_vault: u64,
+    /// Rewards accrued to stakers but not yet paid
+    pub owed_rewards: u64,
+    /// Reward tokens still missing to cover the full reward period
+    pub insufficient_rewards: u64,
+    /// The vaults hold every staked token plus every owed reward
+    pub solvent: bool,
+    /// Annualised reward rate at this slot, assuming equal token prices
+    pub apr: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Outcome {
+    pub slot: u64,
+    pub action: Action,
+    /// Program error message if the instruction would have failed
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub users: BTreeMap<String, UserReport>,
+    pub timeline: Vec<Snapshot>,
+    pub outcomes: Vec<Outcome>,
+    pub creator_fees: u64,
+    pub refunded_rewards: u64,
+}
+
+/// A single pool and its stakers.
+#[derive(Clone)]
+pub struct Simulator {
+    pub pool_config: PoolConfig,
+    pub pool_state: PoolState,
+    pub users: BTreeMap<String, UserInfo>,
+    pub ledgers: BTreeMap<String, UserReport>,
+    pub swap_rate: SwapRate,
+    pub reward_vault: u64,
+    stake_vault: u64,
+    pub creator_fees: u64,
+    pub refunded_rewards: u64,
+}
+
+impl Simulator {
+    pub fn new(params: &PoolParams, swap_rate: SwapRate) -> Self {
+        let pool_config = PoolConfig {
+            owner: Pubkey::default(),
+            pool_id: String::new(),
+            duration: params.duration,
+            start_slot: 0,
+            end_slot: 0,
+            reward_per_slot: params.reward_per_slot,
+            stake_fee: params.stake_fee,
+            unstake_fee: params.unstake_fee,
+            stake_mint: if params.same_mint { REWARD_MINT } else { STAKE_MINT },
+            reward_mint: REWARD_MINT,
+            stake_mint_decimals: params.stake_mint_decimals,
+            reward_mint_decimals: params.reward_mint_decimals,
+            pool_stake_token_vault: Pubkey::default(),
+            pool_reward_token_vault: Pubkey::default(),
+            state_addr: Pubkey::default(),
+            token_program: Pubkey::default(),
+            creator_fees: 0,
+            treasury_fees: 0,
+            performance_fee_model: PerformanceFeeModel::Flat,
+            min_stake: 0,
+            max_stake_per_wallet: 0,
+            max_total_staked: 0,
+            allowlist_root: None,
+            boost_collection: None,
+            boost_multiplier: 0,
+            lock_multiplier: 0,
+            unbonding_slots: 0,
+            vesting_slots: 0,
+            forfeit_unvested: false,
+            referral_share: 0,
+            referral_source: ReferralSource::StakeFee,
+        };
+        let pool_state = PoolState {
+            total_staked: 0,
+            last_reward_slot: 0,
+            acc_token_per_share: 0,
+            reward_amount: params.initial_funding,
+            should_total_paid: 0,
+            paid_rewards: 0,
+            total_earned: 0,
+            total_boosted: 0,
+            total_unbonding: 0,
+            total_vesting: 0,
+            referral_stake_owed: 0,
+            referral_reward_owed: 0,
+            open_boosts: 0,
+        };
+
+        Simulator {
+            pool_config,
+            pool_state,
+            users: BTreeMap::new(),
+            ledgers: BTreeMap::new(),
+            swap_rate,
+            reward_vault: params.initial_funding,
+            stake_vault: 0,
+            creator_fees: 0,
+            refunded_rewards: 0,
+        }
+    }
+
+    fn same_mint(&self) -> bool {
+        self.pool_config.stake_mint == self.pool_config.reward_mint
+    }
+
+    pub fn stake_vault(&self) -> u64 {
+        if self.same_mint() { self.reward_vault } else { self.stake_vault }
+    }
+
+    fn stake_vault_mut(&mut self) -> &mut u64 {
+        if self.same_mint() { &mut self.reward_vault } else { &mut self.stake_vault }
+    }
+
+    /// Runs `action` at `slot` the way the matching instruction would. State is
+    /// left untouched when the instruction fails.
+    pub fn apply(&mut self, slot: u64, action: &Action) -> Result<()> {
+        let before = self.clone();
+        let result = self.execute(slot, action);
+        if result.is_err() {
+            *self = before;
+        }
+        result
+    }
+
+    fn execute(&mut self, slot: u64, action: &Action) -> Result<()> {
+        let mut pool_config = self.pool_config.clone();
+        let mut pool_state = self.pool_state.clone();
+
+        match action {
+            Action::Start => {
+                accounting::start_reward(&mut pool_config, &mut pool_state, slot)?;
+            }
+            Action::Stop => {
+                let refund = accounting::stop_reward(&mut pool_config, &mut pool_state, slot)?;
+                debit(&mut self.reward_vault, refund)?;
+                self.refunded_rewards = self.refunded_rewards.saturating_add(refund);
+            }
+            Action::Fund { amount } => {
+                accounting::fund_pool(&mut pool_state, *amount)?;
+                credit(&mut self.reward_vault, *amount)?;
+            }
+            Action::Stake { user, amount } => {
+                let mut user_info = self.user_info(user);
+                let amounts = accounting::stake(
+                    &pool_config,
+                    &mut pool_state,
+                    &mut user_info,
+                    *amount,
+                    0,
+                    0,
+                    slot
+                )?;
+                debit(&mut self.reward_vault, amounts.reward)?;
+                credit(self.stake_vault_mut(), amounts.staked)?;
+                self.creator_fees = self.creator_fees.saturating_add(amounts.fee);
+
+                let ledger = self.ledgers.entry(user.clone()).or_default();
+                ledger.deposited = ledger.deposited.saturating_add(*amount);
+                ledger.fees_paid = ledger.fees_paid.saturating_add(amounts.fee);
+                ledger.rewards_claimed = ledger.rewards_claimed.saturating_add(amounts.reward);
+                self.users.insert(user.clone(), user_info);
+            }
+            Action::Unstake { user, amount } => {
+                let mut user_info = self.user_info(user);
+                let amounts = accounting::unstake(
+                    &pool_config,
+                    &mut pool_state,
+                    &mut user_info,
+                    *amount,
+                    0,
+                    slot
+                )?;
+                debit(&mut self.reward_vault, amounts.reward)?;
+                debit(self.stake_vault_mut(), amounts.fee + amounts.withdrawn)?;
+                self.creator_fees = self.creator_fees.saturating_add(amounts.fee);
+
+                let ledger = self.ledgers.entry(user.clone()).or_default();
+                ledger.withdrawn = ledger.withdrawn.saturating_add(amounts.withdrawn);
+                ledger.fees_paid = ledger.fees_paid.saturating_add(amounts.fee);
+                ledger.rewards_claimed = ledger.rewards_claimed.saturating_add(amounts.reward);
+                self.users.insert(user.clone(), user_info);
+            }
+            Action::Claim { user } => {
+                let mut user_info = self.user_info(user);
+                let reward = accounting::claim_reward(
+                    &pool_config,
+                    &mut pool_state,
+                    &mut user_info,
+                    slot
+                )?.reward;
+                debit(&mut self.reward_vault, reward)?;
+
+                let ledger = self.ledgers.entry(user.clone()).or_default();
+                ledger.rewards_claimed = ledger.rewards_claimed.saturating_add(reward);
+                self.users.insert(user.clone(), user_info);
+            }
+            Action::Compound { user } => {
+                let mut user_info = self.user_info(user);
+                let mut pending = accounting::harvest_for_compound(
+                    &pool_config,
+                    &mut pool_state,
+                    &mut user_info,
+                    slot
+                )?;
+                if pending > 0 && !self.same_mint() {
+                    // The swap moves reward tokens out of the reward vault
+                    // and pays stake tokens into the stake vault.
+                    debit(&mut self.reward_vault, pending)?;
+                    pending = utils::mul_div(
+                        pending,
+                        self.swap_rate.numerator,
+                        self.swap_rate.denominator
+                    )?;
+                    credit(&mut self.stake_vault, pending)?;
+                }
+                accounting::restake_reward(&pool_config, &mut pool_state, &mut user_info, pending)?;
+
+                let ledger = self.ledgers.entry(user.clone()).or_default();
+                ledger.rewards_compounded = ledger.rewards_compounded.saturating_add(pending);
+                self.users.insert(user.clone(), user_info);
+            }
+        }
+
+        self.pool_config = pool_config;
+        self.pool_state = pool_state;
+        Ok(())
+    }
+
+    fn user_info(&self, user: &str) -> UserInfo {
+        self.users.get(user).cloned().unwrap_or(UserInfo {
+            staked_amount: 0,
+            reward_debt: 0,
+            deposit_slot: 0,
+            boosted_amount: 0,
+            boost_multiplier: 0,
+            boost_mint: None,
+            reward_credit: 0,
+            lock_end_slot: 0,
+            lock_multiplier: 0,
+            ticket_count: 0,
+            open_tickets: 0,
+            vesting_amount: 0,
+            vested_amount: 0,
+            vesting_start_slot: 0,
+            vesting_end_slot: 0,
+            referrer: None,
+            reward_recipient: None,
+            allocation: None,
+        })
+    }
+
+    pub fn pending_reward(&self, user: &str, slot: u64) -> u64 {
+        accounting
+            ::pending_reward_at(&self.pool_config, &self.pool_state, &self.user_info(user), slot)
+            .unwrap_or(0)
+    }
+
+    /// Rewards accrued up to `slot` that have not been paid out yet.
+    pub fn owed_rewards(&self, slot: u64) -> u64 {
+        self.users
+            .keys()
+            .map(|user| self.pending_reward(user, slot))
+            .fold(0, u64::saturating_add)
+    }
+
+    pub fn apr(&self, slot: u64) -> f64 {
+        let pool_config = &self.pool_config;
+        if
+            self.pool_state.total_staked == 0 ||
+            pool_config.start_slot == 0 ||
+            slot < pool_config.start_slot ||
+            slot >= pool_config.end_slot
+        {
+            return 0.0;
+        }
+        let yearly_rewards =
+            ((pool_config.reward_per_slot as f64) * ((SLOTS_PER_DAY * 365) as f64)) /
+            (10f64).powi(pool_config.reward_mint_decimals as i32);
+        let staked =
+            (self.pool_state.total_staked as f64) /
+            (10f64).powi(pool_config.stake_mint_decimals as i32);
+        yearly_rewards / staked
+    }
+
+    pub fn snapshot(&self, slot: u64) -> Snapshot {
+        let owed_rewards = self.owed_rewards(slot);
+        let solvent = if self.same_mint() {
+            self.reward_vault >= self.pool_state.total_staked.saturating_add(owed_rewards)
+        } else {
+            self.stake_vault >= self.pool_state.total_staked && self.reward_vault >= owed_rewards
+        };
+
+        Snapshot {
+            slot,
+            total_staked: self.pool_state.total_staked,
+            acc_token_per_share: self.pool_state.acc_token_per_share,
+            reward_amount: self.pool_state.reward_amount,
+            should_total_paid: self.pool_state.should_total_paid,
+            paid_rewards: self.pool_state.paid_rewards,
+            reward_vault: self.reward_vault,
+            stake_vault: self.stake_vault(),
+            owed_rewards,
+            insufficient_rewards: utils::insufficient_rewards(&self.pool_config, &self.pool_state),
+            solvent,
+            apr: self.apr(slot),
+        }
+    }
+
+    pub fn report(&self, slot: u64) -> BTreeMap<String, UserReport> {
+        self.ledgers
+            .iter()
+            .map(|(user, ledger)| {
+                let mut ledger = ledger.clone();
+                ledger.staked_amount = self.user_info(user).staked_amount;
+                ledger.pending_reward = self.pending_reward(user, slot);
+                (user.clone(), ledger)
+            })
+            .collect()
+    }
+}
+
+// Token transfers fail like the token program would.
+fn debit(vault: &mut u64, amount: u64) -> Result<()> {
+    *vault = vault.checked_sub(amount).ok_or(ProgramError::InsufficientFunds)?;
+    Ok(())
+}
+
+fn credit(vault: &mut u64, amount: u64) -> Result<()> {
+    *vault = vault.checked_add(amount).ok_or(ProgramError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+/// Replays every step of `scenario` and reports payouts and pool solvency.
+pub fn replay(scenario: &Scenario) -> Report {
+    let mut simulator = Simulator::new(&scenario.pool, scenario.swap_rate);
+    let mut timeline = Vec::new();
+    let mut outcomes = Vec::new();
+    let mut last_slot = 0;
+
+    for step in &scenario.steps {
+        if let Some(interval) = scenario.sample_interval.filter(|interval| *interval > 0) {
+            let mut slot = last_slot + interval;
+            while slot < step.slot {
+                timeline.push(simulator.snapshot(slot));
+                slot += interval;
+            }
+        }
+
+        let error = simulator
+            .apply(step.slot, &step.action)
+            .err()
+            .map(|err| err.to_string());
+        outcomes.push(Outcome { slot: step.slot, action: step.action.clone(), error });
+        timeline.push(simulator.snapshot(step.slot));
+        last_slot = step.slot;
+    }
+
+    Report {
+        users: simulator.report(last_slot),
+        timeline,
+        outcomes,
+        creator_fees: simulator.creator_fees,
+        refunded_rewards: simulator.refunded_rewards,
+    }
+}
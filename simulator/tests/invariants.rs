@@ -0,0 +1,172 @@
+//! Stateful fuzzing of the pool accounting.
+//!
+//! Random pools are driven through random action sequences on the simulator,
+//! which runs the program's own accounting, and the pool bookkeeping is
+//! checked against the vault balances after every step. Failed actions are
+//! expected (and rolled back); panics are not.
+//!
+//! Set `PROPTEST_CASES` to run more sequences than the default.
+
+use brew_staking_sim::{ Action, PoolParams, Simulator, SwapRate };
+use proptest::prelude::*;
+use proptest_state_machine::{ prop_state_machine, ReferenceStateMachine, StateMachineTest };
+use staking_on_solana::state::{ MAX_FEE, SLOTS_PER_DAY };
+
+const USERS: [&str; 3] = ["alice", "bob", "carol"];
+
+#[derive(Debug, Clone)]
+struct PoolSetup {
+    params: PoolParams,
+    swap_rate: SwapRate,
+    slot: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    /// Slots to let pass before the action
+    wait: u64,
+    action: Action,
+}
+
+struct PoolModel;
+
+// Pools are funded for their whole reward period up front, so every reward
+// they accrue must stay covered by the reward vault.
+fn pool_setup() -> impl Strategy<Value = PoolSetup> {
+    (
+        prop_oneof![1u64..1_000, 1u64..1_000_000_000_000],
+        1u16..=30,
+        0..=MAX_FEE,
+        0..=MAX_FEE,
+        0u8..=12,
+        0u8..=9,
+        any::<bool>(),
+        0u64..1_000_000,
+        (1u64..=4, 1u64..=4),
+    ).prop_map(
+        |(reward_per_slot, duration, stake_fee, unstake_fee, stake_decimals, reward_decimals, same_mint, surplus, (numerator, denominator))| {
+            PoolSetup {
+                params: PoolParams {
+                    reward_per_slot,
+                    duration,
+                    stake_fee,
+                    unstake_fee,
+                    stake_mint_decimals: stake_decimals,
+                    reward_mint_decimals: reward_decimals,
+                    same_mint,
+                    initial_funding: reward_per_slot * (duration as u64) * SLOTS_PER_DAY +
+                    surplus,
+                },
+                swap_rate: SwapRate { numerator, denominator },
+                slot: 0,
+            }
+        }
+    )
+}
+
+fn amount() -> impl Strategy<Value = u64> {
+    prop_oneof![
+        4 => 1u64..1_000_000,
+        2 => 1u64..1_000_000_000_000_000,
+        1 => Just(0u64),
+        1 => any::<u64>()
+    ]
+}
+
+fn user() -> impl Strategy<Value = String> {
+    prop::sample::select(USERS.to_vec()).prop_map(str::to_string)
+}
+
+impl ReferenceStateMachine for PoolModel {
+    type State = PoolSetup;
+    type Transition = Step;
+
+    fn init_state() -> BoxedStrategy<Self::State> {
+        pool_setup().boxed()
+    }
+
+    fn transitions(_state: &Self::State) -> BoxedStrategy<Self::Transition> {
+        let wait = prop_oneof![
+            2 => Just(0u64),
+            4 => 1u64..100,
+            2 => 100u64..10_000,
+            1 => 10_000u64..(2 * SLOTS_PER_DAY)
+        ];
+        let action = prop_oneof![
+            1 => Just(Action::Start),
+            1 => Just(Action::Stop),
+            1 => amount().prop_map(|amount| Action::Fund { amount }),
+            4 => (user(), amount()).prop_map(|(user, amount)| Action::Stake { user, amount }),
+            3 => (user(), amount()).prop_map(|(user, amount)| Action::Unstake { user, amount }),
+            3 => user().prop_map(|user| Action::Claim { user }),
+            2 => user().prop_map(|user| Action::Compound { user })
+        ];
+        (wait, action).prop_map(|(wait, action)| Step { wait, action }).boxed()
+    }
+
+    fn apply(mut state: Self::State, step: &Self::Transition) -> Self::State {
+        state.slot += step.wait;
+        state
+    }
+}
+
+struct PoolAccounting;
+
+impl StateMachineTest for PoolAccounting {
+    type SystemUnderTest = Simulator;
+    type Reference = PoolModel;
+
+    fn init_test(setup: &PoolSetup) -> Simulator {
+        Simulator::new(&setup.params, setup.swap_rate)
+    }
+
+    fn apply(mut simulator: Simulator, setup: &PoolSetup, step: Step) -> Simulator {
+        let _ = simulator.apply(setup.slot, &step.action);
+        simulator
+    }
+
+    fn check_invariants(simulator: &Simulator, setup: &PoolSetup) {
+        let state = &simulator.pool_state;
+
+        let staked: u128 = simulator.users
+            .values()
+            .map(|user| user.staked_amount as u128)
+            .sum();
+        assert_eq!(staked, state.total_staked as u128, "user stakes don't add up to total_staked");
+
+        assert!(state.paid_rewards <= state.should_total_paid, "paid more than accrued");
+        assert_eq!(
+            (state.total_earned as u128) + (state.paid_rewards as u128),
+            state.should_total_paid as u128,
+            "total_earned drifted from should_total_paid - paid_rewards"
+        );
+
+        // The bookkeeping matches the tokens actually held
+        if setup.params.same_mint {
+            assert_eq!(
+                simulator.reward_vault as u128,
+                (state.reward_amount as u128) + (state.total_staked as u128)
+            );
+        } else {
+            assert_eq!(simulator.reward_vault, state.reward_amount);
+            assert_eq!(simulator.stake_vault(), state.total_staked);
+        }
+
+        let snapshot = simulator.snapshot(setup.slot);
+        assert!(
+            snapshot.solvent,
+            "vaults can't cover stake plus owed rewards: {:?}",
+            snapshot
+        );
+    }
+}
+
+prop_state_machine! {
+    #![proptest_config(ProptestConfig {
+        max_shrink_iters: 4096,
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn pool_accounting_invariants(sequential 1..80 => PoolAccounting);
+}
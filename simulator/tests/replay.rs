@@ -0,0 +1,151 @@
+use brew_staking_sim::{ replay, Action, PoolParams, Scenario, Step };
+
+fn pool(initial_funding: u64) -> PoolParams {
+    PoolParams {
+        reward_per_slot: 100,
+        duration: 1,
+        stake_fee: 0,
+        unstake_fee: 0,
+        stake_mint_decimals: 6,
+        reward_mint_decimals: 6,
+        same_mint: false,
+        initial_funding,
+    }
+}
+
+fn step(slot: u64, action: Action) -> Step {
+    Step { slot, action }
+}
+
+fn stake(user: &str, amount: u64) -> Action {
+    Action::Stake { user: user.to_string(), amount }
+}
+
+fn claim(user: &str) -> Action {
+    Action::Claim { user: user.to_string() }
+}
+
+#[test]
+fn stakers_split_rewards_by_share() {
+    // Start at slot 0 opens the reward period at slot 10.
+    let scenario = Scenario {
+        pool: pool(1_000_000),
+        swap_rate: Default::default(),
+        steps: vec![
+            step(0, Action::Start),
+            step(20, stake("alice", 1_000_000)),
+            step(120, claim("alice")),
+            step(120, stake("bob", 1_000_000)),
+            step(220, claim("alice")),
+            step(220, claim("bob"))
+        ],
+        sample_interval: None,
+    };
+
+    let report = replay(&scenario);
+
+    assert!(report.outcomes.iter().all(|outcome| outcome.error.is_none()));
+    assert_eq!(report.users["alice"].rewards_claimed, 10_000 + 5_000);
+    assert_eq!(report.users["bob"].rewards_claimed, 5_000);
+
+    let last = report.timeline.last().unwrap();
+    assert_eq!(last.should_total_paid, 20_000);
+    assert_eq!(last.paid_rewards, 20_000);
+    assert_eq!(last.reward_vault, 1_000_000 - 20_000);
+    assert_eq!(last.stake_vault, 2_000_000);
+    assert!(last.solvent);
+}
+
+#[test]
+fn fees_go_to_the_pool_owner() {
+    let mut params = pool(1_000_000);
+    params.stake_fee = 100;
+    params.unstake_fee = 200;
+    let scenario = Scenario {
+        pool: params,
+        swap_rate: Default::default(),
+        steps: vec![
+            step(0, Action::Start),
+            step(20, stake("alice", 1_000_000)),
+            step(20, Action::Unstake { user: "alice".to_string(), amount: 500_000 })
+        ],
+        sample_interval: None,
+    };
+
+    let report = replay(&scenario);
+    let alice = &report.users["alice"];
+
+    assert_eq!(alice.staked_amount, 990_000 - 500_000);
+    assert_eq!(alice.withdrawn, 490_000);
+    assert_eq!(alice.fees_paid, 10_000 + 10_000);
+    assert_eq!(report.creator_fees, 20_000);
+}
+
+#[test]
+fn failed_actions_leave_the_pool_untouched() {
+    let scenario = Scenario {
+        pool: pool(5_000),
+        swap_rate: Default::default(),
+        steps: vec![
+            step(0, stake("alice", 1_000_000)),
+            step(0, Action::Start),
+            step(20, stake("alice", 1_000_000)),
+            step(120, claim("alice"))
+        ],
+        sample_interval: Some(50),
+    };
+
+    let report = replay(&scenario);
+
+    // Staking before the pool starts and claiming past the funding both fail.
+    assert!(report.outcomes[0].error.as_deref().unwrap().contains("PoolNotStarted"));
+    assert!(report.outcomes[3].error.as_deref().unwrap().contains("InsufficientReward"));
+    assert_eq!(report.users["alice"].rewards_claimed, 0);
+    assert_eq!(report.users["alice"].pending_reward, 10_000);
+
+    let slots: Vec<u64> = report.timeline
+        .iter()
+        .map(|snapshot| snapshot.slot)
+        .collect();
+    assert_eq!(slots, vec![0, 0, 20, 70, 120]);
+
+    let last = report.timeline.last().unwrap();
+    assert_eq!(last.owed_rewards, 10_000);
+    assert_eq!(last.paid_rewards, 0);
+    assert!(!last.solvent);
+    assert!(last.insufficient_rewards > 0);
+}
+
+#[test]
+fn scenarios_load_from_json() {
+    let scenario: Scenario = serde_json
+        ::from_str(
+            r#"{
+                "pool": {
+                    "reward_per_slot": 100,
+                    "duration": 1,
+                    "stake_mint_decimals": 6,
+                    "reward_mint_decimals": 6,
+                    "same_mint": true,
+                    "initial_funding": 2000000
+                },
+                "steps": [
+                    { "slot": 0, "action": { "type": "start" } },
+                    { "slot": 20, "action": { "type": "stake", "user": "alice", "amount": 1000000 } },
+                    { "slot": 120, "action": { "type": "compound", "user": "alice" } }
+                ]
+            }"#
+        )
+        .unwrap();
+
+    let report = replay(&scenario);
+
+    assert_eq!(report.users["alice"].rewards_compounded, 10_000);
+    assert_eq!(report.users["alice"].staked_amount, 1_010_000);
+
+    // Same-mint pools keep stake and rewards in one vault.
+    let last = report.timeline.last().unwrap();
+    assert_eq!(last.reward_vault, 3_000_000);
+    assert_eq!(last.stake_vault, 3_000_000);
+    assert!(last.solvent);
+}
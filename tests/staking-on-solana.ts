@@ -45,6 +45,8 @@ describe("staking-on-solana", () => {
   let user2;
   const deploy_fee = new BN(0.8 * LAMPORTS_PER_SOL); // Fixed SOL in lamports
   const performance_fee = new BN(0.05 * LAMPORTS_PER_SOL); // Fixed SOL in lamports
+  const treasury_fee_share = 0; // Basis points of stake/unstake fees
+  const reward_performance_fee = 0; // Basis points of rewards, for reward-share pools
 
   before(async () => {
     // Create treasury wallet
@@ -59,7 +61,9 @@ describe("staking-on-solana", () => {
     const tx = await program.methods
       .initialize(
         deploy_fee,
-        performance_fee
+        performance_fee,
+        treasury_fee_share,
+        reward_performance_fee
       )
       .accounts({
         platform: platform_info_pda,
@@ -331,7 +335,7 @@ describe("staking-on-solana", () => {
     const unstakeAmount = new BN(10000);
 
     await program.methods
-      .unstake(unstakeAmount)
+      .unstake(unstakeAmount, 0)
       .accounts({
         user: user1.publicKey,
         admin: admin.publicKey,
@@ -340,12 +344,20 @@ describe("staking-on-solana", () => {
         poolStateAccount: selected_pool.account.stateAddr,
         platform: platform_info_pda,
         userInfo: userInfoPDA,
+        withdrawalTicket: null,
+        referrerInfo: null,
         userStakeTokenVault: userStakeTokenVault.address,
         userRewardTokenVault: userRewardTokenVault.address,
         poolStakeTokenVault: selected_pool.account.poolStakeTokenVault,
         poolRewardTokenVault: selected_pool.account.poolRewardTokenVault,
         creatorStakeTokenVault: creatorStakeTokenVault.address,
         treasuryStakeTokenVault: treasuryStakeTokenVault.address,
+        treasuryRewardTokenVault: null,
+        poolFeeExemption: null,
+        platformFeeExemption: null,
+        stakeMint: selected_pool.account.stakeMint,
+        rewardMint: selected_pool.account.rewardMint,
+        wsolAccount: null,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
       .signers([admin.payer, user1])
@@ -432,17 +444,23 @@ describe("staking-on-solana", () => {
     );
 
     await program.methods
-      .claimReward()
+      .claimReward(0)
       .accounts({
         claimer: user1.publicKey,
         admin: admin.publicKey,
         treasury: treasury.publicKey,
         userInfo: userInfoPDA,
+        referrerInfo: null,
         poolConfigAccount: selected_pool.publicKey,
         poolStateAccount: selected_pool.account.stateAddr,
         platform: platform_info_pda,
         userRewardTokenVault: userRewardTokenVault.address,
         poolRewardTokenVault: selected_pool.account.poolRewardTokenVault,
+        treasuryRewardTokenVault: null,
+        poolFeeExemption: null,
+        platformFeeExemption: null,
+        rewardMint: selected_pool.account.rewardMint,
+        wsolAccount: null,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
       .signers([admin.payer, user1])
@@ -514,7 +532,7 @@ describe("staking-on-solana", () => {
     );
 
     await program.methods
-      .compoundReward()
+      .compoundReward(0)
       .accounts({
         user: user1.publicKey,
         admin: admin.publicKey,
@@ -525,6 +543,10 @@ describe("staking-on-solana", () => {
         platform: platform_info_pda,
         poolStakeTokenVault: selected_pool.account.poolStakeTokenVault,
         poolRewardTokenVault: selected_pool.account.poolRewardTokenVault,
+        treasuryRewardTokenVault: null,
+        poolFeeExemption: null,
+        platformFeeExemption: null,
+        rewardMint: selected_pool.account.rewardMint,
         // treasuryStakeTokenVault: treasuryStakeTokenVault.address,
         tokenProgram: TOKEN_PROGRAM_ID,
         // raydium swap
@@ -628,7 +650,8 @@ describe("staking-on-solana", () => {
         unstakeFee,
         fundingAmount,
         rewardPerSlot,
-        duration
+        duration,
+        { flat: {} }
       )
       .accounts({
         poolConfigAccount: POOL_CONFIG_PDA,
@@ -643,6 +666,8 @@ describe("staking-on-solana", () => {
         // poolStakeTokenVault: poolStakeTokenVault,
         // poolRewardTokenVault: poolRewardTokenVault,
         creatorRewardTokenVault: creatorRewardTokenVault.address,
+        stakeHookAllowance: null,
+        rewardHookAllowance: null,
         systemProgram: SystemProgram.programId,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
@@ -724,21 +749,28 @@ describe("staking-on-solana", () => {
 
     console.log("stakeAmount", stakeAmount.toString())
     await program.methods
-      .stake(stakeAmount)
+      .stake(stakeAmount, stakeAmount, null, 0, 0, null)
       .accounts({
         staker: user.publicKey,
         admin: admin.publicKey,
         treasury: treasury.publicKey,
         platform: platform_info_pda,
         userInfo: userInfoPDA,
+        referrerInfo: null,
         userStakeTokenVault: userStakeTokenVault.address,
         userRewardTokenVault: userRewardTokenVault.address,
         poolStakeTokenVault: pool_config.account.poolStakeTokenVault,
         poolRewardTokenVault: pool_config.account.poolRewardTokenVault,
         creatorStakeTokenVault: creatorStakeTokenVault.address,
         treasuryStakeTokenVault: treasuryStakeTokenVault.address,
+        treasuryRewardTokenVault: null,
+        poolFeeExemption: null,
+        platformFeeExemption: null,
         poolConfigAccount: pool_config.publicKey,
         poolStateAccount: pool_config.account.stateAddr,
+        stakeMint: pool_config.account.stakeMint,
+        rewardMint: pool_config.account.rewardMint,
+        wsolAccount: null,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
       .signers([user])